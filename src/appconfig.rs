@@ -0,0 +1,714 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use crate::terminal::Wakeup;
+
+// ---------------------------------------------------------------------------
+// Data model
+// ---------------------------------------------------------------------------
+
+/// Line endings used for pasted text.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PasteLineEnding {
+    /// Send the clipboard text unchanged.
+    Keep,
+    /// Convert CRLF / LF to CR (what the Enter key sends).
+    Cr,
+    /// Convert CRLF / CR to LF.
+    Lf,
+}
+
+impl PasteLineEnding {
+    pub const ALL: [PasteLineEnding; 3] = [
+        PasteLineEnding::Keep,
+        PasteLineEnding::Cr,
+        PasteLineEnding::Lf,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PasteLineEnding::Keep => "Keep",
+            PasteLineEnding::Cr => "CR",
+            PasteLineEnding::Lf => "LF",
+        }
+    }
+}
+
+/// What happens to control characters, U+FFFD and (optionally) box drawing
+/// in copied text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CopyFilter {
+    /// Copy cell characters as they are.
+    #[default]
+    Off,
+    /// Replace filtered characters with spaces (keeps columns aligned).
+    Replace,
+    /// Drop filtered characters.
+    Drop,
+}
+
+impl CopyFilter {
+    pub const ALL: [CopyFilter; 3] = [CopyFilter::Off, CopyFilter::Replace, CopyFilter::Drop];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CopyFilter::Off => "Off",
+            CopyFilter::Replace => "Replace with spaces",
+            CopyFilter::Drop => "Drop",
+        }
+    }
+}
+
+/// What plain Ctrl+C does. Ctrl+Shift+C always copies.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CtrlCBehavior {
+    /// Copy (and clear) the selection if there is one, otherwise send ^C.
+    #[default]
+    CopyWhenSelected,
+    /// Always send ^C to the shell.
+    AlwaysInterrupt,
+}
+
+impl CtrlCBehavior {
+    pub const ALL: [CtrlCBehavior; 2] = [
+        CtrlCBehavior::CopyWhenSelected,
+        CtrlCBehavior::AlwaysInterrupt,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CtrlCBehavior::CopyWhenSelected => "Copy if selected, else interrupt",
+            CtrlCBehavior::AlwaysInterrupt => "Always interrupt",
+        }
+    }
+}
+
+/// Cursor shape used until a program picks one with DECSCUSR (`ESC[<n> q`).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CursorShape {
+    #[default]
+    Block,
+    Underline,
+    Bar,
+}
+
+impl CursorShape {
+    pub const ALL: [CursorShape; 3] =
+        [CursorShape::Block, CursorShape::Underline, CursorShape::Bar];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CursorShape::Block => "Block",
+            CursorShape::Underline => "Underline",
+            CursorShape::Bar => "Bar",
+        }
+    }
+}
+
+/// When closing the window asks for confirmation first.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CloseConfirm {
+    Always,
+    /// Only while a command runs or output just arrived. Shells without
+    /// prompt integration always ask, since busy and idle look the same.
+    #[default]
+    WhileBusy,
+    Never,
+}
+
+impl CloseConfirm {
+    pub const ALL: [CloseConfirm; 3] = [
+        CloseConfirm::Always,
+        CloseConfirm::WhileBusy,
+        CloseConfirm::Never,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CloseConfirm::Always => "Always",
+            CloseConfirm::WhileBusy => "While a command runs",
+            CloseConfirm::Never => "Never",
+        }
+    }
+}
+
+/// How typed and pasted input appears in the DevTools VT Stream log.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputLog {
+    /// One `•` per typed character; Enter, Tab and escapes stay readable.
+    #[default]
+    Masked,
+    Off,
+    /// Exactly what was sent, passwords included; for debugging.
+    Raw,
+}
+
+impl InputLog {
+    pub const ALL: [InputLog; 3] = [InputLog::Masked, InputLog::Off, InputLog::Raw];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InputLog::Masked => "Masked",
+            InputLog::Off => "Off",
+            InputLog::Raw => "Raw (shows passwords)",
+        }
+    }
+}
+
+/// How the background image is sized to the terminal area.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BackgroundFit {
+    /// Fill the area, cropping the image's overflowing side.
+    #[default]
+    Cover,
+    /// Show the whole image, centered, leaving bands of terminal background.
+    Contain,
+    /// Repeat the image at its native size from the top-left.
+    Tile,
+}
+
+impl BackgroundFit {
+    pub const ALL: [BackgroundFit; 3] = [
+        BackgroundFit::Cover,
+        BackgroundFit::Contain,
+        BackgroundFit::Tile,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            BackgroundFit::Cover => "Cover",
+            BackgroundFit::Contain => "Contain",
+            BackgroundFit::Tile => "Tile",
+        }
+    }
+}
+
+/// Whether cursor blink, the startup animation and animated scrolling are
+/// turned off.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReduceMotion {
+    /// Follow the OS "show animations" setting where it can be read.
+    #[default]
+    System,
+    On,
+    Off,
+}
+
+impl ReduceMotion {
+    pub const ALL: [ReduceMotion; 3] = [ReduceMotion::System, ReduceMotion::On, ReduceMotion::Off];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ReduceMotion::System => "Follow system",
+            ReduceMotion::On => "On",
+            ReduceMotion::Off => "Off",
+        }
+    }
+
+    /// True when motion should be reduced; `System` asks the OS each call.
+    pub fn resolve(self) -> bool {
+        match self {
+            ReduceMotion::System => system_reduces_motion(),
+            ReduceMotion::On => true,
+            ReduceMotion::Off => false,
+        }
+    }
+}
+
+/// Windows' "Show animations in Windows" accessibility switch.
+#[cfg(windows)]
+fn system_reduces_motion() -> bool {
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        SystemParametersInfoW, SPI_GETCLIENTAREAANIMATION,
+    };
+    let mut enabled: i32 = 1;
+    let ok = unsafe {
+        SystemParametersInfoW(
+            SPI_GETCLIENTAREAANIMATION,
+            0,
+            &mut enabled as *mut i32 as *mut _,
+            0,
+        )
+    };
+    ok != 0 && enabled == 0
+}
+
+#[cfg(not(windows))]
+fn system_reduces_motion() -> bool {
+    false
+}
+
+/// Swap chain presentation mode requested from the GPU surface.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentMode {
+    /// Vsync; always supported.
+    Fifo,
+    /// Vsync without blocking; newest frame wins.
+    Mailbox,
+    /// No vsync; may tear.
+    Immediate,
+}
+
+impl PresentMode {
+    pub const ALL: [PresentMode; 3] = [
+        PresentMode::Fifo,
+        PresentMode::Mailbox,
+        PresentMode::Immediate,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PresentMode::Fifo => "Fifo (vsync)",
+            PresentMode::Mailbox => "Mailbox",
+            PresentMode::Immediate => "Immediate",
+        }
+    }
+}
+
+/// How the PowerShell prompt is hooked to report the working directory (OSC 633).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PromptIntegration {
+    /// Keep the user's prompt and emit the CWD sequence before it.
+    #[default]
+    Wrap,
+    /// Replace the prompt with a plain `PS <dir>> `.
+    Replace,
+    /// Leave the prompt alone; CWD tracking is disabled.
+    Off,
+}
+
+impl PromptIntegration {
+    pub const ALL: [PromptIntegration; 3] = [
+        PromptIntegration::Wrap,
+        PromptIntegration::Replace,
+        PromptIntegration::Off,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            PromptIntegration::Wrap => "Wrap existing prompt",
+            PromptIntegration::Replace => "Replace prompt",
+            PromptIntegration::Off => "Off",
+        }
+    }
+}
+
+/// Frame-rate caps offered in the settings UI; 0 means uncapped.
+pub const MAX_FPS_CHOICES: [u32; 5] = [0, 30, 60, 120, 144];
+
+/// Auto-close delays (seconds after the shell exits) offered in the settings UI.
+pub const CLOSE_DELAY_CHOICES: [u32; 5] = [0, 3, 5, 10, 30];
+
+/// Idle minutes before auto-lock offered in the settings UI; 0 disables it.
+pub const AUTO_LOCK_CHOICES: [u32; 5] = [0, 5, 10, 15, 30];
+
+pub const DEFAULT_SELECTION_COLOR: [u8; 3] = [180, 180, 180];
+pub const DEFAULT_CURSOR_COLOR: [u8; 3] = [204, 204, 204];
+
+/// Characters besides whitespace that end a double-click word selection.
+/// `/`, `.`, `-` and `_` are left out so paths and URLs select whole.
+pub const DEFAULT_WORD_SEPARATORS: &str = ",;:|`'\"()[]{}<>";
+
+/// A named launch setup: shell, environment, startup directory and colors.
+/// Empty fields keep what terminrt would use without a profile.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    /// Shell executable, e.g. `pwsh.exe` or `wsl.exe`.
+    pub shell: String,
+    /// Startup directory.
+    pub cwd: String,
+    /// Variables added to (or overriding) the inherited environment.
+    pub env: BTreeMap<String, String>,
+    /// Default text and background colors (sRGB); programs may still change
+    /// them with OSC 10/11.
+    pub foreground: Option<[u8; 3]>,
+    pub background: Option<[u8; 3]>,
+}
+
+/// Copy size limits in bytes offered in the settings UI; the terminal caps
+/// any configured value at 64 MiB.
+pub const MAX_COPY_BYTES_CHOICES: [usize; 4] = [
+    1024 * 1024,
+    2 * 1024 * 1024,
+    16 * 1024 * 1024,
+    64 * 1024 * 1024,
+];
+
+/// Tab stop widths offered in the settings UI.
+pub const TAB_WIDTH_CHOICES: [usize; 3] = [2, 4, 8];
+
+/// PTY read sizes in bytes offered in the settings UI.
+pub const PTY_READ_BUFFER_CHOICES: [usize; 3] = [4 * 1024, 16 * 1024, 64 * 1024];
+/// PTY output queue lengths (in reads) offered in the settings UI.
+pub const PTY_QUEUE_CHOICES: [usize; 4] = [16, 64, 256, 1024];
+/// Largest initial row or column count the settings UI accepts.
+pub const MAX_INITIAL_GRID: u16 = 500;
+/// Largest terminal padding (points) the settings UI accepts.
+pub const MAX_TERMINAL_PADDING: u8 = 48;
+
+/// Application-wide preferences (everything that is not a quick command).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    /// If true, Alt+key sends ESC followed by the key (Meta behavior).
+    pub alt_sends_esc: bool,
+    /// Run quick commands from their key bindings; off passes every key
+    /// through to the terminal.
+    pub quickcmd_keybindings: bool,
+    /// Draw typed characters before the PTY echoes them, for laggy remote
+    /// sessions. Predictions the echo does not confirm are dropped.
+    pub local_echo: bool,
+    /// When the cursor line looks like a password prompt, skip local echo and
+    /// keep what is typed out of the VT log.
+    pub password_guard: bool,
+    /// Line ending normalization applied to pasted text.
+    pub paste_line_ending: PasteLineEnding,
+    /// Drop trailing newlines from pasted text so a paste never executes.
+    pub paste_strip_trailing_newline: bool,
+    /// Wrap pastes in bracketed-paste markers when the program enables the
+    /// mode. Off pastes literally, for programs that mishandle the markers.
+    pub bracketed_paste: bool,
+    /// Filtering applied to copied selections.
+    pub copy_filter: CopyFilter,
+    /// Also filter box-drawing and block characters when `copy_filter` is on.
+    pub copy_filter_box_drawing: bool,
+    /// Characters besides whitespace that end a double-click word selection.
+    pub word_separators: String,
+    /// Copy a word to the clipboard as soon as a double-click selects it.
+    pub copy_on_double_click: bool,
+    /// Largest copy in bytes; longer selections are cut off with a notice.
+    pub max_copy_bytes: usize,
+    /// Whether Ctrl+C copies a selection or always interrupts.
+    pub ctrl_c: CtrlCBehavior,
+    /// Preferred presentation mode; falls back to Fifo if unsupported.
+    pub present_mode: PresentMode,
+    /// Software frame cap in frames per second (0 = uncapped).
+    pub max_fps: u32,
+    /// PowerShell prompt hook used for CWD tracking; applies to new sessions.
+    pub prompt_integration: PromptIntegration,
+    /// Regex matched at the start of a line to spot prompts when the shell
+    /// sends no OSC 633 marks (empty = off). Best effort.
+    pub prompt_regex: String,
+    /// Record executed commands per directory (opt-in; stays on this machine).
+    pub record_command_history: bool,
+    /// How typed and pasted bytes are recorded in the DevTools VT Stream log.
+    pub input_log: InputLog,
+    /// Columns between default tab stops in the emulator.
+    pub tab_width: usize,
+    /// Keep the window (and the last screen) open after the shell exits.
+    pub hold_open_on_exit: bool,
+    /// On reconnect, keep the previous session's output as scrollback above
+    /// the new one, under a "session restarted" line.
+    pub keep_history_on_reconnect: bool,
+    /// With `hold_open_on_exit` off, seconds to wait before closing.
+    pub close_delay_secs: u32,
+    /// Flash the taskbar entry when the bell rings while the window is in the
+    /// background.
+    pub bell_attention: bool,
+    /// Report quick commands marked "notify when done" once they finish, in
+    /// the status bar and by flashing the taskbar entry when in the background.
+    pub quickcmd_finish_alerts: bool,
+    /// Minutes without keyboard input before the terminal switches to
+    /// read-only (0 = never).
+    pub auto_lock_mins: u32,
+    /// When closing the window asks first.
+    pub confirm_close: CloseConfirm,
+    /// Selection highlight (sRGB); the text color is picked for contrast.
+    pub selection_color: [u8; 3],
+    /// Block cursor color (sRGB); the text color is picked for contrast.
+    pub cursor_color: [u8; 3],
+    /// Default cursor shape and blinking; programs may override both with
+    /// DECSCUSR until they reset it (`ESC[0 q`).
+    pub cursor_shape: CursorShape,
+    pub cursor_blink: bool,
+    /// Space (points) between the terminal text and the panel's left and
+    /// right edges; the status bars already space it vertically.
+    pub terminal_padding: u8,
+    /// Show the left side panel (Settings and DevTools buttons); Ctrl+Shift+B
+    /// toggles it.
+    pub show_left_panel: bool,
+    /// Image drawn behind the terminal text (empty = none). Cells with an
+    /// explicit background color still paint over it.
+    pub background_image: String,
+    /// Background image opacity, in percent.
+    pub background_opacity: u8,
+    pub background_fit: BackgroundFit,
+    /// Animate programmatic terminal scrolls; off snaps instantly.
+    pub animate_scrolling: bool,
+    /// Steady cursor, instant scrolls and no startup animation; by default
+    /// follows the OS preference.
+    pub reduce_motion: ReduceMotion,
+    /// List the active terminal input modes in the bottom status bar.
+    pub show_terminal_modes: bool,
+    /// Show a spinner and elapsed time in the status bar while a command runs
+    /// (needs shell integration).
+    pub show_running_command: bool,
+    /// Show how long the current session has been up in the status bar.
+    pub show_session_uptime: bool,
+    /// Ctrl+Shift+L briefly highlights the cursor's row and column.
+    pub cursor_locator: bool,
+    /// Bytes requested per PTY read; applies to new sessions.
+    pub pty_read_buffer_size: usize,
+    /// PTY reads queued for the UI before the reader blocks; applies to new
+    /// sessions. Queued output is bounded by this times the read size.
+    pub pty_queue_capacity: usize,
+    /// Rows the first session starts with; 0 fits the window. A set value
+    /// also sizes the window for it at startup.
+    pub initial_rows: u16,
+    /// Columns the first session starts with; 0 fits the window.
+    pub initial_cols: u16,
+    /// Resize the window when a program asks for a grid size with XTWINOPS
+    /// (`ESC[8;rows;cols t`); off ignores the request.
+    pub allow_resize_requests: bool,
+    /// Saved launch setups, picked with `--profile NAME` or at startup.
+    pub profiles: Vec<Profile>,
+    /// Profile launched when `--profile` is not given; empty asks on the
+    /// startup page (if any profiles exist).
+    pub default_profile: String,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            alt_sends_esc: true,
+            quickcmd_keybindings: true,
+            local_echo: false,
+            password_guard: true,
+            paste_line_ending: PasteLineEnding::Cr,
+            paste_strip_trailing_newline: false,
+            bracketed_paste: true,
+            copy_filter: CopyFilter::Off,
+            copy_filter_box_drawing: false,
+            word_separators: DEFAULT_WORD_SEPARATORS.to_string(),
+            copy_on_double_click: false,
+            max_copy_bytes: 2 * 1024 * 1024,
+            ctrl_c: CtrlCBehavior::CopyWhenSelected,
+            present_mode: PresentMode::Fifo,
+            max_fps: 0,
+            prompt_integration: PromptIntegration::Wrap,
+            prompt_regex: String::new(),
+            record_command_history: false,
+            input_log: InputLog::Masked,
+            tab_width: 8,
+            hold_open_on_exit: true,
+            keep_history_on_reconnect: false,
+            close_delay_secs: 5,
+            auto_lock_mins: 0,
+            bell_attention: true,
+            quickcmd_finish_alerts: false,
+            confirm_close: CloseConfirm::WhileBusy,
+            selection_color: DEFAULT_SELECTION_COLOR,
+            cursor_color: DEFAULT_CURSOR_COLOR,
+            cursor_shape: CursorShape::Block,
+            cursor_blink: true,
+            terminal_padding: 8,
+            show_left_panel: true,
+            background_image: String::new(),
+            background_opacity: 30,
+            background_fit: BackgroundFit::Cover,
+            animate_scrolling: true,
+            reduce_motion: ReduceMotion::System,
+            show_terminal_modes: false,
+            show_running_command: true,
+            show_session_uptime: true,
+            cursor_locator: false,
+            pty_read_buffer_size: 4 * 1024,
+            pty_queue_capacity: 256,
+            initial_rows: 0,
+            initial_cols: 0,
+            allow_resize_requests: false,
+            profiles: Vec::new(),
+            default_profile: String::new(),
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Config persistence
+// ---------------------------------------------------------------------------
+
+/// How often the watcher polls `settings.json` for outside edits.
+const WATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// JSON most recently written by `save_config`. The watcher skips files with
+/// exactly this content so the app's own saves are not reloaded.
+static LAST_SAVED: Mutex<String> = Mutex::new(String::new());
+
+fn config_path() -> PathBuf {
+    let base = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+    base.join("terminrt").join("settings.json")
+}
+
+/// Where the original file is copied when it could not be loaded intact.
+fn backup_path() -> PathBuf {
+    config_path().with_extension("json.bak")
+}
+
+/// Load the preferences. A file that does not parse as a whole is loaded
+/// leniently: every setting that still deserializes is kept, the others fall
+/// back to their defaults, the original is copied to `settings.json.bak`, and
+/// the returned message names what was reset.
+pub fn load_config() -> (AppConfig, Option<String>) {
+    let path = config_path();
+    if !path.exists() {
+        return (AppConfig::default(), None);
+    }
+    let data = match std::fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(e) => {
+            return (
+                AppConfig::default(),
+                Some(format!("Cannot read {}: {}", path.display(), e)),
+            );
+        }
+    };
+    let strict_error = match serde_json::from_str::<AppConfig>(&data) {
+        Ok(config) => return (config, None),
+        Err(e) => e,
+    };
+
+    let backup = backup_path();
+    let backup_note = match std::fs::write(&backup, &data) {
+        Ok(()) => format!("The original was saved to {}.", backup.display()),
+        Err(e) => format!("Backing it up to {} failed: {}.", backup.display(), e),
+    };
+    let Ok(serde_json::Value::Object(fields)) = serde_json::from_str(&data) else {
+        // Not a JSON object at all; nothing can be salvaged.
+        return (
+            AppConfig::default(),
+            Some(format!(
+                "{} is not a valid settings file ({}); all settings are at their defaults. {}",
+                path.display(),
+                strict_error,
+                backup_note
+            )),
+        );
+    };
+
+    let (config, reset) = salvage_settings(fields);
+    let message = if reset.is_empty() {
+        format!(
+            "{} did not load cleanly ({}). {}",
+            path.display(),
+            strict_error,
+            backup_note
+        )
+    } else {
+        format!(
+            "{} settings in {} could not be loaded and were reset to defaults: {}. {}",
+            reset.len(),
+            path.display(),
+            reset.join(", "),
+            backup_note
+        )
+    };
+    (config, Some(message))
+}
+
+/// Lay the file's settings over the defaults one at a time, keeping each one
+/// the config still deserializes with. Returns the config and the keys that
+/// were dropped.
+fn salvage_settings(
+    fields: serde_json::Map<String, serde_json::Value>,
+) -> (AppConfig, Vec<String>) {
+    let mut merged = match serde_json::to_value(AppConfig::default()) {
+        Ok(serde_json::Value::Object(defaults)) => defaults,
+        _ => serde_json::Map::new(),
+    };
+    let mut reset = Vec::new();
+    for (key, value) in fields {
+        let mut candidate = merged.clone();
+        candidate.insert(key.clone(), value);
+        let candidate = serde_json::Value::Object(candidate);
+        if serde_json::from_value::<AppConfig>(candidate.clone()).is_ok() {
+            if let serde_json::Value::Object(candidate) = candidate {
+                merged = candidate;
+            }
+        } else {
+            reset.push(key);
+        }
+    }
+    let config = serde_json::from_value(serde_json::Value::Object(merged)).unwrap_or_default();
+    (config, reset)
+}
+
+pub fn save_config(config: &AppConfig) {
+    let path = config_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string_pretty(config) {
+        // Hold the lock across the write so the watcher never sees the new file
+        // before it knows the content came from us.
+        let mut last_saved = LAST_SAVED.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = std::fs::write(&path, &json);
+        *last_saved = json;
+    }
+}
+
+fn modified_time(path: &std::path::Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// Poll `settings.json` on a background thread and send every version edited
+/// outside the app (e.g. in a text editor). Files that fail to parse are
+/// reported on stderr and skipped, so a half-typed edit never resets the live
+/// settings. `wakeup` is called after each queued config.
+pub fn spawn_config_watcher(wakeup: Wakeup) -> mpsc::Receiver<AppConfig> {
+    let (tx, rx) = mpsc::channel::<AppConfig>();
+    thread::spawn(move || {
+        let path = config_path();
+        let mut last_modified = modified_time(&path);
+        loop {
+            thread::sleep(WATCH_INTERVAL);
+            let modified = modified_time(&path);
+            if modified.is_none() || modified == last_modified {
+                continue;
+            }
+            last_modified = modified;
+
+            let data = {
+                let last_saved = LAST_SAVED.lock().unwrap_or_else(|e| e.into_inner());
+                match std::fs::read_to_string(&path) {
+                    Ok(data) if data != *last_saved => data,
+                    _ => continue,
+                }
+            };
+            match serde_json::from_str::<AppConfig>(&data) {
+                Ok(config) => {
+                    if tx.send(config).is_err() {
+                        break;
+                    }
+                    wakeup();
+                }
+                Err(e) => eprintln!("Ignoring invalid {}: {}", path.display(), e),
+            }
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_wrong_typed_setting_only_resets_itself() {
+        let data = r#"{"max_copy_bytes": "lots", "local_echo": true, "initial_cols": 100}"#;
+        assert!(serde_json::from_str::<AppConfig>(data).is_err());
+        let Ok(serde_json::Value::Object(fields)) = serde_json::from_str(data) else {
+            panic!("not an object");
+        };
+        let (config, reset) = salvage_settings(fields);
+        assert_eq!(reset, ["max_copy_bytes"]);
+        assert_eq!(config.max_copy_bytes, AppConfig::default().max_copy_bytes);
+        assert!(config.local_echo);
+        assert_eq!(config.initial_cols, 100);
+    }
+}
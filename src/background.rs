@@ -0,0 +1,115 @@
+use std::sync::mpsc;
+use std::thread;
+
+use egui::{self, Color32, Pos2, Rect};
+
+use crate::appconfig::BackgroundFit;
+
+/// Longest side (pixels) uploaded to the GPU; larger images are scaled down
+/// so the texture stays within every adapter's limits.
+const MAX_TEXTURE_SIDE: u32 = 4096;
+
+/// Image painted behind the terminal text. Decoding runs on a worker thread,
+/// so picking a large file never stalls a frame; once loaded, drawing it is a
+/// single textured rectangle.
+#[derive(Default)]
+pub struct BackgroundImage {
+    /// Path the texture (or pending load) belongs to; empty when none is set.
+    path: String,
+    texture: Option<egui::TextureHandle>,
+    pending: Option<mpsc::Receiver<Result<egui::ColorImage, String>>>,
+}
+
+impl BackgroundImage {
+    /// Starts loading `path` when it differs from the current one and picks up
+    /// a finished load. An empty path drops the image.
+    pub fn sync(&mut self, ctx: &egui::Context, path: &str) {
+        if path != self.path {
+            self.path = path.to_string();
+            self.texture = None;
+            self.pending = None;
+            if !path.is_empty() {
+                let (tx, rx) = mpsc::channel();
+                let path = path.to_string();
+                let ctx = ctx.clone();
+                thread::spawn(move || {
+                    let _ = tx.send(decode(&path));
+                    ctx.request_repaint();
+                });
+                self.pending = Some(rx);
+            }
+        }
+
+        let Some(rx) = &self.pending else { return };
+        match rx.try_recv() {
+            Ok(Ok(image)) => {
+                self.texture = Some(ctx.load_texture(
+                    "terminal-background",
+                    image,
+                    egui::TextureOptions::LINEAR_REPEAT,
+                ));
+                self.pending = None;
+            }
+            Ok(Err(e)) => {
+                eprintln!("Failed to load background image {}: {}", self.path, e);
+                self.pending = None;
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => self.pending = None,
+        }
+    }
+
+    /// Paints the image into `rect` at `opacity` percent; does nothing until
+    /// a texture is loaded.
+    pub fn paint(&self, ui: &egui::Ui, rect: Rect, fit: BackgroundFit, opacity: u8) {
+        let Some(texture) = &self.texture else { return };
+        if opacity == 0 || rect.width() <= 0.0 || rect.height() <= 0.0 {
+            return;
+        }
+        let alpha = (opacity.min(100) as u32 * 255 / 100) as u8;
+        let tint = Color32::from_white_alpha(alpha);
+        let [tex_w, tex_h] = texture.size().map(|v| v as f32);
+        let (dest, uv) = match fit {
+            BackgroundFit::Cover => {
+                // Crop the image's overflowing axis around its center.
+                let scale = (rect.width() / tex_w).max(rect.height() / tex_h);
+                let uv_w = rect.width() / (tex_w * scale);
+                let uv_h = rect.height() / (tex_h * scale);
+                let min = Pos2::new((1.0 - uv_w) / 2.0, (1.0 - uv_h) / 2.0);
+                (rect, Rect::from_min_size(min, egui::vec2(uv_w, uv_h)))
+            }
+            BackgroundFit::Contain => {
+                let scale = (rect.width() / tex_w).min(rect.height() / tex_h);
+                let size = egui::vec2(tex_w * scale, tex_h * scale);
+                let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+                (Rect::from_center_size(rect.center(), size), uv)
+            }
+            BackgroundFit::Tile => {
+                // One image pixel per screen pixel, repeated from the top-left.
+                let ppp = ui.ctx().pixels_per_point();
+                let uv_max = Pos2::new(rect.width() * ppp / tex_w, rect.height() * ppp / tex_h);
+                (rect, Rect::from_min_max(Pos2::ZERO, uv_max))
+            }
+        };
+        ui.painter().image(texture.id(), dest, uv, tint);
+    }
+}
+
+/// Reads and decodes `path` into an sRGBA image no larger than
+/// `MAX_TEXTURE_SIDE` on either side.
+fn decode(path: &str) -> Result<egui::ColorImage, String> {
+    let mut image = image::open(path).map_err(|e| e.to_string())?;
+    if image.width() > MAX_TEXTURE_SIDE || image.height() > MAX_TEXTURE_SIDE {
+        image = image.resize(
+            MAX_TEXTURE_SIDE,
+            MAX_TEXTURE_SIDE,
+            image::imageops::FilterType::Triangle,
+        );
+    }
+    let rgba = image.to_rgba8();
+    let size = [rgba.width() as usize, rgba.height() as usize];
+    Ok(egui::ColorImage::from_rgba_unmultiplied(
+        size,
+        rgba.as_raw(),
+    ))
+}
@@ -0,0 +1,164 @@
+use std::io::{self, Cursor, Write};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::pty::{MemoryPty, ShellConfig};
+use crate::search::SearchState;
+use crate::terminal::{self, TerminalInstance};
+
+/// Payload size when `--bench-feed` is given without one.
+pub const DEFAULT_PAYLOAD_MIB: usize = 16;
+const GRID_ROWS: u16 = 50;
+const GRID_COLS: u16 = 160;
+/// Logical size of the headless egui screen; roomy enough for the grid above.
+const SCREEN_SIZE: egui::Vec2 = egui::vec2(1600.0, 1000.0);
+
+/// Feed `mib` MiB of generated ANSI-heavy output through a `TerminalInstance`
+/// and print how long parsing and rendering took.
+///
+/// Mirrors the event loop without a window: every frame drains the queue with
+/// `process_input`, then lays the terminal out with `render_terminal` and
+/// tessellates it. GPU work is not included. The payload is the same for a
+/// given size, so runs can be compared across builds; the PTY read size and
+/// queue length come from the settings like a real session.
+pub fn run(mib: usize, shell_config: &ShellConfig) {
+    let payload = generate_payload(mib * 1024 * 1024);
+    let payload_len = payload.len();
+    let mut terminal = TerminalInstance::with_backend(
+        GRID_ROWS,
+        GRID_COLS,
+        PathBuf::new(),
+        MemoryPty::new(Cursor::new(payload)),
+        shell_config,
+        Arc::new(|| {}),
+    );
+
+    let ctx = egui::Context::default();
+    let mut selection = terminal::TerminalSelectionState::default();
+    let mut render_cache = terminal::TerminalRenderCache::default();
+    let mut search = SearchState::default();
+    let highlight = terminal::HighlightColors::new([60, 90, 150], [200, 200, 200]);
+
+    let mut parse_time = Duration::ZERO;
+    let mut render_time = Duration::ZERO;
+    let mut frames = 0usize;
+    let started = Instant::now();
+    loop {
+        let parse_start = Instant::now();
+        let result = terminal.process_input();
+        parse_time += parse_start.elapsed();
+        if result.pty_closed {
+            break;
+        }
+        if !result.had_input {
+            // The reader thread has not queued the next chunk yet.
+            std::thread::yield_now();
+            continue;
+        }
+
+        let render_start = Instant::now();
+        let raw_input = egui::RawInput {
+            screen_rect: Some(egui::Rect::from_min_size(egui::Pos2::ZERO, SCREEN_SIZE)),
+            ..Default::default()
+        };
+        let output = ctx.run(raw_input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                terminal::render_terminal(
+                    ui,
+                    Some(&terminal),
+                    &mut selection,
+                    false,
+                    None,
+                    0,
+                    None,
+                    &mut render_cache,
+                    &mut search,
+                    highlight,
+                    false,
+                    &mut None,
+                    0.0,
+                    false,
+                );
+            });
+        });
+        let primitives = ctx.tessellate(output.shapes, output.pixels_per_point);
+        std::hint::black_box(primitives);
+        render_time += render_start.elapsed();
+        frames += 1;
+    }
+    let total = started.elapsed();
+
+    let mib = payload_len as f64 / (1024.0 * 1024.0);
+    let mib_per_sec = |time: Duration| mib / time.as_secs_f64();
+    let mut out = io::stdout().lock();
+    let _ = writeln!(
+        out,
+        "bench-feed: {:.1} MiB, {}x{} grid, {} B reads, queue {}",
+        mib, GRID_COLS, GRID_ROWS, shell_config.read_buffer_size, shell_config.queue_capacity,
+    );
+    let _ = writeln!(
+        out,
+        "  total   {:>8.1} ms  {:>7.1} MiB/s",
+        total.as_secs_f64() * 1000.0,
+        mib_per_sec(total),
+    );
+    let _ = writeln!(
+        out,
+        "  parse   {:>8.1} ms  {:>7.1} MiB/s",
+        parse_time.as_secs_f64() * 1000.0,
+        mib_per_sec(parse_time),
+    );
+    let _ = writeln!(
+        out,
+        "  render  {:>8.1} ms  {} frames, {:.2} ms/frame",
+        render_time.as_secs_f64() * 1000.0,
+        frames,
+        render_time.as_secs_f64() * 1000.0 / frames.max(1) as f64,
+    );
+}
+
+/// Deterministic mix of what busy programs print: 256-color and truecolor
+/// SGR runs, bold/underline, wide and box-drawing characters, progress lines
+/// redrawn with `\r` + EL, and absolute cursor moves. At least `len` bytes.
+fn generate_payload(len: usize) -> Vec<u8> {
+    let mut out = Vec::with_capacity(len + 1024);
+    let mut line = 0usize;
+    while out.len() < len {
+        let n = line % 256;
+        match line % 8 {
+            0..=3 => {
+                for word in 0..10 {
+                    let color = (n + word * 23) % 256;
+                    let _ = write!(out, "\x1b[38;5;{}mword{:03}\x1b[0m ", color, color);
+                }
+                let _ = write!(out, "\x1b[1;4mbold\x1b[22;24m │ 终端 ✓\r\n");
+            }
+            4 | 5 => {
+                for cell in 0..16 {
+                    let (r, g, b) = ((n + cell * 16) % 256, (cell * 16) % 256, (255 - n) % 256);
+                    let _ = write!(out, "\x1b[48;2;{};{};{}m  ", r, g, b);
+                }
+                let _ = write!(out, "\x1b[0m line {}\r\n", line);
+            }
+            6 => {
+                for pct in (0..=100).step_by(20) {
+                    let _ = write!(out, "\r\x1b[K[{:<20}] {:>3}%", "#".repeat(pct / 5), pct);
+                }
+                out.extend_from_slice(b"\r\n");
+            }
+            _ => {
+                let row = 1 + line % GRID_ROWS as usize;
+                let col = 1 + (line * 7) % (GRID_COLS as usize - 20);
+                let _ = write!(
+                    out,
+                    "\x1b7\x1b[{};{}H\x1b[7m status \x1b[27m\x1b8",
+                    row, col
+                );
+                out.extend_from_slice(b"plain ascii text to fill the rest of the line\r\n");
+            }
+        }
+        line += 1;
+    }
+    out
+}
@@ -0,0 +1,95 @@
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::bench;
+use crate::control;
+
+/// Parsed command-line arguments.
+///
+/// ```text
+/// terminrt [DIR] [--cwd DIR] [--shell PROGRAM] [--profile NAME] [--exec "COMMAND"]
+///          [--no-enter] [--stdin-control] [--glyph-preview] [--bench-feed[=MIB]] [--demo]
+/// ```
+///
+/// A bare `DIR` is kept for backwards compatibility and means the same as `--cwd`.
+/// Flags taking a value accept both `--flag value` and `--flag=value`.
+#[derive(Clone, Debug, Default)]
+pub struct CliArgs {
+    pub cwd: Option<PathBuf>,
+    /// Shell executable to run instead of the default PowerShell.
+    pub shell: Option<String>,
+    /// Saved profile to launch; `--cwd` and `--shell` still override its fields.
+    pub profile: Option<String>,
+    /// Command written to the shell once the terminal is up.
+    pub exec: Option<String>,
+    /// Press Enter after `exec` (disabled by `--no-enter`).
+    pub exec_enter: bool,
+    pub stdin_control: bool,
+    /// Draw the GPU square/glyph preview under the UI (debugging aid).
+    pub glyph_preview: bool,
+    /// Run the headless throughput benchmark over this many MiB and exit.
+    pub bench_feed: Option<usize>,
+    /// Replay canned output instead of spawning a shell (screenshots, UI tests).
+    pub demo: bool,
+}
+
+pub fn parse() -> CliArgs {
+    parse_from(std::env::args_os().skip(1))
+}
+
+fn parse_from(args: impl IntoIterator<Item = OsString>) -> CliArgs {
+    let mut out = CliArgs {
+        exec_enter: true,
+        ..CliArgs::default()
+    };
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        let arg_str = arg.to_string_lossy().into_owned();
+        let (flag, inline_value) = match arg_str.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => {
+                (flag.to_string(), Some(value.to_string()))
+            }
+            _ => (arg_str.clone(), None),
+        };
+        let mut value = |name: &str| -> Option<String> {
+            let v = inline_value
+                .clone()
+                .or_else(|| args.next().map(|a| a.to_string_lossy().into_owned()));
+            if v.is_none() {
+                eprintln!("Missing value for {}", name);
+            }
+            v
+        };
+
+        match flag.as_str() {
+            "--cwd" => out.cwd = value("--cwd").map(PathBuf::from),
+            "--shell" => out.shell = value("--shell"),
+            "--profile" => out.profile = value("--profile"),
+            "--exec" => out.exec = value("--exec"),
+            "--no-enter" => out.exec_enter = false,
+            control::STDIN_CONTROL_FLAG => out.stdin_control = true,
+            "--glyph-preview" => out.glyph_preview = true,
+            "--demo" => out.demo = true,
+            // The size is optional, so only the `=` form takes it.
+            "--bench-feed" => {
+                out.bench_feed = match inline_value.as_deref().map(str::parse) {
+                    None => Some(bench::DEFAULT_PAYLOAD_MIB),
+                    Some(Ok(mib)) if mib > 0 => Some(mib),
+                    Some(_) => {
+                        eprintln!("Invalid --bench-feed size, expected MiB > 0");
+                        Some(bench::DEFAULT_PAYLOAD_MIB)
+                    }
+                }
+            }
+            _ if flag.starts_with("--") => eprintln!("Ignoring unknown argument: {}", flag),
+            _ => {
+                if out.cwd.is_none() {
+                    out.cwd = Some(PathBuf::from(arg));
+                }
+            }
+        }
+    }
+
+    out
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// Commands remembered per directory (most recent first).
+const MAX_COMMANDS_PER_DIR: usize = 50;
+/// Directories remembered; the least recently used one is dropped first.
+const MAX_DIRS: usize = 200;
+
+// ---------------------------------------------------------------------------
+// Data model
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+struct DirHistory {
+    commands: Vec<String>,
+    /// Bumped on every insert; used to evict stale directories.
+    last_used: u64,
+}
+
+/// Per-directory command history, fed by shell-integration command marks.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CommandHistory {
+    dirs: HashMap<String, DirHistory>,
+    clock: u64,
+}
+
+impl CommandHistory {
+    /// Remember `command` as the most recent one run in `dir`.
+    pub fn record(&mut self, dir: &str, command: &str) {
+        self.clock += 1;
+        let entry = self.dirs.entry(dir_key(dir)).or_default();
+        entry.last_used = self.clock;
+        entry.commands.retain(|c| c != command);
+        entry.commands.insert(0, command.to_string());
+        entry.commands.truncate(MAX_COMMANDS_PER_DIR);
+
+        if self.dirs.len() > MAX_DIRS {
+            if let Some(oldest) = self
+                .dirs
+                .iter()
+                .min_by_key(|(_, h)| h.last_used)
+                .map(|(k, _)| k.clone())
+            {
+                self.dirs.remove(&oldest);
+            }
+        }
+    }
+
+    /// Commands previously run in `dir`, most recent first.
+    pub fn for_dir(&self, dir: &str) -> &[String] {
+        self.dirs
+            .get(&dir_key(dir))
+            .map(|h| h.commands.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dirs.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.dirs.clear();
+        self.clock = 0;
+    }
+}
+
+/// Windows paths are case-insensitive and may come with or without a trailing
+/// separator; normalize both so `C:\Src\` and `c:\src` share history.
+fn dir_key(dir: &str) -> String {
+    let trimmed = dir.trim_end_matches(['\\', '/']);
+    let trimmed = if trimmed.is_empty() { dir } else { trimmed };
+    if cfg!(windows) {
+        trimmed.to_lowercase()
+    } else {
+        trimmed.to_string()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Persistence
+// ---------------------------------------------------------------------------
+
+fn history_path() -> PathBuf {
+    let base = dirs::config_dir().unwrap_or_else(|| PathBuf::from("."));
+    base.join("terminrt").join("command_history.json")
+}
+
+pub fn load_history() -> CommandHistory {
+    let path = history_path();
+    if !path.exists() {
+        return CommandHistory::default();
+    }
+    match std::fs::read_to_string(&path) {
+        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
+        Err(_) => CommandHistory::default(),
+    }
+}
+
+pub fn save_history(history: &CommandHistory) {
+    let path = history_path();
+    if let Some(parent) = path.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+    if let Ok(json) = serde_json::to_string_pretty(history) {
+        let _ = std::fs::write(&path, json);
+    }
+}
+
+/// Remove the history file entirely.
+pub fn delete_history() {
+    let _ = std::fs::remove_file(history_path());
+}
@@ -0,0 +1,164 @@
+use serde::Deserialize;
+use std::io::{self, BufRead, Read, Write};
+use std::sync::mpsc;
+use std::thread;
+
+use crate::terminal::Wakeup;
+
+/// Command-line flag that enables the stdin control protocol.
+pub const STDIN_CONTROL_FLAG: &str = "--stdin-control";
+
+/// Lines longer than this are rejected instead of parsed.
+const MAX_LINE_BYTES: usize = 1024 * 1024;
+/// Upper bound for `resize` requests, in cells.
+pub const MAX_GRID_DIM: u16 = 1000;
+
+/// One newline-delimited JSON request read from stdin.
+///
+/// `{"type":"write","data":"ls\n"}`, `{"type":"resize","rows":40,"cols":120}`,
+/// `{"type":"snapshot"}`, `{"type":"quick_command","name":"Build"}`.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ControlCommand {
+    /// Send `data` to the PTY as if typed.
+    Write { data: String },
+    /// Pin the PTY to a fixed grid size instead of fitting the window.
+    Resize { rows: u16, cols: u16 },
+    /// Reply on stdout with the visible screen text.
+    Snapshot,
+    /// Run a saved quick command, looked up by `id` or else by `name`, exactly
+    /// as if its button was clicked (including any confirmation).
+    QuickCommand {
+        #[serde(default)]
+        id: Option<String>,
+        #[serde(default)]
+        name: Option<String>,
+    },
+}
+
+/// Read control commands from stdin on a background thread. Malformed lines
+/// are answered with an error reply and otherwise ignored. `wakeup` is called
+/// after each queued command so the event loop drains it promptly.
+pub fn spawn_stdin_listener(wakeup: Wakeup) -> mpsc::Receiver<ControlCommand> {
+    let (tx, rx) = mpsc::channel::<ControlCommand>();
+    thread::spawn(move || {
+        let mut stdin = io::stdin().lock();
+        let mut buf = Vec::new();
+        loop {
+            match read_line(&mut stdin, &mut buf) {
+                Ok(LineRead::Line) => {}
+                Ok(LineRead::TooLong) => {
+                    reply_error("line too long");
+                    continue;
+                }
+                Ok(LineRead::Eof) | Err(_) => break,
+            }
+            let Ok(line) = std::str::from_utf8(&buf) else {
+                reply_error("line is not valid UTF-8");
+                continue;
+            };
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<ControlCommand>(line) {
+                Ok(cmd) => {
+                    if tx.send(cmd).is_err() {
+                        break;
+                    }
+                    wakeup();
+                }
+                Err(e) => reply_error(&e.to_string()),
+            }
+        }
+    });
+    rx
+}
+
+enum LineRead {
+    Line,
+    /// Longer than `MAX_LINE_BYTES`; the whole line was skipped.
+    TooLong,
+    Eof,
+}
+
+/// Read the next line into `buf`, newline included. At most one byte more
+/// than `MAX_LINE_BYTES` is buffered: the rest of a longer line is read past
+/// and dropped, so a client that never sends a newline can't grow memory.
+fn read_line(input: &mut impl BufRead, buf: &mut Vec<u8>) -> io::Result<LineRead> {
+    buf.clear();
+    let limit = MAX_LINE_BYTES as u64 + 1;
+    if input.take(limit).read_until(b'\n', buf)? == 0 {
+        return Ok(LineRead::Eof);
+    }
+    if buf.len() > MAX_LINE_BYTES && buf.last() != Some(&b'\n') {
+        buf.clear();
+        input.skip_until(b'\n')?;
+        return Ok(LineRead::TooLong);
+    }
+    Ok(LineRead::Line)
+}
+
+pub fn reply_snapshot(rows: usize, cols: usize, text: &str) {
+    reply(&serde_json::json!({
+        "type": "snapshot",
+        "rows": rows,
+        "cols": cols,
+        "text": text,
+    }));
+}
+
+pub fn reply_error(message: &str) {
+    reply(&serde_json::json!({
+        "type": "error",
+        "message": message,
+    }));
+}
+
+fn reply(value: &serde_json::Value) {
+    let mut out = io::stdout().lock();
+    let _ = writeln!(out, "{}", value);
+    let _ = out.flush();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn oversized_lines_are_skipped_up_to_their_newline() {
+        let mut data = vec![b'x'; MAX_LINE_BYTES + 10];
+        data.extend_from_slice(b"\n{\"type\":\"snapshot\"}\n");
+        let mut input = io::Cursor::new(data);
+        let mut buf = Vec::new();
+
+        assert!(matches!(
+            read_line(&mut input, &mut buf).unwrap(),
+            LineRead::TooLong
+        ));
+        assert!(buf.is_empty());
+        assert!(matches!(
+            read_line(&mut input, &mut buf).unwrap(),
+            LineRead::Line
+        ));
+        assert_eq!(buf, b"{\"type\":\"snapshot\"}\n");
+        assert!(matches!(
+            read_line(&mut input, &mut buf).unwrap(),
+            LineRead::Eof
+        ));
+    }
+
+    #[test]
+    fn a_line_at_the_limit_is_kept() {
+        let mut data = vec![b'x'; MAX_LINE_BYTES];
+        data.push(b'\n');
+        let mut input = io::Cursor::new(data);
+        let mut buf = Vec::new();
+
+        assert!(matches!(
+            read_line(&mut input, &mut buf).unwrap(),
+            LineRead::Line
+        ));
+        assert_eq!(buf.len(), MAX_LINE_BYTES + 1);
+    }
+}
@@ -0,0 +1,185 @@
+use std::io::{self, Cursor, Read, Write};
+use std::sync::mpsc;
+
+use crate::pty::MemoryPty;
+
+/// Window title the script sets, so screenshots show where they came from.
+const TITLE: &str = "terminrt demo";
+const DIR: &str = "C:\\demo";
+/// Shown after Enter on a non-empty line instead of running anything.
+const NOT_RUN_NOTE: &str = "\x1b[90m(demo mode: commands are not run)\x1b[0m\r\n";
+
+/// A shell-less PTY for `--demo`: plays a fixed script of representative
+/// output (prompt, directory listing, build log, colors, text styles, wide
+/// characters), then echoes keystrokes locally like a bare line editor.
+/// Nothing is executed, so screenshots and UI tests do not depend on the
+/// machine's shell.
+pub fn backend() -> MemoryPty {
+    let (echo_tx, echo_rx) = mpsc::channel::<Vec<u8>>();
+    let output = Cursor::new(script()).chain(EchoReader {
+        rx: echo_rx,
+        pending: Cursor::new(Vec::new()),
+    });
+    let mut line = String::new();
+    MemoryPty::new(output).with_input_hook(move |data| {
+        let echo = echo_input(&mut line, data);
+        if !echo.is_empty() {
+            let _ = echo_tx.send(echo);
+        }
+    })
+}
+
+fn prompt(out: &mut Vec<u8>) {
+    let _ = write!(
+        out,
+        "\x1b]633;CWD={}\x07\x1b]633;A\x07\x1b[32mPS\x1b[0m \x1b[1;34m{}\x1b[0m> ",
+        DIR, DIR
+    );
+}
+
+/// A prompt followed by `command`, as if it had been typed and run.
+fn command(out: &mut Vec<u8>, command: &str) {
+    prompt(out);
+    let _ = write!(out, "{}\r\n\x1b]633;C\x07", command);
+}
+
+/// The canned session. Generated rather than stored so the color ramps stay
+/// readable here; the bytes are the same on every run.
+fn script() -> Vec<u8> {
+    let mut out = Vec::new();
+    let _ = write!(out, "\x1b]0;{}\x07", TITLE);
+
+    command(&mut out, "Get-ChildItem");
+    let _ = write!(out, "\r\n    Directory: {}\r\n\r\n", DIR);
+    out.extend_from_slice(b"\x1b[32;1mMode          LastWriteTime  Length Name\x1b[0m\r\n");
+    out.extend_from_slice(b"\x1b[32;1m----          -------------  ------ ----\x1b[0m\r\n");
+    for (mode, len, name) in [
+        ("d----", "", "assets"),
+        ("d----", "", "src"),
+        ("-a---", "1214", "Cargo.toml"),
+        ("-a---", "3840", "README.md"),
+    ] {
+        let color = if mode.starts_with('d') {
+            "\x1b[1;34m"
+        } else {
+            ""
+        };
+        let _ = write!(
+            out,
+            "{}     2024-03-01  09:12  {:>6} {}{}\x1b[0m\r\n",
+            mode, len, color, name
+        );
+    }
+    out.extend_from_slice(b"\x1b]633;D;0\x07\r\n");
+
+    command(&mut out, "cargo build --release");
+    out.extend_from_slice(b"   \x1b[1;32mCompiling\x1b[0m terminrt v0.1.0 (C:\\demo)\r\n");
+    out.extend_from_slice(b"\x1b[1;33mwarning\x1b[0m\x1b[1m: unused variable: `size`\x1b[0m\r\n");
+    out.extend_from_slice(b"  \x1b[1;34m-->\x1b[0m src\\main.rs:42:9\r\n");
+    out.extend_from_slice(
+        b"    \x1b[1;32mFinished\x1b[0m `release` profile [optimized] target(s) in 12.34s\r\n",
+    );
+    out.extend_from_slice(b"\x1b]633;D;0\x07\r\n");
+
+    command(&mut out, "terminrt-colors");
+    for bright in [false, true] {
+        for color in 0..8 {
+            let base = if bright { 100 } else { 40 };
+            let _ = write!(out, "\x1b[{}m    ", base + color);
+        }
+        out.extend_from_slice(b"\x1b[0m\r\n");
+    }
+    for row in 0..2 {
+        for cell in 0..108 {
+            let _ = write!(out, "\x1b[48;5;{}m ", 16 + row * 108 + cell);
+            if cell % 54 == 53 {
+                out.extend_from_slice(b"\x1b[0m\r\n");
+            }
+        }
+    }
+    for cell in 0..24 {
+        let _ = write!(out, "\x1b[48;5;{}m   ", 232 + cell);
+    }
+    out.extend_from_slice(b"\x1b[0m\r\n");
+    for cell in 0..72u32 {
+        let (r, g, b) = (255 - cell * 255 / 71, cell * 255 / 71, 128);
+        let _ = write!(out, "\x1b[48;2;{};{};{}m ", r, g, b);
+    }
+    out.extend_from_slice(b"\x1b[0m\r\n");
+    out.extend_from_slice(
+        b"\x1b[1mbold\x1b[0m \x1b[2mdim\x1b[0m \x1b[3mitalic\x1b[0m \x1b[4munderline\x1b[0m \
+          \x1b[4:3;58;5;196mcurly\x1b[0m \x1b[9mstrike\x1b[0m \x1b[7minverse\x1b[0m\r\n",
+    );
+    for row in [
+        "┌───────────────────┐",
+        "│ 终端 · ターミナル │",
+        "└───────────────────┘",
+    ] {
+        let _ = write!(out, "{}\r\n", row);
+    }
+    out.extend_from_slice(b"\x1b]633;D;0\x07\r\n");
+
+    prompt(&mut out);
+    out
+}
+
+/// What a bare line editor would echo for `data`, keeping the current line
+/// in `line`. Escape sequences (arrow keys, query replies) are dropped.
+fn echo_input(line: &mut String, data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::new();
+    if data.first() == Some(&0x1b) {
+        return out;
+    }
+    for ch in String::from_utf8_lossy(data).chars() {
+        match ch {
+            // Enter sends CR; a pasted CRLF must not print two prompts.
+            '\r' => {
+                out.extend_from_slice(b"\r\n");
+                if !line.trim().is_empty() {
+                    out.extend_from_slice(NOT_RUN_NOTE.as_bytes());
+                }
+                line.clear();
+                prompt(&mut out);
+            }
+            '\x7f' | '\x08' => {
+                if line.pop().is_some() {
+                    out.extend_from_slice(b"\x08 \x08");
+                }
+            }
+            '\x03' => {
+                out.extend_from_slice(b"^C\r\n");
+                line.clear();
+                prompt(&mut out);
+            }
+            ch if ch.is_control() => {}
+            ch => {
+                line.push(ch);
+                let mut buf = [0u8; 4];
+                out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+            }
+        }
+    }
+    out
+}
+
+/// Output that follows the script: echoed input, blocking until the next
+/// write. Ends (closing the PTY) once the terminal drops the sender.
+struct EchoReader {
+    rx: mpsc::Receiver<Vec<u8>>,
+    pending: Cursor<Vec<u8>>,
+}
+
+impl Read for EchoReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.pending.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            match self.rx.recv() {
+                Ok(data) => self.pending = Cursor::new(data),
+                Err(_) => return Ok(0),
+            }
+        }
+    }
+}
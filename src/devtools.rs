@@ -1,7 +1,7 @@
-use egui;
-use crate::terminal;
 use crate::quickcmd::{self, QuickCommandConfig};
-use crate::settings::SettingsState;
+use crate::settings::{self, SettingsState};
+use crate::terminal;
+use std::path::PathBuf;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DevToolsTab {
@@ -14,19 +14,53 @@ pub enum DevToolsTab {
 pub struct QuickCmdAction {
     pub command: String,
     pub auto_execute: bool,
+    pub confirm: bool,
+    /// Directory to change to first (see `QuickCommand::cwd`).
+    pub cwd: Option<String>,
+    /// Label to report the command under once it finishes, when it asks for
+    /// that (see `QuickCommand::notify_when_done`).
+    pub notify: Option<String>,
+}
+
+/// Everything the panel asks the caller to do this frame.
+#[derive(Default)]
+pub struct DevToolsAction {
+    pub quick_cmd: Option<QuickCmdAction>,
+    /// Start session logging to (path, strip escapes).
+    pub start_logging: Option<(PathBuf, bool)>,
+    pub stop_logging: bool,
+    /// New value for `AppConfig::bracketed_paste`.
+    pub set_bracketed_paste: Option<bool>,
+    /// Raw bytes to write to the PTY, from the send field.
+    pub send_bytes: Option<Vec<u8>>,
 }
 
 pub struct DevToolsState {
     pub active_tab: DevToolsTab,
     /// Tag currently selected for filtering quick commands in the panel.
     pub qcmd_filter_tag: String,
+    /// Session log file path being edited in the VT Stream tab.
+    pub log_path: String,
+    /// Strip escape sequences from the session log.
+    pub log_clean: bool,
+    /// Last error from starting the session log.
+    pub log_error: Option<String>,
+    /// Escaped byte string being edited in the VT Stream send field.
+    pub send_input: String,
 }
 
 impl Default for DevToolsState {
     fn default() -> Self {
+        let log_path = dirs::home_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("terminrt-session.log");
         Self {
             active_tab: DevToolsTab::QuickCommands,
             qcmd_filter_tag: String::new(),
+            log_path: log_path.display().to_string(),
+            log_clean: true,
+            log_error: None,
+            send_input: String::new(),
         }
     }
 }
@@ -37,11 +71,12 @@ pub fn render_devtools(
     terminal: Option<&terminal::TerminalInstance>,
     qcmd_config: &QuickCommandConfig,
     settings_state: &mut SettingsState,
+    bracketed_paste: bool,
     width: f32,
-) -> Option<QuickCmdAction> {
+) -> DevToolsAction {
     let side_fill = egui::Color32::from_rgb(30, 30, 30);
     let panel_stroke = egui::Stroke::new(1.0, egui::Color32::from_gray(60));
-    let mut action: Option<QuickCmdAction> = None;
+    let mut action = DevToolsAction::default();
 
     egui::SidePanel::right("right_panel")
         .resizable(false)
@@ -62,9 +97,14 @@ pub fn render_devtools(
 
             match state.active_tab {
                 DevToolsTab::QuickCommands => {
-                    action = render_quick_commands_panel(ui, state, qcmd_config, settings_state);
+                    action.quick_cmd =
+                        render_quick_commands_panel(ui, state, qcmd_config, settings_state);
                 }
                 DevToolsTab::VtStream => {
+                    render_session_log_controls(ui, state, terminal, &mut action);
+                    render_bracketed_paste_toggle(ui, terminal, bracketed_paste, &mut action);
+                    render_send_bytes(ui, state, terminal.is_some(), &mut action);
+                    ui.separator();
                     terminal::render_vt_log(ui, terminal);
                 }
                 DevToolsTab::Network => {
@@ -82,6 +122,207 @@ pub fn render_devtools(
     action
 }
 
+// ---------------------------------------------------------------------------
+// Session logging controls (VT Stream tab)
+// ---------------------------------------------------------------------------
+
+fn render_session_log_controls(
+    ui: &mut egui::Ui,
+    state: &mut DevToolsState,
+    terminal: Option<&terminal::TerminalInstance>,
+    action: &mut DevToolsAction,
+) {
+    let active_log = terminal.and_then(|t| t.session_log());
+
+    ui.horizontal(|ui| {
+        ui.add_space(6.0);
+        if let Some(log) = active_log {
+            ui.label(
+                egui::RichText::new("● REC")
+                    .monospace()
+                    .size(11.0)
+                    .color(egui::Color32::from_rgb(220, 80, 80)),
+            );
+            ui.label(
+                egui::RichText::new(format!(
+                    "{}{}",
+                    log.path().display(),
+                    if log.is_clean() { " (clean)" } else { " (raw)" }
+                ))
+                .monospace()
+                .size(11.0)
+                .color(egui::Color32::from_gray(150)),
+            );
+            if ui
+                .button(egui::RichText::new("Stop").monospace().size(11.0))
+                .clicked()
+            {
+                action.stop_logging = true;
+            }
+        } else {
+            ui.add(
+                egui::TextEdit::singleline(&mut state.log_path)
+                    .desired_width((ui.available_width() - 140.0).max(80.0))
+                    .font(egui::FontId::monospace(11.0))
+                    .hint_text("Log file path"),
+            );
+            ui.checkbox(
+                &mut state.log_clean,
+                egui::RichText::new("Clean").monospace().size(11.0),
+            )
+            .on_hover_text("Strip escape sequences so the log is plain text");
+            let can_start = terminal.is_some() && !state.log_path.trim().is_empty();
+            if ui
+                .add_enabled(
+                    can_start,
+                    egui::Button::new(egui::RichText::new("Log").monospace().size(11.0)),
+                )
+                .on_hover_text("Append all terminal output to this file")
+                .clicked()
+            {
+                action.start_logging =
+                    Some((PathBuf::from(state.log_path.trim()), state.log_clean));
+            }
+        }
+    });
+
+    if let Some(err) = &state.log_error {
+        ui.horizontal(|ui| {
+            ui.add_space(6.0);
+            ui.label(
+                egui::RichText::new(err)
+                    .monospace()
+                    .size(11.0)
+                    .color(egui::Color32::from_rgb(220, 90, 90)),
+            );
+        });
+    }
+}
+
+/// A field for writing arbitrary bytes to the PTY, e.g. `\x1b[6n` to see how
+/// the terminal answers. Enter or the button sends.
+fn render_send_bytes(
+    ui: &mut egui::Ui,
+    state: &mut DevToolsState,
+    has_terminal: bool,
+    action: &mut DevToolsAction,
+) {
+    let decoded = decode_escaped_bytes(&state.send_input);
+    ui.horizontal(|ui| {
+        ui.add_space(6.0);
+        let field = ui.add(
+            egui::TextEdit::singleline(&mut state.send_input)
+                .desired_width((ui.available_width() - 140.0).max(80.0))
+                .font(egui::FontId::monospace(11.0))
+                .hint_text(r"Send bytes, e.g. \x1b[6n"),
+        );
+        let (status, color) = match &decoded {
+            Ok(bytes) => (format!("{} B", bytes.len()), egui::Color32::from_gray(150)),
+            Err(err) => (err.clone(), egui::Color32::from_rgb(220, 90, 90)),
+        };
+        ui.label(
+            egui::RichText::new(status)
+                .monospace()
+                .size(11.0)
+                .color(color),
+        );
+        let ready = matches!(&decoded, Ok(bytes) if !bytes.is_empty()) && has_terminal;
+        let submitted = field.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+        let clicked = ui
+            .add_enabled(
+                ready,
+                egui::Button::new(egui::RichText::new("Send").monospace().size(11.0)),
+            )
+            .on_hover_text(r"Escapes: \xNN \e \r \n \t \a \0 \\")
+            .clicked();
+        if ready && (clicked || submitted) {
+            action.send_bytes = decoded.ok();
+            if submitted {
+                field.request_focus();
+            }
+        }
+    });
+}
+
+/// Decode `\xNN`, `\e` (ESC), `\r`, `\n`, `\t`, `\a` (BEL), `\0` and `\\`;
+/// everything else is taken as UTF-8 text.
+fn decode_escaped_bytes(input: &str) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(input.len());
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0u8; 4];
+            out.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                // `from_str_radix` alone would take a sign, as in `\x+1`.
+                let byte = (hex.len() == 2 && hex.chars().all(|c| c.is_ascii_hexdigit()))
+                    .then(|| u8::from_str_radix(&hex, 16).ok())
+                    .flatten()
+                    .ok_or_else(|| format!(r"bad \x{}", hex))?;
+                out.push(byte);
+            }
+            Some('e') => out.push(0x1b),
+            Some('r') => out.push(b'\r'),
+            Some('n') => out.push(b'\n'),
+            Some('t') => out.push(b'\t'),
+            Some('a') => out.push(0x07),
+            Some('0') => out.push(0),
+            Some('\\') => out.push(b'\\'),
+            Some(other) => return Err(format!(r"bad \{}", other)),
+            None => return Err(r"trailing \".to_string()),
+        }
+    }
+    Ok(out)
+}
+
+/// Shows whether the program asked for bracketed paste (`ESC[?2004h`) and
+/// lets the user turn the wrapping off for programs that mishandle it.
+fn render_bracketed_paste_toggle(
+    ui: &mut egui::Ui,
+    terminal: Option<&terminal::TerminalInstance>,
+    bracketed_paste: bool,
+    action: &mut DevToolsAction,
+) {
+    let requested = terminal.is_some_and(|t| t.is_bracketed_paste_enabled());
+    ui.horizontal(|ui| {
+        ui.add_space(6.0);
+        let mut wrap = bracketed_paste;
+        if ui
+            .checkbox(
+                &mut wrap,
+                egui::RichText::new("Bracketed paste")
+                    .monospace()
+                    .size(11.0),
+            )
+            .on_hover_text("Off pastes literally even when the program asks for markers")
+            .changed()
+        {
+            action.set_bracketed_paste = Some(wrap);
+        }
+        let (status, color) = match (requested, bracketed_paste) {
+            (true, true) => (
+                "program on, wrapping",
+                egui::Color32::from_rgb(110, 190, 120),
+            ),
+            (true, false) => (
+                "program on, forced literal",
+                egui::Color32::from_rgb(220, 170, 80),
+            ),
+            (false, _) => ("program off", egui::Color32::from_gray(150)),
+        };
+        ui.label(
+            egui::RichText::new(status)
+                .monospace()
+                .size(11.0)
+                .color(color),
+        );
+    });
+}
+
 // ---------------------------------------------------------------------------
 // Quick commands panel in the right sidebar
 // ---------------------------------------------------------------------------
@@ -95,6 +336,9 @@ fn render_quick_commands_panel(
     let mut action: Option<QuickCmdAction> = None;
     let tags = config.tags();
 
+    settings::render_settings_load_warning(ui, settings_state);
+    settings::render_quickcmd_load_warning(ui, settings_state);
+
     // Header: tag filter buttons + settings "+" button
     ui.horizontal_wrapped(|ui| {
         ui.style_mut().spacing.item_spacing = egui::vec2(4.0, 3.0);
@@ -227,18 +471,34 @@ fn render_quick_commands_panel(
                         ui.style_mut().spacing.item_spacing = egui::vec2(4.0, 4.0);
                         for cmd in &tag_cmds {
                             let btn_text = if cmd.keybinding.is_empty() {
-                                cmd.name.clone()
+                                cmd.label()
                             } else {
-                                format!("{} [{}]", cmd.name, cmd.keybinding.display())
+                                format!("{} [{}]", cmd.label(), cmd.keybinding.display())
+                            };
+                            let (fill, stroke, text) = match cmd.color {
+                                Some([r, g, b]) => {
+                                    let fill = egui::Color32::from_rgb(r, g, b);
+                                    let stroke = egui::Color32::from_rgb(
+                                        r.saturating_add(40),
+                                        g.saturating_add(40),
+                                        b.saturating_add(40),
+                                    );
+                                    (fill, stroke, crate::terminal::contrasting_fg(fill))
+                                }
+                                None => (
+                                    egui::Color32::from_gray(40),
+                                    egui::Color32::from_gray(65),
+                                    egui::Color32::from_gray(220),
+                                ),
                             };
                             let btn = egui::Button::new(
                                 egui::RichText::new(&btn_text)
                                     .monospace()
                                     .size(11.0)
-                                    .color(egui::Color32::from_gray(220)),
+                                    .color(text),
                             )
-                            .fill(egui::Color32::from_gray(40))
-                            .stroke(egui::Stroke::new(1.0, egui::Color32::from_gray(65)))
+                            .fill(fill)
+                            .stroke(egui::Stroke::new(1.0, stroke))
                             .rounding(egui::Rounding::same(4.0));
 
                             let resp = ui.add(btn).on_hover_text(&cmd.command);
@@ -246,6 +506,9 @@ fn render_quick_commands_panel(
                                 action = Some(QuickCmdAction {
                                     command: cmd.command.clone(),
                                     auto_execute: cmd.auto_execute,
+                                    confirm: cmd.confirm,
+                                    cwd: cmd.cwd.clone(),
+                                    notify: cmd.notify_label(),
                                 });
                             }
                         }
@@ -257,3 +520,29 @@ fn render_quick_commands_panel(
 
     action
 }
+
+#[cfg(test)]
+mod tests {
+    use super::decode_escaped_bytes;
+
+    #[test]
+    fn escapes_decode_to_their_bytes() {
+        assert_eq!(
+            decode_escaped_bytes(r"\x1b[31m\xFFa\x00"),
+            Ok(b"\x1b[31m\xffa\x00".to_vec())
+        );
+        assert_eq!(
+            decode_escaped_bytes(r"\e\r\n\t\a\0"),
+            Ok(b"\x1b\r\n\t\x07\x00".to_vec())
+        );
+        assert_eq!(decode_escaped_bytes(r"a\\b\\"), Ok(br"a\b\".to_vec()));
+        assert_eq!(decode_escaped_bytes("é"), Ok("é".as_bytes().to_vec()));
+    }
+
+    #[test]
+    fn malformed_and_truncated_escapes_are_errors() {
+        for input in [r"\x", r"\x4", r"\xg1", r"\x+1", r"\q", "\\", r"ok\"] {
+            assert!(decode_escaped_bytes(input).is_err(), "{input:?}");
+        }
+    }
+}
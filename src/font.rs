@@ -1,11 +1,48 @@
 use std::fs;
 
+/// Monospace font compiled into the binary (DejaVu Sans Mono, Bitstream Vera
+/// license, see `assets/fonts/`), so the terminal renders even on systems
+/// without any of the fonts below.
+pub const EMBEDDED_MONOSPACE: &[u8] = include_bytes!("../assets/fonts/DejaVuSansMono.ttf");
+
+/// Installed monospace fonts preferred over the embedded one, best first.
+const SYSTEM_MONOSPACE_CANDIDATES: &[&str] = &[
+    "C:\\Windows\\Fonts\\CascadiaMono.ttf",
+    "C:\\Windows\\Fonts\\consola.ttf",
+    "/System/Library/Fonts/SFNSMono.ttf",
+    "/System/Library/Fonts/Menlo.ttc",
+    "/usr/share/fonts/truetype/dejavu/DejaVuSansMono.ttf",
+    "/usr/share/fonts/truetype/liberation/LiberationMono-Regular.ttf",
+    "/usr/share/fonts/truetype/ubuntu/UbuntuMono-R.ttf",
+];
+
+/// Rasterizes glyphs for the GPU preview. Holds a fallback chain; each glyph
+/// comes from the first font that has it.
 pub struct FontRasterizer {
-    font: fontdue::Font,
+    fonts: Vec<fontdue::Font>,
 }
 
 impl FontRasterizer {
-    pub fn load_system() -> Self {
+    /// Use the same fonts, in the same order, as `family` in the egui font
+    /// definitions, so the preview matches what the terminal draws. Falls back
+    /// to `load_system`, then to `EMBEDDED_MONOSPACE`.
+    pub fn from_egui_family(defs: &egui::FontDefinitions, family: &egui::FontFamily) -> Self {
+        let fonts = fonts_for_family(defs, family);
+        if !fonts.is_empty() {
+            return Self { fonts };
+        }
+        if let Some(system) = Self::load_system() {
+            return system;
+        }
+        eprintln!("No usable system font; the glyph preview uses the embedded font");
+        let embedded = fontdue::Font::from_bytes(EMBEDDED_MONOSPACE, Default::default());
+        Self {
+            fonts: embedded.into_iter().collect(),
+        }
+    }
+
+    /// First font that loads from a list of common system locations.
+    pub fn load_system() -> Option<Self> {
         // Try a small set of common system font locations for portability.
         let candidates = system_font_candidates();
         let mut last_err = None;
@@ -13,7 +50,7 @@ impl FontRasterizer {
             match fs::read(&path) {
                 Ok(bytes) => {
                     match fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default()) {
-                        Ok(font) => return Self { font },
+                        Ok(font) => return Some(Self { fonts: vec![font] }),
                         Err(err) => {
                             last_err = Some(format!("Font parse failed for {}: {}", path, err));
                         }
@@ -25,35 +62,64 @@ impl FontRasterizer {
             }
         }
 
-        panic!(
+        eprintln!(
             "Failed to load any system font. Last error: {}",
             last_err.unwrap_or_else(|| "no candidates tried".to_string())
         );
+        None
     }
 
-    pub fn rasterize(&self, ch: char, size_px: f32) -> (fontdue::Metrics, Vec<u8>) {
-        self.font.rasterize(ch, size_px)
+    /// `None` when no font could be loaded at all.
+    pub fn rasterize(&self, ch: char, size_px: f32) -> Option<(fontdue::Metrics, Vec<u8>)> {
+        let font = self
+            .fonts
+            .iter()
+            .find(|font| font.has_glyph(ch))
+            .or_else(|| self.fonts.first())?;
+        Some(font.rasterize(ch, size_px))
     }
 }
 
-fn system_font_candidates() -> Vec<String> {
-    let mut paths = Vec::new();
-
-    // Windows common fonts
-    paths.push("C:\\Windows\\Fonts\\arial.ttf".to_string());
-    paths.push("C:\\Windows\\Fonts\\arialbd.ttf".to_string());
-    paths.push("C:\\Windows\\Fonts\\consola.ttf".to_string());
-    paths.push("C:\\Windows\\Fonts\\segoeui.ttf".to_string());
-
-    // macOS common fonts
-    paths.push("/System/Library/Fonts/SFNS.ttf".to_string());
-    paths.push("/System/Library/Fonts/Supplemental/Arial.ttf".to_string());
-    paths.push("/System/Library/Fonts/Supplemental/Courier New.ttf".to_string());
+fn fonts_for_family(defs: &egui::FontDefinitions, family: &egui::FontFamily) -> Vec<fontdue::Font> {
+    defs.families
+        .get(family)
+        .into_iter()
+        .flatten()
+        .filter_map(|name| defs.font_data.get(name))
+        .filter_map(|data| {
+            let settings = fontdue::FontSettings {
+                collection_index: data.index,
+                ..fontdue::FontSettings::default()
+            };
+            fontdue::Font::from_bytes(&*data.font, settings).ok()
+        })
+        .collect()
+}
 
-    // Linux common fonts
-    paths.push("/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf".to_string());
-    paths.push("/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf".to_string());
-    paths.push("/usr/share/fonts/truetype/ubuntu/Ubuntu-R.ttf".to_string());
+/// Bytes of the first installed monospace font that parses, for egui's
+/// monospace family ahead of the embedded fallback.
+pub fn load_system_monospace() -> Option<Vec<u8>> {
+    SYSTEM_MONOSPACE_CANDIDATES.iter().find_map(|path| {
+        let bytes = fs::read(path).ok()?;
+        fontdue::Font::from_bytes(bytes.as_slice(), fontdue::FontSettings::default()).ok()?;
+        Some(bytes)
+    })
+}
 
-    paths
+fn system_font_candidates() -> Vec<String> {
+    vec![
+        // Windows common fonts
+        "C:\\Windows\\Fonts\\arial.ttf".to_string(),
+        "C:\\Windows\\Fonts\\arialbd.ttf".to_string(),
+        "C:\\Windows\\Fonts\\consola.ttf".to_string(),
+        "C:\\Windows\\Fonts\\segoeui.ttf".to_string(),
+        // macOS common fonts
+        "/System/Library/Fonts/SFNS.ttf".to_string(),
+        "/System/Library/Fonts/Supplemental/Arial.ttf".to_string(),
+        "/System/Library/Fonts/Supplemental/Courier New.ttf".to_string(),
+        // Linux common fonts
+        "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf".to_string(),
+        "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf".to_string(),
+        "/usr/share/fonts/truetype/ubuntu/Ubuntu-R.ttf".to_string(),
+    ]
 }
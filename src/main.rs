@@ -2,36 +2,59 @@
 
 use egui_wgpu::ScreenDescriptor;
 use std::path::PathBuf;
-use std::sync::{mpsc, Arc};
+use std::sync::{mpsc, Arc, Mutex};
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use wgpu::util::DeviceExt;
 use winit::{
     dpi::PhysicalSize,
     event::{Event, WindowEvent},
-    event_loop::EventLoop,
+    event_loop::{ControlFlow, EventLoopBuilder},
     window::WindowBuilder,
 };
 
+mod appconfig;
+mod background;
+mod bench;
+mod cli;
+mod cmdhistory;
+mod control;
+mod demo;
+mod devtools;
 mod font;
 mod leftpanel;
+mod palette;
 mod pty;
+mod quickcmd;
+mod search;
+mod sessionlog;
+mod settings;
 #[path = "startup-page.rs"]
 mod startup_page;
 mod terminal;
-mod devtools;
 mod topbar;
-mod quickcmd;
-mod settings;
 
+const WINDOW_TITLE: &str = "terminrt";
 const WINDOW_WIDTH: u32 = 1638;
 const WINDOW_HEIGHT: u32 = 1024;
 const SQUARE_SIZE: f32 = 200.0;
 const FONT_SIZE: f32 = 120.0;
-const ENABLE_QUICKCMD_KEYBINDINGS: bool = true;
+/// A command must run this long before the status bar shows it as running.
+const RUNNING_INDICATOR_DELAY: Duration = Duration::from_secs(1);
+const SPINNER_FRAME: Duration = Duration::from_millis(250);
+/// How long the status bar says a copy hit `max_copy_bytes`.
+const COPY_TRUNCATED_NOTICE: Duration = Duration::from_secs(4);
+/// How long the status bar reports a watched quick command, counted from when
+/// the window has focus.
+const COMMAND_NOTICE: Duration = Duration::from_secs(10);
+/// Output this recent counts as a busy terminal when closing asks first.
+const RECENT_OUTPUT_WINDOW: Duration = Duration::from_secs(1);
+/// How long the cursor locator (Ctrl+Shift+L) stays up while it fades out.
+const CURSOR_LOCATOR_DURATION: Duration = Duration::from_secs(1);
 struct UiState {
     terminal: Option<terminal::TerminalInstance>,
     terminal_selection: terminal::TerminalSelectionState,
+    terminal_render_cache: terminal::TerminalRenderCache,
     pending_terminal: Option<terminal::TerminalInstance>,
     terminal_init_error: Option<String>,
     terminal_exited: bool,
@@ -41,23 +64,87 @@ struct UiState {
     terminal_scroll_request_frames_left: u8,
     terminal_scroll_id: u64,
     terminal_view_size_px: egui::Vec2,
+    /// Cell size in points, measured each frame; zero until the first layout.
+    terminal_cell_size_px: egui::Vec2,
+    /// Start the first session at `initial_rows`/`initial_cols` and size the
+    /// window for them. Cleared once that session is spawned.
+    initial_size_pending: bool,
     pty_render_size_px: egui::Vec2,
     pty_grid_size: (usize, usize),
     loading_started_at: Instant,
     startup_dir: PathBuf,
+    shell_config: pty::ShellConfig,
+    /// `--shell` and `--cwd` from the command line; they win over the fields
+    /// of the profile chosen at startup.
+    cli_shell: Option<String>,
+    cli_dir: Option<PathBuf>,
+    /// Name of the profile new sessions launch with, if any.
+    profile_name: Option<String>,
+    /// The profile's default foreground and background, set on each session.
+    theme_colors: [Option<[u8; 3]>; 2],
+    /// The startup page asks which profile to launch; nothing spawns until
+    /// one is picked.
+    choosing_profile: bool,
+    /// `--demo`: sessions replay canned output instead of running the shell.
+    demo: bool,
+    /// Command from `--exec`, written once the first terminal is attached.
+    startup_exec: Option<String>,
     close_confirm_open: bool,
     close_confirmed: bool,
+    /// "Don't ask again" in the close dialog; applied when Close is clicked.
+    close_dont_ask: bool,
+    /// When the terminal last produced output, for `CloseConfirm::WhileBusy`.
+    last_output_at: Option<Instant>,
+    /// When a copy was last cut off at `max_copy_bytes`, for the status bar.
+    copy_truncated_at: Option<Instant>,
+    /// Status bar message about a watched quick command (text, color, shown at).
+    command_notice: Option<(String, egui::Color32, Instant)>,
+    /// When the window closes itself after the shell exited (hold-open off).
+    /// Cleared by any key, click or reconnect.
+    exit_close_at: Option<Instant>,
     close_focus_pending: bool,
     devtools_open: bool,
     devtools_state: devtools::DevToolsState,
     quickcmd_config: quickcmd::QuickCommandConfig,
+    app_config: appconfig::AppConfig,
     settings_state: settings::SettingsState,
-    /// Pending quick command to write to PTY (set by UI, consumed by event loop).
-    pending_quick_cmd: Option<(String, bool)>,
+    palette_state: palette::PaletteState,
+    search_state: search::SearchState,
+    /// Texture for `app_config.background_image`, loaded off-thread.
+    background: background::BackgroundImage,
+    /// Commands seen per directory; only filled when the user opted in.
+    command_history: cmdhistory::CommandHistory,
+    /// Pending quick command to write to PTY (set by UI, consumed by event loop):
+    /// text, auto-execute, and the label to report it under once it finishes.
+    pending_quick_cmd: Option<(String, bool, Option<String>)>,
+    /// Quick command waiting for the user to confirm before it is sent.
+    quickcmd_confirm: Option<(String, bool, Option<String>)>,
+    /// Multi-line clipboard text waiting for review before "paste and run".
+    paste_confirm: Option<String>,
+    /// Profile to switch to once the user confirms ending the live session.
+    profile_switch_confirm: Option<String>,
     /// Terminal content area rect (egui points), used for file-drop hit testing.
     terminal_drop_rect: Option<egui::Rect>,
     /// Latest cursor position in egui points.
     last_cursor_pos: Option<egui::Pos2>,
+    /// In-progress IME composition shown inline at the terminal cursor.
+    ime_preedit: Option<terminal::ImePreedit>,
+    /// Read-only mode: output still renders, but nothing is written to the PTY.
+    terminal_read_only: bool,
+    /// Fixed grid size (rows, cols) requested over the control protocol; when set
+    /// the PTY is no longer fitted to the view.
+    pty_size_override: Option<(u16, u16)>,
+    /// When the cursor locator was last triggered; cleared once it has faded.
+    cursor_locator_at: Option<Instant>,
+    /// Whether the window has keyboard focus (a bell in the background asks
+    /// for attention).
+    window_focused: bool,
+    /// `app_config.reduce_motion` resolved against the OS preference.
+    reduce_motion: bool,
+    /// Last key press or IME commit, for `app_config.auto_lock_mins`.
+    last_key_input_at: Instant,
+    /// Read-only was switched on by the idle timer; shows the lock overlay.
+    auto_locked: bool,
 }
 
 #[repr(C)]
@@ -137,6 +224,9 @@ struct State {
     queue: wgpu::Queue,
     config: wgpu::SurfaceConfiguration,
     size: PhysicalSize<u32>,
+    /// Present modes the surface supports, for validating the user's choice.
+    supported_present_modes: Vec<wgpu::PresentMode>,
+    present_mode_pref: appconfig::PresentMode,
 
     uniforms: Uniforms,
     uniform_buffer: wgpu::Buffer,
@@ -153,12 +243,20 @@ struct State {
     glyph_bind_group: wgpu::BindGroup,
     glyph_texture: GlyphTexture,
     glyph_dims: Option<(u32, u32)>,
+    /// Draw the centered square and glyph preview under egui. Off unless
+    /// started with `--glyph-preview`; the terminal is the main view.
+    show_preview: bool,
 
     font: font::FontRasterizer,
 }
 
 impl State {
-    async fn new(window: Arc<winit::window::Window>) -> Self {
+    async fn new(
+        window: Arc<winit::window::Window>,
+        present_mode_pref: appconfig::PresentMode,
+        show_preview: bool,
+        font: font::FontRasterizer,
+    ) -> Self {
         let size = window.inner_size();
 
         let instance = wgpu::Instance::default();
@@ -200,7 +298,7 @@ impl State {
             format: surface_format,
             width: size.width.max(1),
             height: size.height.max(1),
-            present_mode: surface_caps.present_modes[0],
+            present_mode: choose_present_mode(&surface_caps.present_modes, present_mode_pref),
             desired_maximum_frame_latency: 2,
             alpha_mode: surface_caps.alpha_modes[0],
             view_formats: vec![],
@@ -364,8 +462,6 @@ impl State {
             &glyph_texture,
         );
 
-        let font = font::FontRasterizer::load_system();
-
         Self {
             window,
             surface,
@@ -385,8 +481,20 @@ impl State {
             glyph_bind_group,
             glyph_texture,
             glyph_dims: None,
+            show_preview,
             font,
+            supported_present_modes: surface_caps.present_modes,
+            present_mode_pref,
+        }
+    }
+
+    fn set_present_mode(&mut self, pref: appconfig::PresentMode) {
+        if pref == self.present_mode_pref {
+            return;
         }
+        self.present_mode_pref = pref;
+        self.config.present_mode = choose_present_mode(&self.supported_present_modes, pref);
+        self.surface.configure(&self.device, &self.config);
     }
 
     fn window(&self) -> &winit::window::Window {
@@ -433,14 +541,15 @@ impl State {
         }
     }
 
+    #[allow(dead_code)] // nothing picks a preview glyph yet
     fn set_glyph(&mut self, ch: char) {
         // Rasterize glyph into a grayscale bitmap and upload to GPU.
-        let (metrics, bitmap) = self.font.rasterize(ch, FONT_SIZE);
-        if metrics.width == 0 || metrics.height == 0 {
+        let glyph = self.font.rasterize(ch, FONT_SIZE);
+        let Some((metrics, bitmap)) = glyph.filter(|(m, _)| m.width > 0 && m.height > 0) else {
             self.glyph_dims = None;
             self.glyph_vertex_count = 0;
             return;
-        }
+        };
 
         let (padded, row_pitch) = pad_glyph(&bitmap, metrics.width as u32, metrics.height as u32);
         let extent = wgpu::Extent3d {
@@ -549,12 +658,14 @@ impl State {
                 timestamp_writes: None,
             });
 
-            rpass.set_pipeline(&self.color_pipeline);
-            rpass.set_bind_group(0, &self.uniform_bind_group, &[]);
-            rpass.set_vertex_buffer(0, self.square_vertex_buffer.slice(..));
-            rpass.draw(0..6, 0..1);
+            if self.show_preview {
+                rpass.set_pipeline(&self.color_pipeline);
+                rpass.set_bind_group(0, &self.uniform_bind_group, &[]);
+                rpass.set_vertex_buffer(0, self.square_vertex_buffer.slice(..));
+                rpass.draw(0..6, 0..1);
+            }
 
-            if self.glyph_vertex_count > 0 {
+            if self.show_preview && self.glyph_vertex_count > 0 {
                 rpass.set_pipeline(&self.glyph_pipeline);
                 rpass.set_bind_group(0, &self.glyph_bind_group, &[]);
                 rpass.set_vertex_buffer(0, self.glyph_vertex_buffer.slice(..));
@@ -651,7 +762,7 @@ fn centered_rect(size: PhysicalSize<u32>, width: f32, height: f32) -> (f32, f32,
 }
 
 fn pad_glyph(bitmap: &[u8], width: u32, height: u32) -> (Vec<u8>, u32) {
-    let row_pitch = ((width + 255) / 256) * 256;
+    let row_pitch = width.div_ceil(256) * 256;
     let mut padded = vec![0u8; (row_pitch * height) as usize];
     for y in 0..height as usize {
         let src_start = y * width as usize;
@@ -725,18 +836,154 @@ fn create_glyph_bind_group(
     })
 }
 
+/// Grid size for a new session: the control protocol's fixed size, else the
+/// configured initial size for the first session, else what fits the view.
+/// `None` until the first frame has measured the cell size.
+fn spawn_grid_size(ui_state: &UiState) -> Option<(u16, u16)> {
+    if let Some(size) = ui_state.pty_size_override {
+        return Some(size);
+    }
+    let cell = ui_state.terminal_cell_size_px;
+    if cell.x <= 0.0 || cell.y <= 0.0 {
+        return None;
+    }
+    let view = ui_state.terminal_view_size_px;
+    let mut rows = (view.y / cell.y).floor() as u16;
+    let mut cols = (view.x / cell.x).floor() as u16;
+    if ui_state.initial_size_pending {
+        if ui_state.app_config.initial_rows > 0 {
+            rows = ui_state.app_config.initial_rows;
+        }
+        if ui_state.app_config.initial_cols > 0 {
+            cols = ui_state.app_config.initial_cols;
+        }
+    }
+    Some(terminal::clamp_grid_size(rows, cols))
+}
+
+/// Grow or shrink the window so the terminal area holds exactly `rows`×`cols`
+/// cells, keeping the title and status bars as laid out last frame.
+fn fit_window_to_grid(
+    window: &winit::window::Window,
+    ui_state: &UiState,
+    (rows, cols): (u16, u16),
+    pixels_per_point: f32,
+) {
+    let cell = ui_state.terminal_cell_size_px;
+    let view = ui_state.terminal_view_size_px;
+    // Half a pixel of slack so the fit does not floor to one cell less.
+    let wanted = egui::vec2(cols as f32 * cell.x, rows as f32 * cell.y) + egui::vec2(0.5, 0.5);
+    let delta = (wanted - view) * pixels_per_point;
+    let size = window.inner_size();
+    let _ = window.request_inner_size(PhysicalSize::new(
+        (size.width as f32 + delta.x).round().max(1.0) as u32,
+        (size.height as f32 + delta.y).round().max(1.0) as u32,
+    ));
+}
+
+/// Start a session on a background thread; with `demo` it replays the canned
+/// `--demo` script instead of spawning the shell.
 fn spawn_terminal_async(
+    (rows, cols): (u16, u16),
     startup_dir: PathBuf,
+    shell_config: pty::ShellConfig,
+    demo: bool,
+    wakeup: terminal::Wakeup,
 ) -> mpsc::Receiver<std::io::Result<terminal::TerminalInstance>> {
     let (terminal_init_tx, terminal_init_rx) =
         mpsc::channel::<std::io::Result<terminal::TerminalInstance>>();
     thread::spawn(move || {
-        let result = terminal::TerminalInstance::new(24, 80, startup_dir);
+        let result = if demo {
+            Ok(terminal::TerminalInstance::with_backend(
+                rows,
+                cols,
+                startup_dir,
+                demo::backend(),
+                &shell_config,
+                wakeup.clone(),
+            ))
+        } else {
+            terminal::TerminalInstance::new(rows, cols, startup_dir, &shell_config, wakeup.clone())
+        };
         let _ = terminal_init_tx.send(result);
+        wakeup();
     });
     terminal_init_rx
 }
 
+/// Events posted to the event loop from background threads.
+#[derive(Clone, Copy, Debug)]
+enum UserEvent {
+    /// New PTY output, a finished terminal spawn, a control command, or reloaded
+    /// settings are waiting.
+    Wakeup,
+}
+
+/// Push `app_config` values that are cached elsewhere into live state. Most
+/// settings are read every frame and need nothing here; the present mode is
+/// applied after each frame.
+fn apply_app_config(ui_state: &mut UiState) {
+    ui_state.shell_config.prompt_integration = ui_state.app_config.prompt_integration;
+    ui_state.shell_config.read_buffer_size = ui_state.app_config.pty_read_buffer_size;
+    ui_state.shell_config.queue_capacity = ui_state.app_config.pty_queue_capacity;
+    ui_state.reduce_motion = ui_state.app_config.reduce_motion.resolve();
+    // Someone is at the settings; don't auto-lock on the spot.
+    ui_state.last_key_input_at = Instant::now();
+    ui_state
+        .terminal_selection
+        .set_word_separators(&ui_state.app_config.word_separators);
+    if let Some(term) = ui_state.terminal.as_mut() {
+        term.set_tab_width(ui_state.app_config.tab_width);
+        term.set_prompt_regex(&ui_state.app_config.prompt_regex);
+        term.set_input_logging(
+            ui_state.app_config.input_log,
+            ui_state.app_config.password_guard,
+        );
+        term.set_default_cursor_style(
+            ui_state.app_config.cursor_shape,
+            ui_state.app_config.cursor_blink,
+        );
+    }
+}
+
+/// `42s`, `3m 07s`, `1h 05m`.
+fn format_elapsed(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    if secs < 60 {
+        format!("{}s", secs)
+    } else if secs < 3600 {
+        format!("{}m {:02}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {:02}m", secs / 3600, secs / 60 % 60)
+    }
+}
+
+fn format_byte_rate(bytes_per_sec: usize) -> String {
+    if bytes_per_sec >= 1024 * 1024 {
+        format!("{:.1} MB/s", bytes_per_sec as f64 / (1024.0 * 1024.0))
+    } else {
+        format!("{} KB/s", bytes_per_sec / 1024)
+    }
+}
+
+/// The copy limit as `selected_text` applies it: `max_copy_bytes`, capped at
+/// `MAX_SELECTION_COPY_LIMIT`.
+fn format_copy_limit(max_copy_bytes: usize) -> String {
+    let bytes = max_copy_bytes.min(terminal::MAX_SELECTION_COPY_LIMIT);
+    if bytes >= 1024 * 1024 {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    } else if bytes >= 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+/// Move the next scheduled repaint to `at` if that is sooner.
+fn schedule_repaint(repaint_at: &mut Option<Instant>, at: Instant) {
+    *repaint_at = Some(repaint_at.map_or(at, |current| current.min(at)));
+}
+
 fn format_dropped_path_for_powershell(path: &std::path::Path) -> String {
     let raw = path.to_string_lossy();
     if raw.is_empty() {
@@ -748,18 +995,274 @@ fn format_dropped_path_for_powershell(path: &std::path::Path) -> String {
     format!("'{}' ", escaped)
 }
 
-fn show_close_confirm_dialog(ctx: &egui::Context, ui_state: &mut UiState) {
-    if !ui_state.close_confirm_open {
+/// Queue a quick command for the event loop, or hold it for confirmation first.
+/// A `cwd` is prepended as a directory change in the shell's own syntax, so the
+/// confirmation shows exactly what will be sent. `notify` names the command in
+/// the finish notice, when it asks for one.
+fn queue_quick_command(
+    ui_state: &mut UiState,
+    command: String,
+    auto_execute: bool,
+    confirm: bool,
+    cwd: Option<&str>,
+    notify: Option<String>,
+) {
+    let command = match cwd.map(str::trim).filter(|dir| !dir.is_empty()) {
+        Some(dir) => quickcmd::with_working_dir(&command, dir, ui_state.shell_config.syntax()),
+        None => command,
+    };
+    if confirm {
+        ui_state.quickcmd_confirm = Some((command, auto_execute, notify));
+    } else {
+        ui_state.pending_quick_cmd = Some((command, auto_execute, notify));
+    }
+}
+
+/// How a saved quick command is named by a keybinding or by automation.
+#[derive(Clone, Copy, Debug)]
+enum QuickCommandLookup<'a> {
+    Id(&'a str),
+    Name(&'a str),
+    KeyBinding(&'a quickcmd::KeyBinding),
+}
+
+/// Queue a saved quick command as if its button was clicked. Returns `false`
+/// and queues nothing when no command matches.
+fn queue_saved_quick_command(ui_state: &mut UiState, lookup: QuickCommandLookup) -> bool {
+    let config = &ui_state.quickcmd_config;
+    let found = match lookup {
+        QuickCommandLookup::Id(id) => config.find_by_id(id),
+        QuickCommandLookup::Name(name) => config.find_by_name(name),
+        QuickCommandLookup::KeyBinding(kb) => config.find_by_keybinding(kb),
+    };
+    let Some(cmd) = found else {
+        return false;
+    };
+    let (command, auto_execute, confirm, cwd, notify) = (
+        cmd.command.clone(),
+        cmd.auto_execute,
+        cmd.confirm,
+        cmd.cwd.clone(),
+        cmd.notify_label(),
+    );
+    queue_quick_command(
+        ui_state,
+        command,
+        auto_execute,
+        confirm,
+        cwd.as_deref(),
+        notify,
+    );
+    true
+}
+
+/// Show or hide the left panel and remember the choice.
+/// The letter of a fresh Ctrl+Shift+<letter> press, lowercased, for the app's
+/// own shortcuts. Releases and auto-repeat don't count.
+fn ctrl_shift_letter(
+    event: &winit::event::KeyEvent,
+    modifiers: &winit::event::Modifiers,
+) -> Option<char> {
+    if !event.state.is_pressed()
+        || event.repeat
+        || !modifiers.state().control_key()
+        || !modifiers.state().shift_key()
+    {
+        return None;
+    }
+    match &event.logical_key {
+        winit::keyboard::Key::Character(text) => {
+            let mut chars = text.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) if c.is_ascii_alphabetic() => Some(c.to_ascii_lowercase()),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn toggle_left_panel(ui_state: &mut UiState) {
+    ui_state.app_config.show_left_panel = !ui_state.app_config.show_left_panel;
+    appconfig::save_config(&ui_state.app_config);
+}
+
+/// Copy the selection to the clipboard and clear it, like a right-click.
+fn copy_terminal_selection(ui_state: &mut UiState, formatted: bool) {
+    write_selection_to_clipboard(ui_state, formatted);
+    ui_state.terminal_selection.clear();
+}
+
+/// Copy the selection as plain text, or with `formatted` as HTML too (plain
+/// text stays as the fallback flavor for apps that don't take HTML).
+fn write_selection_to_clipboard(ui_state: &mut UiState, formatted: bool) {
+    let Some(terminal) = ui_state.terminal.as_ref() else {
         return;
+    };
+    let max_bytes = ui_state.app_config.max_copy_bytes;
+    if let Some((text, truncated)) = terminal::selected_text_for_copy(
+        terminal,
+        &ui_state.terminal_selection,
+        ui_state.app_config.copy_filter,
+        ui_state.app_config.copy_filter_box_drawing,
+        max_bytes,
+    ) {
+        if truncated {
+            ui_state.copy_truncated_at = Some(Instant::now());
+        }
+        if !text.is_empty() {
+            let html = formatted
+                .then(|| {
+                    terminal::selected_html_for_copy(
+                        terminal,
+                        &ui_state.terminal_selection,
+                        max_bytes,
+                    )
+                })
+                .flatten();
+            if let Ok(mut cb) = arboard::Clipboard::new() {
+                let _ = match html {
+                    Some(html) => cb.set_html(html, Some(text)),
+                    None => cb.set_text(text),
+                };
+            }
+        }
+    }
+}
+
+/// Point new sessions at `profile`'s shell, environment, directory and colors;
+/// `None` (or an empty field) keeps the plain launch setup. `--shell` and
+/// `--cwd` still win while `cli_shell` / `cli_dir` are set.
+fn select_profile(ui_state: &mut UiState, profile: Option<&appconfig::Profile>) {
+    let field = |value: &String| Some(value.clone()).filter(|v| !v.is_empty());
+    ui_state.shell_config.program = ui_state
+        .cli_shell
+        .clone()
+        .or_else(|| profile.and_then(|p| field(&p.shell)));
+    ui_state.shell_config.env = profile
+        .map(|p| p.env.iter().map(|(k, v)| (k.clone(), v.clone())).collect())
+        .unwrap_or_default();
+    let dir = ui_state
+        .cli_dir
+        .clone()
+        .or_else(|| profile.and_then(|p| field(&p.cwd)).map(PathBuf::from));
+    ui_state.startup_dir = resolve_startup_dir(dir);
+    ui_state.theme_colors = profile.map_or([None; 2], |p| [p.foreground, p.background]);
+    ui_state.profile_name = profile.map(|p| p.name.clone());
+}
+
+/// Respawn the shell with the profile called `name`, through the reconnect
+/// path; the running session is replaced once the new one is up. Command-line
+/// `--shell` / `--cwd` no longer apply after an explicit switch.
+fn switch_profile(ui_state: &mut UiState, name: &str) {
+    let Some(profile) = ui_state
+        .app_config
+        .profiles
+        .iter()
+        .find(|p| p.name == name)
+        .cloned()
+    else {
+        eprintln!("Unknown profile: {}", name);
+        return;
+    };
+    ui_state.cli_shell = None;
+    ui_state.cli_dir = None;
+    select_profile(ui_state, Some(&profile));
+    ui_state.choosing_profile = false;
+    ui_state.exit_close_at = None;
+    ui_state.reconnect_requested = true;
+}
+
+/// Launch a separate terminrt process in the shell's current directory, with
+/// the same shell. The startup directory stands in when the shell reports
+/// none, or one this process cannot enter (deleted, remote over ssh, a WSL
+/// path). Failures are logged; the current window is unaffected.
+fn spawn_new_window(ui_state: &UiState) {
+    let exe = match std::env::current_exe() {
+        Ok(exe) => exe,
+        Err(e) => {
+            eprintln!(
+                "Cannot open a new window: executable path unavailable: {}",
+                e
+            );
+            return;
+        }
+    };
+    let dir = ui_state
+        .terminal
+        .as_ref()
+        .filter(|term| term.current_dir_reported())
+        .map(|term| PathBuf::from(term.current_dir()))
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(|| ui_state.startup_dir.clone());
+
+    let mut command = std::process::Command::new(&exe);
+    command.arg("--cwd").arg(&dir);
+    if let Some(profile) = ui_state.profile_name.as_ref() {
+        command.arg("--profile").arg(profile);
+    }
+    if let Some(program) = ui_state.shell_config.program.as_ref() {
+        command.arg("--shell").arg(program);
+    }
+    // The child must not compete for our stdin (e.g. under --stdin-control).
+    command.stdin(std::process::Stdio::null());
+    if let Err(e) = command.spawn() {
+        eprintln!("Failed to launch {}: {}", exe.display(), e);
     }
+}
+
+/// The text a quick command sends, for the confirm dialog. When the command
+/// is only pasted, the character the `{|}` marker leaves the cursor on is drawn
+/// as a block cursor instead of showing the marker itself.
+fn quickcmd_preview(command: &str, auto_execute: bool) -> egui::text::LayoutJob {
+    let (text, chars_after_marker) = quickcmd::resolve_cursor_marker(command);
+    let color = egui::Color32::from_rgb(230, 130, 90);
+    let format = egui::TextFormat::simple(egui::FontId::monospace(13.0), color);
+    let mut job = egui::text::LayoutJob::default();
+    let cursor = (!auto_execute && chars_after_marker > 0)
+        .then(|| text.char_indices().rev().nth(chars_after_marker - 1))
+        .flatten();
+    match cursor {
+        Some((idx, ch)) => {
+            let end = idx + ch.len_utf8();
+            job.append(&text[..idx], 0.0, format.clone());
+            job.append(
+                &text[idx..end],
+                0.0,
+                egui::TextFormat {
+                    color: egui::Color32::from_gray(14),
+                    background: color,
+                    ..format.clone()
+                },
+            );
+            job.append(&text[end..], 0.0, format);
+        }
+        None => job.append(&text, 0.0, format),
+    }
+    job
+}
 
+/// A confirmation window centered over a dimmed screen, shared by the close,
+/// profile switch, quick command and paste confirmations. `body` draws the
+/// message above a centered row of `buttons`, the first styled as the primary
+/// action (and focused when `focus_primary` is set). Returns the index of the
+/// button clicked this frame.
+fn show_confirm_modal(
+    ctx: &egui::Context,
+    id: &str,
+    title: &str,
+    window_size: egui::Vec2,
+    buttons: &[&str],
+    focus_primary: bool,
+    body: impl FnOnce(&mut egui::Ui),
+) -> Option<usize> {
     // Draw a dim background behind the confirmation window.
     // Keep this layer non-interactive to avoid stealing pointer events
     // from the dialog buttons and drag handle.
     let screen_rect = ctx.screen_rect();
     let blocker_layer = egui::LayerId::new(
         egui::Order::Middle,
-        egui::Id::new("close_confirm_modal_blocker"),
+        egui::Id::new(format!("{}_modal_blocker", id)),
     );
     ctx.layer_painter(blocker_layer).rect_filled(
         screen_rect,
@@ -767,15 +1270,15 @@ fn show_close_confirm_dialog(ctx: &egui::Context, ui_state: &mut UiState) {
         egui::Color32::from_rgba_unmultiplied(0, 0, 0, 70),
     );
 
-    let window_size = egui::vec2(270.0, 130.0);
     let center = screen_rect.center();
     let default_pos = egui::pos2(
         center.x - window_size.x * 0.5,
         center.y - window_size.y * 0.5,
     );
 
-    egui::Window::new("Confirm Close")
-        .id(egui::Id::new("close_confirm_dialog"))
+    let mut clicked = None;
+    egui::Window::new(title)
+        .id(egui::Id::new(format!("{}_dialog", id)))
         .collapsible(false)
         .resizable(false)
         .fixed_size(window_size)
@@ -790,51 +1293,358 @@ fn show_close_confirm_dialog(ctx: &egui::Context, ui_state: &mut UiState) {
                 .rounding(egui::Rounding::same(8.0))
                 .inner_margin(egui::Margin::symmetric(12.0, 10.0))
                 .show(ui, |ui| {
-                    ui.set_min_size(egui::vec2(250.0, 105.0));
+                    ui.set_min_size(window_size - egui::vec2(20.0, 25.0));
 
-                    ui.label(
-                        egui::RichText::new("Are you sure you want to close this window?")
-                            .size(16.0)
-                            .strong(),
-                    );
-                    ui.label(
-                        egui::RichText::new("Your current terminal session will be interrupted.")
-                            .size(13.0),
-                    );
+                    body(ui);
 
                     ui.add_space(6.0);
                     let button_w = 92.0;
                     let button_h = 30.0;
-                    let total_buttons_w = button_w * 2.0 + ui.spacing().item_spacing.x;
+                    let count = buttons.len() as f32;
+                    let total_buttons_w =
+                        button_w * count + ui.spacing().item_spacing.x * (count - 1.0);
                     let left_pad = ((ui.available_width() - total_buttons_w) * 0.5).max(0.0);
                     ui.horizontal(|ui| {
                         ui.add_space(left_pad);
-                        let close_button = egui::Button::new(
-                            egui::RichText::new("Close")
-                                .color(egui::Color32::WHITE)
-                                .strong(),
-                        )
-                        .min_size(egui::vec2(button_w, button_h))
-                        .fill(egui::Color32::from_rgb(45, 125, 235))
-                        .stroke(egui::Stroke::new(1.0, egui::Color32::from_rgb(90, 160, 255)));
-                        let close_response = ui.add(close_button);
-                        if ui_state.close_focus_pending {
-                            close_response.request_focus();
-                            ui_state.close_focus_pending = false;
-                        }
-                        if close_response.clicked() {
-                            ui_state.close_confirm_open = false;
-                            ui_state.close_confirmed = true;
-                        }
-
-                        let cancel_button =
-                            egui::Button::new("Cancel").min_size(egui::vec2(button_w, button_h));
-                        if ui.add(cancel_button).clicked() {
-                            ui_state.close_confirm_open = false;
+                        for (i, label) in buttons.iter().enumerate() {
+                            let button = if i == 0 {
+                                egui::Button::new(
+                                    egui::RichText::new(*label)
+                                        .color(egui::Color32::WHITE)
+                                        .strong(),
+                                )
+                                .fill(egui::Color32::from_rgb(45, 125, 235))
+                                .stroke(egui::Stroke::new(
+                                    1.0,
+                                    egui::Color32::from_rgb(90, 160, 255),
+                                ))
+                            } else {
+                                egui::Button::new(*label)
+                            };
+                            let response = ui.add(button.min_size(egui::vec2(button_w, button_h)));
+                            if i == 0 && focus_primary {
+                                response.request_focus();
+                            }
+                            if response.clicked() {
+                                clicked = Some(i);
+                            }
                         }
                     });
                 });
         });
+    clicked
+}
+
+fn show_quickcmd_confirm_dialog(ctx: &egui::Context, ui_state: &mut UiState) {
+    let Some((command, auto_execute, notify)) = ui_state.quickcmd_confirm.clone() else {
+        return;
+    };
+
+    let send = if auto_execute { "Run" } else { "Paste" };
+    let clicked = show_confirm_modal(
+        ctx,
+        "quickcmd_confirm",
+        "Confirm Command",
+        egui::vec2(360.0, 150.0),
+        &[send, "Cancel"],
+        false,
+        |ui| {
+            ui.label(
+                egui::RichText::new(if auto_execute {
+                    "Run this command?"
+                } else {
+                    "Paste this command?"
+                })
+                .size(16.0)
+                .strong(),
+            );
+            egui::Frame::none()
+                .fill(egui::Color32::from_gray(14))
+                .rounding(egui::Rounding::same(4.0))
+                .inner_margin(egui::Margin::symmetric(8.0, 6.0))
+                .show(ui, |ui| {
+                    let label = ui
+                        .add(egui::Label::new(quickcmd_preview(&command, auto_execute)).wrap(true));
+                    if !auto_execute && command.contains(quickcmd::CURSOR_MARKER) {
+                        label.on_hover_text("The cursor is left on the highlighted character");
+                    }
+                });
+        },
+    );
+    match clicked {
+        Some(0) => {
+            ui_state.quickcmd_confirm = None;
+            ui_state.pending_quick_cmd = Some((command, auto_execute, notify));
+        }
+        Some(_) => ui_state.quickcmd_confirm = None,
+        None => {}
+    }
+}
+
+fn show_profile_switch_confirm_dialog(ctx: &egui::Context, ui_state: &mut UiState) {
+    let Some(name) = ui_state.profile_switch_confirm.clone() else {
+        return;
+    };
+
+    let clicked = show_confirm_modal(
+        ctx,
+        "profile_switch_confirm",
+        "Switch Profile",
+        egui::vec2(360.0, 130.0),
+        &["Switch", "Cancel"],
+        false,
+        |ui| {
+            ui.label(
+                egui::RichText::new(format!("Switch to \"{}\"?", name))
+                    .size(16.0)
+                    .strong(),
+            );
+            ui.label(
+                egui::RichText::new("The running shell and anything in it will be ended.")
+                    .size(13.0)
+                    .color(egui::Color32::from_gray(180)),
+            );
+        },
+    );
+    match clicked {
+        Some(0) => {
+            ui_state.profile_switch_confirm = None;
+            switch_profile(ui_state, &name);
+        }
+        Some(_) => ui_state.profile_switch_confirm = None,
+        None => {}
+    }
+}
+
+fn show_paste_confirm_dialog(ctx: &egui::Context, ui_state: &mut UiState) {
+    let Some(text) = ui_state.paste_confirm.clone() else {
+        return;
+    };
+
+    let clicked = show_confirm_modal(
+        ctx,
+        "paste_confirm",
+        "Confirm Paste",
+        egui::vec2(420.0, 230.0),
+        &["Run", "Paste only", "Cancel"],
+        false,
+        |ui| {
+            let line_count = text.trim_end_matches(['\r', '\n']).lines().count();
+            ui.label(
+                egui::RichText::new(format!("Run {} pasted lines?", line_count))
+                    .size(16.0)
+                    .strong(),
+            );
+            egui::Frame::none()
+                .fill(egui::Color32::from_gray(14))
+                .rounding(egui::Rounding::same(4.0))
+                .inner_margin(egui::Margin::symmetric(8.0, 6.0))
+                .show(ui, |ui| {
+                    egui::ScrollArea::vertical()
+                        .max_height(110.0)
+                        .auto_shrink([false, true])
+                        .show(ui, |ui| {
+                            ui.add(
+                                egui::Label::new(
+                                    egui::RichText::new(&text)
+                                        .monospace()
+                                        .size(13.0)
+                                        .color(egui::Color32::from_rgb(230, 130, 90)),
+                                )
+                                .wrap(true),
+                            );
+                        });
+                });
+        },
+    );
+    let Some(clicked) = clicked else {
+        return;
+    };
+    ui_state.paste_confirm = None;
+    let run = match clicked {
+        0 => true,
+        1 => false,
+        _ => return,
+    };
+    if let Some(terminal) = ui_state.terminal.as_mut() {
+        if !ui_state.terminal_exited && !ui_state.terminal_read_only {
+            terminal.paste_text(
+                &text,
+                ui_state.app_config.paste_line_ending,
+                ui_state.app_config.paste_strip_trailing_newline,
+                ui_state.app_config.bracketed_paste,
+                run,
+            );
+        }
+    }
+}
+
+/// Whether closing now would cut off a command: one is running (per shell
+/// integration) or output arrived within `RECENT_OUTPUT_WINDOW`. Without
+/// integration marks there is no telling, so that counts as busy.
+fn terminal_busy(ui_state: &UiState) -> bool {
+    let Some(terminal) = ui_state.terminal.as_ref() else {
+        return false;
+    };
+    if ui_state.terminal_exited {
+        return false;
+    }
+    !terminal.tracks_commands()
+        || terminal.running_command_since().is_some()
+        || ui_state
+            .last_output_at
+            .is_some_and(|at| at.elapsed() < RECENT_OUTPUT_WINDOW)
+}
+
+/// Whether the shell is waiting at a prompt, for `KeyBindingMode::AtPrompt`.
+/// Without shell integration or a prompt pattern there is no telling, so
+/// anything but a full-screen program counts.
+fn shell_at_prompt(ui_state: &UiState) -> bool {
+    let Some(terminal) = ui_state.terminal.as_ref() else {
+        return false;
+    };
+    if terminal.is_alt_screen() {
+        return false;
+    }
+    !terminal.tracks_commands() || terminal.running_command_since().is_none()
+}
+
+/// Close the window, asking first if `confirm_close` says so.
+fn request_close(ui_state: &mut UiState) {
+    let ask = match ui_state.app_config.confirm_close {
+        appconfig::CloseConfirm::Always => true,
+        appconfig::CloseConfirm::WhileBusy => terminal_busy(ui_state),
+        appconfig::CloseConfirm::Never => false,
+    };
+    if ask {
+        ui_state.close_confirm_open = true;
+        ui_state.close_focus_pending = true;
+        ui_state.close_dont_ask = false;
+    } else {
+        ui_state.close_confirmed = true;
+    }
+}
+
+/// Switch to read-only once `auto_lock_mins` pass without typing, and wake
+/// up in time to do so.
+fn update_auto_lock(ctx: &egui::Context, ui_state: &mut UiState) {
+    if ui_state.auto_locked && !ui_state.terminal_read_only {
+        // Unlocked (overlay, top bar, settings or Ctrl+Shift+R): restart the
+        // idle clock so it doesn't lock again straight away.
+        ui_state.auto_locked = false;
+        ui_state.last_key_input_at = Instant::now();
+    }
+    let mins = ui_state.app_config.auto_lock_mins;
+    if mins == 0 || ui_state.terminal_read_only || ui_state.terminal.is_none() {
+        return;
+    }
+    let idle = ui_state.last_key_input_at.elapsed();
+    let limit = Duration::from_secs(u64::from(mins) * 60);
+    if idle >= limit {
+        ui_state.terminal_read_only = true;
+        ui_state.auto_locked = true;
+        ui_state.terminal_selection.clear();
+    } else {
+        ctx.request_repaint_after(limit - idle);
+    }
+}
+
+/// Dims the terminal while auto-locked; a click anywhere on it (or
+/// Ctrl+Shift+R) unlocks. Output keeps rendering underneath.
+fn show_auto_lock_overlay(ctx: &egui::Context, ui_state: &mut UiState) {
+    if !ui_state.auto_locked {
+        return;
+    }
+    let Some(rect) = ui_state.terminal_drop_rect else {
+        return;
+    };
+
+    let response = egui::Area::new(egui::Id::new("auto_lock_overlay"))
+        .order(egui::Order::Foreground)
+        .fixed_pos(rect.min)
+        .show(ctx, |ui| {
+            let (response, painter) = ui.allocate_painter(rect.size(), egui::Sense::click());
+            painter.rect_filled(
+                rect,
+                0.0,
+                egui::Color32::from_rgba_unmultiplied(0, 0, 0, 90),
+            );
+            let badge = egui::Rect::from_center_size(rect.center(), egui::vec2(300.0, 56.0));
+            painter.rect(
+                badge,
+                8.0,
+                egui::Color32::from_rgb(24, 24, 24),
+                egui::Stroke::new(1.0, egui::Color32::from_rgb(230, 180, 80)),
+            );
+            painter.text(
+                badge.center() - egui::vec2(0.0, 9.0),
+                egui::Align2::CENTER_CENTER,
+                "🔒 Locked while idle",
+                egui::FontId::monospace(14.0),
+                egui::Color32::from_rgb(230, 180, 80),
+            );
+            painter.text(
+                badge.center() + egui::vec2(0.0, 11.0),
+                egui::Align2::CENTER_CENTER,
+                "Click or Ctrl+Shift+R to unlock",
+                egui::FontId::monospace(11.0),
+                egui::Color32::from_gray(150),
+            );
+            response.on_hover_cursor(egui::CursorIcon::PointingHand)
+        })
+        .inner;
+    if response.clicked() {
+        ui_state.terminal_read_only = false;
+        ctx.request_repaint();
+    }
+}
+
+fn show_close_confirm_dialog(ctx: &egui::Context, ui_state: &mut UiState) {
+    if !ui_state.close_confirm_open {
+        return;
+    }
+
+    let running = ui_state
+        .terminal
+        .as_ref()
+        .is_some_and(|t| t.running_command_since().is_some());
+    let focus_close = std::mem::take(&mut ui_state.close_focus_pending);
+    let clicked = show_confirm_modal(
+        ctx,
+        "close_confirm",
+        "Confirm Close",
+        egui::vec2(270.0, 150.0),
+        &["Close", "Cancel"],
+        focus_close,
+        |ui| {
+            ui.label(
+                egui::RichText::new("Are you sure you want to close this window?")
+                    .size(16.0)
+                    .strong(),
+            );
+            ui.label(
+                egui::RichText::new(if running {
+                    "A command is still running and will be stopped."
+                } else {
+                    "Your current terminal session will be interrupted."
+                })
+                .size(13.0),
+            );
+            ui.checkbox(&mut ui_state.close_dont_ask, "Don't ask again");
+        },
+    );
+    match clicked {
+        Some(0) => {
+            ui_state.close_confirm_open = false;
+            ui_state.close_confirmed = true;
+            if ui_state.close_dont_ask {
+                ui_state.app_config.confirm_close = appconfig::CloseConfirm::Never;
+                appconfig::save_config(&ui_state.app_config);
+            }
+        }
+        Some(_) => ui_state.close_confirm_open = false,
+        None => {}
+    }
 }
 
 fn build_ui(
@@ -844,6 +1654,7 @@ fn build_ui(
 ) -> Option<egui::Rect> {
     let screen_rect = ctx.screen_rect();
     let mut ime_cursor_rect = None;
+    update_auto_lock(ctx, ui_state);
     ui_state.terminal_drop_rect = None;
 
     let total_w = screen_rect.width().max(1.0);
@@ -856,29 +1667,106 @@ fn build_ui(
         egui::Color32::from_gray(20)
     };
 
-    let left_action = leftpanel::render(ctx, &mut ui_state.devtools_open);
-    if left_action.open_settings {
-        ui_state.settings_state.open = true;
+    // Hiding the panel hands its width to the terminal, which refits its
+    // grid like any other layout change.
+    if ui_state.app_config.show_left_panel {
+        let left_action = leftpanel::render(ctx, &mut ui_state.devtools_open);
+        if left_action.open_settings {
+            ui_state.settings_state.open = true;
+        }
     }
 
     if ui_state.devtools_open {
-        let qcmd_action = devtools::render_devtools(
+        let devtools_action = devtools::render_devtools(
             ctx,
             &mut ui_state.devtools_state,
             ui_state.terminal.as_ref(),
             &ui_state.quickcmd_config,
             &mut ui_state.settings_state,
+            ui_state.app_config.bracketed_paste,
             right_w,
         );
-        if let Some(act) = qcmd_action {
-            ui_state.pending_quick_cmd = Some((act.command, act.auto_execute));
+        if let Some(bytes) = devtools_action.send_bytes {
+            if let Some(term) = ui_state.terminal.as_mut() {
+                if !ui_state.terminal_exited && !ui_state.terminal_read_only {
+                    term.write_to_pty(&bytes);
+                }
+            }
+        }
+        if let Some(bracketed_paste) = devtools_action.set_bracketed_paste {
+            ui_state.app_config.bracketed_paste = bracketed_paste;
+            appconfig::save_config(&ui_state.app_config);
+        }
+        if let Some(act) = devtools_action.quick_cmd {
+            queue_quick_command(
+                ui_state,
+                act.command,
+                act.auto_execute,
+                act.confirm,
+                act.cwd.as_deref(),
+                act.notify,
+            );
+        }
+        if let Some(term) = ui_state.terminal.as_mut() {
+            if let Some((path, clean)) = devtools_action.start_logging {
+                ui_state.devtools_state.log_error = term
+                    .start_session_log(&path, clean)
+                    .err()
+                    .map(|e| format!("Cannot open log: {}", e));
+            }
+            if devtools_action.stop_logging {
+                term.stop_session_log();
+            }
         }
     }
 
     // Settings modal (rendered on top)
-    if settings::render_settings(ctx, &mut ui_state.settings_state, &mut ui_state.quickcmd_config) {
+    let settings_action = settings::render_settings(
+        ctx,
+        &mut ui_state.settings_state,
+        &mut ui_state.quickcmd_config,
+        &mut ui_state.app_config,
+        &mut ui_state.terminal_read_only,
+        ui_state.command_history.is_empty(),
+    );
+    if settings_action.quickcmd_dirty {
         quickcmd::save_config(&ui_state.quickcmd_config);
     }
+    if settings_action.app_config_dirty {
+        appconfig::save_config(&ui_state.app_config);
+        apply_app_config(ui_state);
+    }
+    if settings_action.clear_command_history {
+        ui_state.command_history.clear();
+        cmdhistory::delete_history();
+    }
+
+    let dir_history = match ui_state.terminal.as_ref() {
+        Some(term) if ui_state.app_config.record_command_history => {
+            ui_state.command_history.for_dir(term.current_dir())
+        }
+        _ => &[],
+    };
+    if let Some(act) = palette::render(
+        ctx,
+        &mut ui_state.palette_state,
+        &ui_state.quickcmd_config,
+        dir_history,
+    ) {
+        queue_quick_command(
+            ui_state,
+            act.command,
+            act.auto_execute,
+            act.confirm,
+            act.cwd.as_deref(),
+            act.notify,
+        );
+    }
+
+    if let Some(term) = ui_state.terminal.as_ref() {
+        ui_state.search_state.step(ctx, term);
+    }
+    search::render(ctx, &mut ui_state.search_state);
 
     egui::CentralPanel::default()
         .frame(egui::Frame::none().fill(center_fill).stroke(panel_stroke))
@@ -889,21 +1777,27 @@ fn build_ui(
             // ── Unified status bar parameters (adjust these to tune) ──
             let bar_h: f32 = 22.0;        // 状态栏高度（上下共用）
             let bar_pad: f32 = 14.0;       // 状态栏与终端之间的间距（上下共用）
-            let bar_fade: f32 = 30.0;      // 渐变长度（上下共用）
+            let bar_fade: f32 = bar_pad;   // 渐变长度（上下共用，等于 bar_pad）
             let bar_gray: u8 = 26;         // 状态栏底色灰度（上下共用）
             // ───────────────────────────────────────────────────────────
+            // The fades are drawn over the terminal's padding only; a longer fade
+            // would cover the first and last rows of output.
 
             let prompt_h = bar_h;
             let term_top_pad = bar_pad;
             let term_bot_pad = bar_pad;
             let bottom_h = bar_h;
-            let terminal_h = (available.y - prompt_h - term_top_pad - term_bot_pad - bottom_h).max(0.0);
+            let terminal_h =
+                (available.y - prompt_h - term_top_pad - term_bot_pad - bottom_h).max(0.0);
 
             let prompt_rect = egui::Rect::from_min_size(origin, egui::vec2(available.x, prompt_h));
-            let term_left_pad: f32 = 8.0;
+            // The same padding on both sides; the grid is fitted to what's left.
+            let term_side_pad = (ui_state.app_config.terminal_padding as f32)
+                .min(available.x / 4.0)
+                .max(0.0);
             let terminal_rect = egui::Rect::from_min_size(
-                egui::pos2(origin.x + term_left_pad, origin.y + prompt_h + term_top_pad),
-                egui::vec2((available.x - term_left_pad).max(0.0), terminal_h),
+                egui::pos2(origin.x + term_side_pad, origin.y + prompt_h + term_top_pad),
+                egui::vec2((available.x - 2.0 * term_side_pad).max(0.0), terminal_h),
             );
             ui_state.terminal_drop_rect = Some(terminal_rect);
             let bottom_rect = egui::Rect::from_min_size(
@@ -918,7 +1812,21 @@ fn build_ui(
                     topbar::TopBarInput {
                         terminal_exited: ui_state.terminal_exited,
                         terminal_connecting: ui_state.terminal_connecting,
+                        terminal_read_only: ui_state.terminal_read_only,
+                        copy_mode: ui_state.terminal_selection.copy_mode_active(),
+                        current_dir: ui_state
+                            .terminal
+                            .as_ref()
+                            .map(|t| (t.current_dir(), t.current_dir_reported())),
                         reconnect_requested: &mut ui_state.reconnect_requested,
+                        profiles: &ui_state.app_config.profiles,
+                        active_profile: ui_state.profile_name.as_deref(),
+                        left_panel_hidden: !ui_state.app_config.show_left_panel,
+                        close_countdown_secs: ui_state.exit_close_at.map(|at| {
+                            at.saturating_duration_since(Instant::now())
+                                .as_secs_f32()
+                                .ceil() as u64
+                        }),
                     },
                     egui::Color32::from_gray(bar_gray),
                 );
@@ -931,98 +1839,216 @@ fn build_ui(
                 if action.request_drag_window {
                     let _ = window.drag_window();
                 }
+                if action.request_exit_copy_mode {
+                    ui_state.terminal_selection.exit_copy_mode();
+                }
+                if action.request_toggle_read_only {
+                    ui_state.terminal_read_only = !ui_state.terminal_read_only;
+                }
+                if action.request_new_window {
+                    spawn_new_window(ui_state);
+                }
+                if action.request_show_left_panel {
+                    toggle_left_panel(ui_state);
+                }
+                if let Some(idx) = action.request_switch_profile {
+                    let name = ui_state.app_config.profiles[idx].name.clone();
+                    let live = ui_state.terminal.is_some() && !ui_state.terminal_exited;
+                    if live {
+                        ui_state.profile_switch_confirm = Some(name);
+                    } else {
+                        switch_profile(ui_state, &name);
+                    }
+                }
+                if action.request_open_current_dir {
+                    if let Some(term) = ui_state.terminal.as_ref() {
+                        if let Err(e) = open::that_detached(term.current_dir()) {
+                            eprintln!("Failed to open {}: {}", term.current_dir(), e);
+                        }
+                    }
+                }
                 if action.request_close {
-                    ui_state.close_confirm_open = true;
-                    ui_state.close_focus_pending = true;
+                    request_close(ui_state);
                 }
             });
 
             // Middle area: terminal display
             ui.allocate_ui_at_rect(terminal_rect, |ui| {
-                egui::Frame::none()
-                    .fill(egui::Color32::from_rgb(18, 18, 18))
-                    .show(ui, |ui| {
-                        let available = ui.available_size();
-                        ui_state.terminal_view_size_px = available;
-
-                        if let Some(term) = ui_state.terminal.as_mut() {
-                            let font_id = egui::FontId::monospace(terminal::TERM_FONT_SIZE);
-                            let row_height = terminal::aligned_row_height(ui, &font_id);
-                            let char_width = terminal::aligned_glyph_width(ui, &font_id, 'M');
-                            if row_height > 0.0 && char_width > 0.0 {
-                                let new_rows = (available.y / row_height).floor() as u16;
-                                let new_cols = (available.x / char_width).floor() as u16;
-                                if new_rows > 0
-                                    && new_cols > 0
-                                    && (new_rows as usize != term.rows()
-                                        || new_cols as usize != term.cols())
-                                {
-                                    term.resize(new_rows, new_cols);
-                                    ui_state.terminal_scroll_request =
-                                        Some(terminal::ScrollRequest::ScreenTop);
-                                    ui_state.terminal_scroll_request_frames_left = 30;
-                                    ui_state.terminal_scroll_id =
-                                        ui_state.terminal_scroll_id.wrapping_add(1);
+                let term_fill = ui_state
+                    .terminal
+                    .as_ref()
+                    .map_or(terminal::TERM_BACKGROUND, |t| t.default_background());
+                ui_state
+                    .background
+                    .sync(ui.ctx(), &ui_state.app_config.background_image);
+                egui::Frame::none().fill(term_fill).show(ui, |ui| {
+                    // Default-colored cells are transparent, so the image
+                    // shows through them; explicit backgrounds cover it.
+                    ui_state.background.paint(
+                        ui,
+                        ui.max_rect(),
+                        ui_state.app_config.background_fit,
+                        ui_state.app_config.background_opacity,
+                    );
+                    let available = ui.available_size();
+                    ui_state.terminal_view_size_px = available;
+                    let font_id = egui::FontId::monospace(terminal::TERM_FONT_SIZE);
+                    let row_height = terminal::aligned_row_height(ui, &font_id);
+                    let char_width = terminal::aligned_glyph_width(ui, &font_id, 'M');
+                    ui_state.terminal_cell_size_px = egui::vec2(char_width, row_height);
+
+                    if let Some(term) = ui_state.terminal.as_mut() {
+                        // A minimized window reports a zero-sized area; keep the
+                        // grid as it was rather than squeezing the shell.
+                        let minimized = window.is_minimized().unwrap_or(false);
+                        if row_height > 0.0 && char_width > 0.0 && !minimized {
+                            let (fit_rows, fit_cols) = ui_state.pty_size_override.unwrap_or((
+                                (available.y / row_height).floor() as u16,
+                                (available.x / char_width).floor() as u16,
+                            ));
+                            // Zero means the layout has no room this frame (e.g.
+                            // mid-resize); anything else is clamped to the floor.
+                            let (new_rows, new_cols) =
+                                terminal::clamp_grid_size(fit_rows, fit_cols);
+                            if fit_rows > 0
+                                && fit_cols > 0
+                                && (new_rows as usize != term.rows()
+                                    || new_cols as usize != term.cols())
+                            {
+                                // Throttled while the window is dragged; come back
+                                // for the final size once the interval is up.
+                                match term.request_resize(new_rows, new_cols) {
+                                    Some(wait) => ui.ctx().request_repaint_after(wait),
+                                    None => {
+                                        ui_state.terminal_scroll_request =
+                                            Some(terminal::ScrollRequest::ScreenTop);
+                                        ui_state.terminal_scroll_request_frames_left = 30;
+                                        ui_state.terminal_scroll_id =
+                                            ui_state.terminal_scroll_id.wrapping_add(1);
+                                    }
                                 }
                             }
-
-                            let pty_cols = term.cols();
-                            let pty_rows = term.rows();
-                            ui_state.pty_grid_size = (pty_cols, pty_rows);
-                            ui_state.pty_render_size_px = if row_height > 0.0 && char_width > 0.0 {
-                                egui::vec2(
-                                    char_width * pty_cols as f32,
-                                    row_height * pty_rows as f32,
-                                )
-                            } else {
-                                egui::Vec2::ZERO
-                            };
-                        } else {
-                            ui_state.pty_grid_size = (0, 0);
-                            ui_state.pty_render_size_px = egui::Vec2::ZERO;
                         }
 
-                        if ui_state.terminal.is_some() {
-                            let scroll_request = if ui_state.terminal_scroll_request_frames_left > 0
-                            {
-                                ui_state.terminal_scroll_request
-                            } else {
-                                None
-                            };
+                        let pty_cols = term.cols();
+                        let pty_rows = term.rows();
+                        ui_state.pty_grid_size = (pty_cols, pty_rows);
+                        ui_state.pty_render_size_px = if row_height > 0.0 && char_width > 0.0 {
+                            egui::vec2(char_width * pty_cols as f32, row_height * pty_rows as f32)
+                        } else {
+                            egui::Vec2::ZERO
+                        };
+                    } else {
+                        ui_state.pty_grid_size = (0, 0);
+                        ui_state.pty_render_size_px = egui::Vec2::ZERO;
+                    }
 
-                            ime_cursor_rect = terminal::render_terminal(
-                                ui,
-                                ui_state.terminal.as_ref(),
-                                &mut ui_state.terminal_selection,
-                                ui_state.close_confirm_open,
-                                scroll_request,
-                                ui_state.terminal_scroll_id,
-                            );
+                    if ui_state.terminal.is_some() {
+                        let scroll_request = if ui_state.terminal_scroll_request_frames_left > 0 {
+                            ui_state.terminal_scroll_request
+                        } else {
+                            None
+                        };
 
-                            if ui_state.terminal_scroll_request_frames_left > 0 {
-                                ui_state.terminal_scroll_request_frames_left -= 1;
-                                if ui_state.terminal_scroll_request_frames_left == 0 {
-                                    ui_state.terminal_scroll_request = None;
-                                }
+                        let mut jump_request = None;
+                        let cursor_locator = match ui_state.cursor_locator_at {
+                            Some(at) if at.elapsed() < CURSOR_LOCATOR_DURATION => {
+                                // Repaint only while the flash fades.
+                                ui.ctx().request_repaint();
+                                1.0 - at.elapsed().as_secs_f32()
+                                    / CURSOR_LOCATOR_DURATION.as_secs_f32()
+                            }
+                            _ => {
+                                ui_state.cursor_locator_at = None;
+                                0.0
+                            }
+                        };
+                        ime_cursor_rect = terminal::render_terminal(
+                            ui,
+                            ui_state.terminal.as_ref(),
+                            &mut ui_state.terminal_selection,
+                            ui_state.close_confirm_open
+                                || ui_state.quickcmd_confirm.is_some()
+                                || ui_state.paste_confirm.is_some()
+                                || ui_state.profile_switch_confirm.is_some()
+                                || ui_state.auto_locked,
+                            scroll_request,
+                            ui_state.terminal_scroll_id,
+                            ui_state.ime_preedit.as_ref(),
+                            &mut ui_state.terminal_render_cache,
+                            &mut ui_state.search_state,
+                            terminal::HighlightColors::new(
+                                ui_state.app_config.selection_color,
+                                ui_state
+                                    .terminal
+                                    .as_ref()
+                                    .and_then(|t| t.cursor_color())
+                                    .unwrap_or(ui_state.app_config.cursor_color),
+                            ),
+                            ui_state.app_config.animate_scrolling && !ui_state.reduce_motion,
+                            &mut jump_request,
+                            cursor_locator,
+                            ui_state.reduce_motion,
+                        );
+
+                        if ui_state.terminal_scroll_request_frames_left > 0 {
+                            ui_state.terminal_scroll_request_frames_left -= 1;
+                            if ui_state.terminal_scroll_request_frames_left == 0 {
+                                ui_state.terminal_scroll_request = None;
+                            } else {
+                                // The request spans several frames; keep them coming.
+                                ui.ctx().request_repaint();
                             }
-                        } else {
-                            startup_page::render(
-                                ui,
-                                ui_state.loading_started_at,
-                                ui_state.terminal_init_error.as_deref(),
-                            );
                         }
-                    });
+                        // Unlike a right-click, the copied word stays selected.
+                        if ui_state.terminal_selection.take_word_selected()
+                            && ui_state.app_config.copy_on_double_click
+                        {
+                            write_selection_to_clipboard(ui_state, false);
+                        }
+                        // Jump buttons snap to their offset on the next frame.
+                        if let Some(request) = jump_request {
+                            ui_state.terminal_scroll_request = Some(request);
+                            ui_state.terminal_scroll_request_frames_left = 1;
+                            ui.ctx().request_repaint();
+                        }
+                    } else if ui_state.choosing_profile {
+                        let names: Vec<&str> = ui_state
+                            .app_config
+                            .profiles
+                            .iter()
+                            .map(|p| p.name.as_str())
+                            .collect();
+                        let picked = startup_page::render_profile_picker(
+                            ui,
+                            ui_state.loading_started_at,
+                            ui_state.reduce_motion,
+                            &names,
+                        );
+                        if let Some(idx) = picked {
+                            let profile = ui_state.app_config.profiles[idx].clone();
+                            select_profile(ui_state, Some(&profile));
+                            ui_state.choosing_profile = false;
+                            ui_state.reconnect_requested = true;
+                            ui.ctx().request_repaint();
+                        }
+                    } else {
+                        startup_page::render(
+                            ui,
+                            ui_state.loading_started_at,
+                            ui_state.terminal_init_error.as_deref(),
+                            ui_state.reduce_motion,
+                        );
+                    }
+                });
             });
 
             // Bottom area: reserve space (text painted later on top layer)
             ui.allocate_ui_at_rect(bottom_rect, |_ui| {});
 
             // --- Layer 1 (Foreground): gradient overlays on top of terminal content ---
-            let fg_layer = egui::LayerId::new(
-                egui::Order::Foreground,
-                egui::Id::new("gradient_overlays"),
-            );
+            let fg_layer =
+                egui::LayerId::new(egui::Order::Foreground, egui::Id::new("gradient_overlays"));
             let fg_painter = ui.ctx().layer_painter(fg_layer);
 
             // Expand rects by 1px on each side to cover panel stroke edges
@@ -1079,6 +2105,7 @@ fn build_ui(
             // Top prompt bar: reserved for future use
 
             // Bottom status text
+            let status_right;
             {
                 let connect_status = if ui_state.terminal.is_some() {
                     if ui_state.terminal_exited {
@@ -1093,7 +2120,7 @@ fn build_ui(
                 } else {
                     "starting"
                 };
-                let status = format!(
+                let mut status = format!(
                     "Terminal: {} | View: {:.0}x{:.0}px | PTY: {:.0}x{:.0}px ({}x{} cells)",
                     connect_status,
                     ui_state.terminal_view_size_px.x,
@@ -1103,17 +2130,135 @@ fn build_ui(
                     ui_state.pty_grid_size.0,
                     ui_state.pty_grid_size.1,
                 );
+                let mode_term = ui_state
+                    .terminal
+                    .as_ref()
+                    .filter(|_| ui_state.app_config.show_terminal_modes);
+                if let Some(term) = mode_term {
+                    let modes = term.active_mode_labels();
+                    status.push_str(" | Modes: ");
+                    status.push_str(&if modes.is_empty() {
+                        "-".to_string()
+                    } else {
+                        modes.join(" ")
+                    });
+                }
+                let uptime_term = ui_state
+                    .terminal
+                    .as_ref()
+                    .filter(|_| ui_state.app_config.show_session_uptime);
+                if let Some(term) = uptime_term {
+                    let elapsed = term.session_elapsed();
+                    status.push_str(&format!(" | Up {}", format_elapsed(elapsed)));
+                    if !ui_state.terminal_exited {
+                        // Tick on the next whole second.
+                        let into_second = Duration::from_nanos(elapsed.subsec_nanos() as u64);
+                        ctx.request_repaint_after(Duration::from_secs(1) - into_second);
+                    }
+                }
+                let running_since = ui_state
+                    .terminal
+                    .as_ref()
+                    .filter(|_| {
+                        ui_state.app_config.show_running_command && !ui_state.terminal_exited
+                    })
+                    .and_then(|t| t.running_command_since());
+                if let Some(since) = running_since {
+                    // Quick commands finish before the delay and never flash it.
+                    let elapsed = since.elapsed();
+                    if elapsed >= RUNNING_INDICATOR_DELAY {
+                        const SPINNER: [char; 4] = ['|', '/', '-', '\\'];
+                        let frame = (elapsed.as_millis() / SPINNER_FRAME.as_millis()) as usize;
+                        status.push_str(&format!(
+                            " | {} Running {}",
+                            SPINNER[frame % SPINNER.len()],
+                            format_elapsed(elapsed)
+                        ));
+                        ctx.request_repaint_after(SPINNER_FRAME);
+                    } else {
+                        ctx.request_repaint_after(RUNNING_INDICATOR_DELAY - elapsed);
+                    }
+                }
                 let font_id = egui::FontId::monospace(12.0);
-                let galley = text_painter.layout_no_wrap(
-                    status,
-                    font_id,
-                    egui::Color32::from_gray(120),
-                );
+                let galley =
+                    text_painter.layout_no_wrap(status, font_id, egui::Color32::from_gray(120));
                 let text_pos = egui::pos2(bottom_rect.left() + 8.0, bottom_rect.top() + 8.0);
+                let mut right = text_pos.x + galley.size().x;
                 text_painter.galley(text_pos, galley, egui::Color32::from_gray(120));
+
+                let truncated_ago = ui_state.copy_truncated_at.map(|at| at.elapsed());
+                if let Some(ago) = truncated_ago.filter(|ago| *ago < COPY_TRUNCATED_NOTICE) {
+                    let notice = format!(
+                        "Copy truncated to {}",
+                        format_copy_limit(ui_state.app_config.max_copy_bytes)
+                    );
+                    let color = egui::Color32::from_rgb(190, 150, 80);
+                    let galley =
+                        text_painter.layout_no_wrap(notice, egui::FontId::monospace(12.0), color);
+                    let notice_pos = egui::pos2(right + 16.0, text_pos.y);
+                    right = notice_pos.x + galley.size().x;
+                    text_painter.galley(notice_pos, galley, color);
+                    ctx.request_repaint_after(COPY_TRUNCATED_NOTICE - ago);
+                } else {
+                    ui_state.copy_truncated_at = None;
+                }
+
+                let notice_ago = ui_state
+                    .command_notice
+                    .as_ref()
+                    .map(|(_, _, at)| at.elapsed());
+                let focused = ui_state.window_focused;
+                if let Some(ago) = notice_ago.filter(|ago| !focused || *ago < COMMAND_NOTICE) {
+                    let (notice, color, _) = ui_state.command_notice.as_ref().unwrap();
+                    let galley = text_painter.layout_no_wrap(
+                        notice.clone(),
+                        egui::FontId::monospace(12.0),
+                        *color,
+                    );
+                    let notice_pos = egui::pos2(right + 16.0, text_pos.y);
+                    right = notice_pos.x + galley.size().x;
+                    text_painter.galley(notice_pos, galley, *color);
+                    // Kept up while the window is in the background.
+                    if focused {
+                        ctx.request_repaint_after(COMMAND_NOTICE.saturating_sub(ago));
+                    }
+                } else {
+                    ui_state.command_notice = None;
+                }
+                status_right = right;
+            }
+
+            // Flood indicator (right side), only while output is heavy
+            if let Some(stats) = ui_state
+                .terminal
+                .as_ref()
+                .and_then(|t| t.heavy_output_stats())
+            {
+                let mut text = format!("▲ {}", format_byte_rate(stats.bytes_per_sec));
+                if stats.coalesced {
+                    text.push_str(" · frames coalesced");
+                }
+                let galley = text_painter.layout_no_wrap(
+                    text,
+                    egui::FontId::monospace(12.0),
+                    egui::Color32::from_rgb(190, 150, 80),
+                );
+                let text_pos = egui::pos2(
+                    bottom_rect.right() - galley.size().x - 10.0,
+                    bottom_rect.top() + 8.0,
+                );
+                if text_pos.x > status_right + 16.0 {
+                    text_painter.galley(text_pos, galley, egui::Color32::from_rgb(190, 150, 80));
+                }
+                // Output may stop without another frame; come back to hide it.
+                ctx.request_repaint_after(Duration::from_secs(1));
             }
         });
 
+    show_auto_lock_overlay(ctx, ui_state);
+    show_quickcmd_confirm_dialog(ctx, ui_state);
+    show_paste_confirm_dialog(ctx, ui_state);
+    show_profile_switch_confirm_dialog(ctx, ui_state);
     show_close_confirm_dialog(ctx, ui_state);
     ime_cursor_rect
 }
@@ -1137,13 +2282,83 @@ fn load_system_chinese_font() -> Option<Vec<u8>> {
     None
 }
 
-fn main() {
-    let startup_dir = resolve_startup_dir();
+/// egui's defaults plus, for monospace, an installed font first and the
+/// embedded one second, and a CJK fallback. Shared by the UI and the glyph
+/// preview so both render with the same typeface.
+fn build_font_definitions() -> egui::FontDefinitions {
+    let mut fonts = egui::FontDefinitions::default();
+    let mut monospace = vec!["embedded-mono".to_string()];
+    fonts.font_data.insert(
+        "embedded-mono".to_string(),
+        egui::FontData::from_static(font::EMBEDDED_MONOSPACE),
+    );
+    if let Some(font_data) = font::load_system_monospace() {
+        fonts.font_data.insert(
+            "system-mono".to_string(),
+            egui::FontData::from_owned(font_data),
+        );
+        monospace.insert(0, "system-mono".to_string());
+    }
+    let family = fonts
+        .families
+        .entry(egui::FontFamily::Monospace)
+        .or_default();
+    family.splice(0..0, monospace);
+    // Without a CJK font those characters fall back to egui's replacement glyph.
+    if let Some(font_data) = load_system_chinese_font() {
+        fonts
+            .font_data
+            .insert("zh".to_string(), egui::FontData::from_owned(font_data));
+        for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+            fonts
+                .families
+                .entry(family)
+                .or_default()
+                .push("zh".to_string());
+        }
+    }
+    fonts
+}
 
-    let event_loop = EventLoop::new().expect("event loop");
+fn main() {
+    let args = cli::parse();
+    let startup_dir = resolve_startup_dir(args.cwd.clone());
+    let (app_config, settings_load_warning) = appconfig::load_config();
+    let (quickcmd_config, quickcmd_load_warning) = quickcmd::load_config();
+    for warning in [&settings_load_warning, &quickcmd_load_warning]
+        .into_iter()
+        .flatten()
+    {
+        eprintln!("{}", warning);
+    }
+    let shell_config = pty::ShellConfig {
+        program: args.shell.clone(),
+        prompt_integration: app_config.prompt_integration,
+        read_buffer_size: app_config.pty_read_buffer_size,
+        queue_capacity: app_config.pty_queue_capacity,
+        env: Vec::new(),
+    };
+    if let Some(mib) = args.bench_feed {
+        bench::run(mib, &shell_config);
+        return;
+    }
+    let startup_exec = args
+        .exec
+        .clone()
+        .map(|cmd| if args.exec_enter { cmd + "\r" } else { cmd });
+
+    let event_loop = EventLoopBuilder::<UserEvent>::with_user_event()
+        .build()
+        .expect("event loop");
+    let event_proxy = Mutex::new(event_loop.create_proxy());
+    let wakeup: terminal::Wakeup = Arc::new(move || {
+        if let Ok(proxy) = event_proxy.lock() {
+            let _ = proxy.send_event(UserEvent::Wakeup);
+        }
+    });
     let window = Arc::new(
         WindowBuilder::new()
-            .with_title("terminrt")
+            .with_title(WINDOW_TITLE)
             .with_inner_size(PhysicalSize::new(WINDOW_WIDTH, WINDOW_HEIGHT))
             .with_decorations(false)
             .with_visible(false)
@@ -1153,25 +2368,15 @@ fn main() {
     window.set_ime_allowed(true);
     window.set_ime_purpose(winit::window::ImePurpose::Terminal);
 
-    let mut state = pollster::block_on(State::new(window.clone()));
+    let fonts = build_font_definitions();
+    let mut state = pollster::block_on(State::new(
+        window.clone(),
+        app_config.present_mode,
+        args.glyph_preview,
+        font::FontRasterizer::from_egui_family(&fonts, &egui::FontFamily::Monospace),
+    ));
     let egui_ctx = egui::Context::default();
-    if let Some(font_data) = load_system_chinese_font() {
-        let mut fonts = egui::FontDefinitions::default();
-        fonts
-            .font_data
-            .insert("zh".to_string(), egui::FontData::from_owned(font_data));
-        fonts
-            .families
-            .get_mut(&egui::FontFamily::Proportional)
-            .unwrap()
-            .push("zh".to_string());
-        fonts
-            .families
-            .get_mut(&egui::FontFamily::Monospace)
-            .unwrap()
-            .push("zh".to_string());
-        egui_ctx.set_fonts(fonts);
-    }
+    egui_ctx.set_fonts(fonts);
     let mut egui_state = egui_winit::State::new(
         egui_ctx.clone(),
         egui::ViewportId::ROOT,
@@ -1181,50 +2386,151 @@ fn main() {
     );
     let mut egui_renderer = egui_wgpu::Renderer::new(&state.device, state.config.format, None, 1);
 
-    let mut terminal_init_rx = Some(spawn_terminal_async(startup_dir.clone()));
+    // The first session is spawned once a frame has measured the terminal
+    // area, so the shell starts at its real size.
+    let mut terminal_init_rx = None;
+    let config_rx = appconfig::spawn_config_watcher(wakeup.clone());
+    let control_rx = if args.stdin_control {
+        Some(control::spawn_stdin_listener(wakeup.clone()))
+    } else {
+        None
+    };
 
     let mut ui_state = UiState {
         terminal: None,
-        terminal_selection: terminal::TerminalSelectionState::default(),
+        terminal_selection: {
+            let mut selection = terminal::TerminalSelectionState::default();
+            selection.set_word_separators(&app_config.word_separators);
+            selection
+        },
+        terminal_render_cache: terminal::TerminalRenderCache::default(),
         pending_terminal: None,
         terminal_init_error: None,
         terminal_exited: false,
         terminal_connecting: true,
-        reconnect_requested: false,
+        reconnect_requested: true,
         terminal_scroll_request: None,
         terminal_scroll_request_frames_left: 0,
         terminal_scroll_id: 0,
         terminal_view_size_px: egui::Vec2::ZERO,
+        terminal_cell_size_px: egui::Vec2::ZERO,
+        initial_size_pending: app_config.initial_rows > 0 || app_config.initial_cols > 0,
         pty_render_size_px: egui::Vec2::ZERO,
         pty_grid_size: (0, 0),
         loading_started_at: Instant::now(),
         startup_dir,
+        shell_config,
+        cli_shell: args.shell.clone(),
+        cli_dir: args.cwd.clone(),
+        profile_name: None,
+        theme_colors: [None; 2],
+        choosing_profile: false,
+        demo: args.demo,
+        startup_exec,
         close_confirm_open: false,
         close_confirmed: false,
+        close_dont_ask: false,
+        window_focused: true,
+        reduce_motion: app_config.reduce_motion.resolve(),
+        last_key_input_at: Instant::now(),
+        auto_locked: false,
+        last_output_at: None,
+        copy_truncated_at: None,
+        command_notice: None,
+        exit_close_at: None,
         close_focus_pending: false,
         devtools_open: false,
         devtools_state: devtools::DevToolsState::default(),
-        quickcmd_config: quickcmd::load_config(),
-        settings_state: settings::SettingsState::default(),
+        quickcmd_config,
+        app_config,
+        settings_state: settings::SettingsState {
+            quickcmd_load_warning,
+            settings_load_warning,
+            ..Default::default()
+        },
+        palette_state: palette::PaletteState::default(),
+        search_state: search::SearchState::default(),
+        background: background::BackgroundImage::default(),
+        command_history: cmdhistory::load_history(),
         pending_quick_cmd: None,
+        quickcmd_confirm: None,
+        paste_confirm: None,
+        profile_switch_confirm: None,
         terminal_drop_rect: None,
         last_cursor_pos: None,
+        ime_preedit: None,
+        terminal_read_only: false,
+        pty_size_override: None,
+        cursor_locator_at: None,
     };
+    let requested_profile = args.profile.clone().or_else(|| {
+        Some(ui_state.app_config.default_profile.clone()).filter(|name| !name.is_empty())
+    });
+    let profile = requested_profile.and_then(|name| {
+        let found = ui_state
+            .app_config
+            .profiles
+            .iter()
+            .find(|p| p.name == name)
+            .cloned();
+        if found.is_none() {
+            eprintln!("Unknown profile: {}", name);
+        }
+        found
+    });
+    if profile.is_some() {
+        select_profile(&mut ui_state, profile.as_ref());
+    } else if !ui_state.app_config.profiles.is_empty() && !args.demo && !args.stdin_control {
+        // Automation and demo runs must not wait for a click.
+        ui_state.choosing_profile = true;
+        ui_state.reconnect_requested = false;
+    }
     let mut window_shown = false;
+    let mut last_frame_at = Instant::now();
+    // Redraws are event driven: window input, background wakeups and egui's own
+    // repaint requests all schedule one here, and `AboutToWait` honors it.
+    let mut repaint_at: Option<Instant> = Some(Instant::now());
 
     let mut current_modifiers = winit::event::Modifiers::default();
 
     let _ = event_loop.run(move |event, elwt| {
         match event {
             Event::WindowEvent { event, window_id } if window_id == state.window().id() => {
-                let terminal_input_active = ui_state.terminal.is_some()
+                let terminal_focused = ui_state.terminal.is_some()
                     && !ui_state.close_confirm_open
+                    && ui_state.quickcmd_confirm.is_none()
+                    && ui_state.paste_confirm.is_none()
+                    && ui_state.profile_switch_confirm.is_none()
                     && !ui_state.settings_state.open
+                    && !ui_state.palette_state.open
+                    && !ui_state.search_state.open
                     && !ui_state.terminal_exited;
+                let terminal_input_active = terminal_focused && !ui_state.terminal_read_only;
+
+                // Any key press or click keeps an exited session's window open;
+                // releases and auto-repeat of a key held from before don't.
+                let user_pressed = match &event {
+                    WindowEvent::KeyboardInput { event, .. } => {
+                        event.state.is_pressed() && !event.repeat
+                    }
+                    WindowEvent::MouseInput { state, .. } => state.is_pressed(),
+                    _ => false,
+                };
+                if user_pressed {
+                    ui_state.exit_close_at = None;
+                }
 
                 // Track modifier state
                 if let WindowEvent::ModifiersChanged(mods) = &event {
-                    current_modifiers = mods.clone();
+                    current_modifiers = *mods;
+                }
+
+                if matches!(
+                    &event,
+                    WindowEvent::KeyboardInput { event, .. } if event.state.is_pressed()
+                ) || matches!(&event, WindowEvent::Ime(winit::event::Ime::Commit(_)))
+                {
+                    ui_state.last_key_input_at = Instant::now();
                 }
 
                 if let WindowEvent::CursorMoved { position, .. } = &event {
@@ -1257,34 +2563,248 @@ fn main() {
                     }
                 }
 
+                if let WindowEvent::Ime(ime) = &event {
+                    match ime {
+                        winit::event::Ime::Preedit(text, cursor) if !text.is_empty() => {
+                            ui_state.ime_preedit = Some(terminal::ImePreedit {
+                                text: text.clone(),
+                                cursor: *cursor,
+                            });
+                        }
+                        _ => ui_state.ime_preedit = None,
+                    }
+                }
+
                 // Forward keyboard input to terminal BEFORE egui processes it
                 if let WindowEvent::Ime(winit::event::Ime::Commit(text)) = &event {
-                    if terminal_input_active && !text.is_empty() {
+                    if terminal_input_active
+                        && !text.is_empty()
+                        && !ui_state.terminal_selection.copy_mode_active()
+                    {
                         if let Some(ref mut terminal) = ui_state.terminal {
                             ui_state.terminal_scroll_request =
                                 Some(terminal::ScrollRequest::CursorLine);
                             ui_state.terminal_scroll_request_frames_left = 1;
-                            terminal.write_to_pty(text.as_bytes());
+                            terminal
+                                .write_keystrokes(text.as_bytes(), ui_state.app_config.local_echo);
                         }
                     }
                 }
 
                 if let WindowEvent::KeyboardInput { ref event, .. } = event {
+                    // A key handled as an app shortcut (read-only toggle, quick command) is
+                    // not also sent to the terminal (otherwise Alt-bound commands would
+                    // leak an ESC-prefixed key).
+                    let mut key_consumed = false;
+
+                    // Ctrl+Shift+<letter> app shortcuts. Ctrl+Shift+C without Alt,
+                    // and any letter whose shortcut does not apply right now, fall
+                    // through to the handling below.
+                    if let Some(letter) =
+                        ctrl_shift_letter(event, &current_modifiers).filter(|_| terminal_focused)
+                    {
+                        key_consumed = true;
+                        match letter {
+                            // Read-only mode.
+                            'r' => ui_state.terminal_read_only = !ui_state.terminal_read_only,
+                            // The left panel.
+                            'b' => toggle_left_panel(&mut ui_state),
+                            // The command palette.
+                            'p' => ui_state.palette_state.open(),
+                            // Another terminrt window.
+                            'n' => spawn_new_window(&ui_state),
+                            // Scrollback search.
+                            'f' => ui_state.search_state.open(),
+                            // With Alt, copy the selection with its colors and styles.
+                            'c' if current_modifiers.state().alt_key()
+                                && !ui_state.terminal_selection.copy_mode_active()
+                                && ui_state.terminal_selection.has_selection() =>
+                            {
+                                copy_terminal_selection(&mut ui_state, true);
+                            }
+                            // Flash the cursor locator, bringing the cursor into
+                            // view first.
+                            'l' if ui_state.app_config.cursor_locator => {
+                                ui_state.cursor_locator_at = Some(Instant::now());
+                                ui_state.terminal_scroll_request =
+                                    Some(terminal::ScrollRequest::CursorLine);
+                                ui_state.terminal_scroll_request_frames_left = 1;
+                            }
+                            // Clear the line being typed with whatever keys the
+                            // shell's line editor uses for it. Full-screen apps get
+                            // the key itself (plain Ctrl+U still sends ^U either way).
+                            'u' if terminal_input_active
+                                && ui_state
+                                    .terminal
+                                    .as_ref()
+                                    .is_some_and(|t| !t.is_alt_screen()) =>
+                            {
+                                if let Some(ref mut terminal) = ui_state.terminal {
+                                    ui_state.terminal_scroll_request =
+                                        Some(terminal::ScrollRequest::CursorLine);
+                                    ui_state.terminal_scroll_request_frames_left = 1;
+                                    terminal.write_keystrokes(
+                                        ui_state.shell_config.syntax().clear_line_keys(),
+                                        ui_state.app_config.local_echo,
+                                    );
+                                }
+                            }
+                            // Copy mode; while active every key drives the selection
+                            // cursor instead of reaching the shell.
+                            'm' => {
+                                if let Some(ref terminal) = ui_state.terminal {
+                                    if ui_state.terminal_selection.copy_mode_active() {
+                                        ui_state.terminal_selection.exit_copy_mode();
+                                    } else {
+                                        ui_state.terminal_selection.enter_copy_mode(terminal);
+                                    }
+                                }
+                            }
+                            _ => key_consumed = false,
+                        }
+                    }
+
+                    // Ctrl+Shift+C / Ctrl+Shift+V copy and paste. Plain Ctrl+C copies
+                    // too while text is selected (unless set to always interrupt);
+                    // otherwise it and Ctrl+V stay with the shell.
+                    if terminal_focused
+                        && !key_consumed
+                        && event.state.is_pressed()
+                        && current_modifiers.state().control_key()
+                        && !current_modifiers.state().alt_key()
+                        && !ui_state.terminal_selection.copy_mode_active()
+                    {
+                        let shift = current_modifiers.state().shift_key();
+                        let key = match &event.logical_key {
+                            winit::keyboard::Key::Character(text) => text.to_ascii_lowercase(),
+                            _ => String::new(),
+                        };
+                        let copy_on_ctrl_c = ui_state.app_config.ctrl_c
+                            == appconfig::CtrlCBehavior::CopyWhenSelected
+                            && ui_state.terminal_selection.has_selection();
+                        if key == "c" && (shift || copy_on_ctrl_c) {
+                            copy_terminal_selection(&mut ui_state, false);
+                            key_consumed = true;
+                        } else if key == "v" && shift {
+                            if let (Some(terminal), true) =
+                                (ui_state.terminal.as_mut(), terminal_input_active)
+                            {
+                                if let Ok(text) =
+                                    arboard::Clipboard::new().and_then(|mut cb| cb.get_text())
+                                {
+                                    terminal.paste_text(
+                                        &text,
+                                        ui_state.app_config.paste_line_ending,
+                                        ui_state.app_config.paste_strip_trailing_newline,
+                                        ui_state.app_config.bracketed_paste,
+                                        false,
+                                    );
+                                }
+                            }
+                            key_consumed = true;
+                        }
+                    }
+
+                    // Ctrl+Shift+Up / Ctrl+Shift+Down jump to the previous / next
+                    // prompt and Ctrl+Shift+O copies the last command's output,
+                    // both found with the prompt pattern. Without one the keys
+                    // go to the shell.
+                    if terminal_focused
+                        && !key_consumed
+                        && !ui_state.app_config.prompt_regex.is_empty()
+                        && event.state.is_pressed()
+                        && current_modifiers.state().control_key()
+                        && current_modifiers.state().shift_key()
+                    {
+                        if let Some(ref terminal) = ui_state.terminal {
+                            match &event.logical_key {
+                                winit::keyboard::Key::Named(
+                                    key @ (winit::keyboard::NamedKey::ArrowUp
+                                    | winit::keyboard::NamedKey::ArrowDown),
+                                ) => {
+                                    let top = ui_state.search_state.viewport_top_row();
+                                    let prompts = terminal.prompt_rows();
+                                    let target = if *key == winit::keyboard::NamedKey::ArrowUp {
+                                        prompts.iter().rev().find(|&&row| row < top)
+                                    } else {
+                                        prompts.iter().find(|&&row| row > top)
+                                    };
+                                    if let Some(&row) = target {
+                                        ui_state.terminal_scroll_request =
+                                            Some(terminal::ScrollRequest::Row(row));
+                                        ui_state.terminal_scroll_request_frames_left = 1;
+                                    }
+                                    key_consumed = true;
+                                }
+                                winit::keyboard::Key::Character(text)
+                                    if text.eq_ignore_ascii_case("o") && !event.repeat =>
+                                {
+                                    if let Some(output) = terminal.last_command_output() {
+                                        if let Ok(mut cb) = arboard::Clipboard::new() {
+                                            let _ = cb.set_text(output);
+                                        }
+                                    }
+                                    key_consumed = true;
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+
+                    if terminal_focused
+                        && !key_consumed
+                        && ui_state.terminal_selection.copy_mode_active()
+                    {
+                        if let (true, Some(terminal)) =
+                            (event.state.is_pressed(), ui_state.terminal.as_ref())
+                        {
+                            let action = ui_state.terminal_selection.handle_copy_mode_key(
+                                &event.logical_key,
+                                current_modifiers.state().shift_key(),
+                                terminal,
+                            );
+                            match action {
+                                terminal::CopyModeAction::Copy => {
+                                    if let Some((text, truncated)) =
+                                        terminal::selected_text_for_copy(
+                                            terminal,
+                                            &ui_state.terminal_selection,
+                                            ui_state.app_config.copy_filter,
+                                            ui_state.app_config.copy_filter_box_drawing,
+                                            ui_state.app_config.max_copy_bytes,
+                                        )
+                                    {
+                                        if truncated {
+                                            ui_state.copy_truncated_at = Some(Instant::now());
+                                        }
+                                        if let Ok(mut cb) = arboard::Clipboard::new() {
+                                            let _ = cb.set_text(text);
+                                        }
+                                    }
+                                    ui_state.terminal_selection.exit_copy_mode();
+                                }
+                                terminal::CopyModeAction::Exit => {
+                                    ui_state.terminal_selection.exit_copy_mode();
+                                }
+                                terminal::CopyModeAction::None => {}
+                            }
+                        }
+                        key_consumed = true;
+                    }
+
                     // --- Quick command keybinding matching ---
-                    if ENABLE_QUICKCMD_KEYBINDINGS
+                    if ui_state.app_config.quickcmd_keybindings
+                        && !key_consumed
                         && event.state.is_pressed()
                         && !event.repeat
-                        && !ui_state.close_confirm_open
-                        && !ui_state.settings_state.open
-                        && !ui_state.terminal_exited
-                        && ui_state.terminal.is_some()
+                        && terminal_input_active
                     {
                         let ctrl = current_modifiers.state().control_key();
                         let alt = current_modifiers.state().alt_key();
                         let shift = current_modifiers.state().shift_key();
                         let key_name = match &event.logical_key {
                             winit::keyboard::Key::Character(text) => {
-                                Some(format!("{}", text.to_uppercase()))
+                                Some(text.to_uppercase())
                             }
                             winit::keyboard::Key::Named(named) => {
                                 Some(format!("{:?}", named))
@@ -1302,16 +2822,32 @@ fn main() {
                                     shift,
                                     key: kn,
                                 };
-                                if let Some(cmd) = ui_state.quickcmd_config.find_by_keybinding(&probe) {
-                                    ui_state.pending_quick_cmd =
-                                        Some((cmd.command.clone(), cmd.auto_execute));
+                                let mode = ui_state
+                                    .quickcmd_config
+                                    .find_by_keybinding(&probe)
+                                    .map(|cmd| cmd.keybinding_mode);
+                                let fire = match mode {
+                                    Some(quickcmd::KeyBindingMode::AtPrompt) => {
+                                        shell_at_prompt(&ui_state)
+                                    }
+                                    Some(_) => true,
+                                    None => false,
+                                };
+                                if fire
+                                    && queue_saved_quick_command(
+                                        &mut ui_state,
+                                        QuickCommandLookup::KeyBinding(&probe),
+                                    )
+                                {
+                                    key_consumed =
+                                        mode != Some(quickcmd::KeyBindingMode::PassThrough);
                                 }
                             }
                         }
                     }
 
                     if let Some(ref mut terminal) = ui_state.terminal {
-                        if terminal_input_active {
+                        if terminal_input_active && !key_consumed {
                             let ctrl = current_modifiers.state().control_key();
                             let is_ctrl_l = ctrl
                                 && matches!(
@@ -1323,18 +2859,34 @@ fn main() {
                                 if event.state.is_pressed() && !event.repeat {
                                     ui_state.terminal_scroll_request =
                                         Some(terminal::ScrollRequest::ScreenTop);
-                                    ui_state.terminal_scroll_request_frames_left = 60;
+                                    // An animated jump needs the request held until
+                                    // the animation lands; a snap is done in one frame.
+                                    ui_state.terminal_scroll_request_frames_left =
+                                        if ui_state.app_config.animate_scrolling
+                                            && !ui_state.reduce_motion
+                                        {
+                                            60
+                                        } else {
+                                            1
+                                        };
                                     ui_state.terminal_scroll_id =
                                         ui_state.terminal_scroll_id.wrapping_add(1);
                                     terminal.write_to_pty(&[0x0c]);
                                 }
-                            } else if let Some(input_bytes) =
-                                terminal::key_to_terminal_input(event, &current_modifiers)
-                            {
+                            } else if let Some(input_bytes) = terminal::key_to_terminal_input(
+                                event,
+                                &current_modifiers,
+                                terminal.mode(),
+                                terminal.is_modify_other_keys_enabled(),
+                                ui_state.app_config.alt_sends_esc,
+                            ) {
                                 ui_state.terminal_scroll_request =
                                     Some(terminal::ScrollRequest::CursorLine);
                                 ui_state.terminal_scroll_request_frames_left = 1;
-                                terminal.write_to_pty(&input_bytes);
+                                terminal.write_keystrokes(
+                                    &input_bytes,
+                                    ui_state.app_config.local_echo,
+                                );
                             }
                         }
                     }
@@ -1346,31 +2898,50 @@ fn main() {
                     {
                         if let Some(ref mut terminal) = ui_state.terminal {
                             if !ui_state.close_confirm_open
+                                && ui_state.quickcmd_confirm.is_none()
+                                && ui_state.paste_confirm.is_none()
+                                && ui_state.profile_switch_confirm.is_none()
                                 && !ui_state.settings_state.open
                                 && !ui_state.terminal_exited
                             {
                                 if let Ok(mut cb) = arboard::Clipboard::new() {
                                     if ui_state.terminal_selection.has_selection() {
-                                        if let Some(text) = terminal::selected_text_for_copy(
-                                            terminal,
-                                            &ui_state.terminal_selection,
-                                        ) {
+                                        if let Some((text, truncated)) =
+                                            terminal::selected_text_for_copy(
+                                                terminal,
+                                                &ui_state.terminal_selection,
+                                                ui_state.app_config.copy_filter,
+                                                ui_state.app_config.copy_filter_box_drawing,
+                                                ui_state.app_config.max_copy_bytes,
+                                            )
+                                        {
+                                            if truncated {
+                                                ui_state.copy_truncated_at = Some(Instant::now());
+                                            }
                                             if !text.is_empty() {
                                                 let _ = cb.set_text(text);
                                             }
                                         }
                                         ui_state.terminal_selection.clear();
+                                    } else if ui_state.terminal_read_only {
+                                        // Paste is input; read-only mode only allows copy.
                                     } else if let Ok(text) = cb.get_text() {
-                                        if !text.is_empty() {
-                                            if terminal.is_bracketed_paste_enabled() {
-                                                let mut bytes = Vec::with_capacity(text.len() + 12);
-                                                bytes.extend_from_slice(b"\x1b[200~");
-                                                bytes.extend_from_slice(text.as_bytes());
-                                                bytes.extend_from_slice(b"\x1b[201~");
-                                                terminal.write_to_pty(&bytes);
-                                            } else {
-                                                terminal.write_to_pty(text.as_bytes());
-                                            }
+                                        // Shift+right-click pastes and runs; multi-line
+                                        // text is shown for review first.
+                                        let run = current_modifiers.state().shift_key();
+                                        let multi_line = text
+                                            .trim_end_matches(['\r', '\n'])
+                                            .contains(['\r', '\n']);
+                                        if run && multi_line {
+                                            ui_state.paste_confirm = Some(text);
+                                        } else {
+                                            terminal.paste_text(
+                                                &text,
+                                                ui_state.app_config.paste_line_ending,
+                                                ui_state.app_config.paste_strip_trailing_newline,
+                                                ui_state.app_config.bracketed_paste,
+                                                run,
+                                            );
                                         }
                                     }
                                 }
@@ -1380,6 +2951,16 @@ fn main() {
                 }
 
                 if let WindowEvent::Focused(focused) = &event {
+                    ui_state.window_focused = *focused;
+                    if *focused {
+                        window.request_user_attention(None);
+                        // Count the finish notice from when it can be seen.
+                        if let Some((_, _, shown_at)) = ui_state.command_notice.as_mut() {
+                            *shown_at = Instant::now();
+                        }
+                        // The OS preference may have changed while away.
+                        ui_state.reduce_motion = ui_state.app_config.reduce_motion.resolve();
+                    }
                     if let Some(ref mut terminal) = ui_state.terminal {
                         if !ui_state.close_confirm_open
                             && !ui_state.settings_state.open
@@ -1396,7 +2977,7 @@ fn main() {
                 // to avoid focus-navigation activating window controls.
                 let forward_to_egui = match &event {
                     WindowEvent::KeyboardInput { .. } | WindowEvent::Ime(_) => {
-                        !terminal_input_active
+                        !terminal_focused
                     }
                     _ => true,
                 };
@@ -1404,21 +2985,45 @@ fn main() {
                     let response = egui_state.on_window_event(window.as_ref(), &event);
                     let _ = response;
                 }
+                // Any input may change what is on screen (terminal keys go to the PTY
+                // and never reach egui), so repaint for everything but the redraw itself.
+                if !matches!(event, WindowEvent::RedrawRequested) {
+                    schedule_repaint(&mut repaint_at, Instant::now());
+                }
                 match event {
-                    WindowEvent::CloseRequested => {
-                        ui_state.close_confirm_open = true;
-                        ui_state.close_focus_pending = true;
-                        state.window().request_redraw();
-                    }
+                    WindowEvent::CloseRequested => request_close(&mut ui_state),
                     WindowEvent::Resized(size) => state.resize(size),
                     WindowEvent::RedrawRequested => {
+                        last_frame_at = Instant::now();
+                        repaint_at = None;
                         let loading_elapsed = ui_state.loading_started_at.elapsed().as_secs_f32();
 
                         if ui_state.reconnect_requested && terminal_init_rx.is_none() {
-                            terminal_init_rx = Some(spawn_terminal_async(ui_state.startup_dir.clone()));
-                            ui_state.reconnect_requested = false;
-                            ui_state.terminal_connecting = true;
-                            ui_state.terminal_init_error = None;
+                            match spawn_grid_size(&ui_state) {
+                                Some(grid_size) => {
+                                    if ui_state.initial_size_pending {
+                                        fit_window_to_grid(
+                                            &window,
+                                            &ui_state,
+                                            grid_size,
+                                            egui_ctx.pixels_per_point(),
+                                        );
+                                        ui_state.initial_size_pending = false;
+                                    }
+                                    terminal_init_rx = Some(spawn_terminal_async(
+                                        grid_size,
+                                        ui_state.startup_dir.clone(),
+                                        ui_state.shell_config.clone(),
+                                        ui_state.demo,
+                                        wakeup.clone(),
+                                    ));
+                                    ui_state.reconnect_requested = false;
+                                    ui_state.terminal_connecting = true;
+                                    ui_state.terminal_init_error = None;
+                                }
+                                // Nothing measured yet; the next frame will have it.
+                                None => egui_ctx.request_repaint(),
+                            }
                         }
 
                         if let Some(rx) = terminal_init_rx.as_ref() {
@@ -1448,10 +3053,47 @@ fn main() {
 
                         if let Some(term) = ui_state.pending_terminal.take() {
                             if ui_state.terminal.is_none()
-                                && !startup_page::is_animation_done(loading_elapsed)
+                                && !startup_page::is_animation_done(
+                                    loading_elapsed,
+                                    ui_state.reduce_motion,
+                                )
                             {
                                 ui_state.pending_terminal = Some(term);
+                                // Attach it once the startup animation finishes.
+                                egui_ctx.request_repaint();
                             } else {
+                                let mut term = term;
+                                if ui_state.app_config.keep_history_on_reconnect {
+                                    if let Some(old) = ui_state.terminal.as_ref() {
+                                        term.seed_history(&old.dump_scrollback());
+                                    }
+                                }
+                                term.set_tab_width(ui_state.app_config.tab_width);
+                                term.set_theme_colors(
+                                    ui_state.theme_colors[0],
+                                    ui_state.theme_colors[1],
+                                );
+                                term.set_prompt_regex(&ui_state.app_config.prompt_regex);
+                                term.set_input_logging(
+                                    ui_state.app_config.input_log,
+                                    ui_state.app_config.password_guard,
+                                );
+                                term.set_default_cursor_style(
+                                    ui_state.app_config.cursor_shape,
+                                    ui_state.app_config.cursor_blink,
+                                );
+                                if let Some(cmd) = ui_state.startup_exec.take() {
+                                    term.write_to_pty(cmd.as_bytes());
+                                }
+                                // Mirror the application's title (OSC 0/2) on the
+                                // window so it shows in the taskbar.
+                                window.set_title(WINDOW_TITLE);
+                                let title_window = window.clone();
+                                term.set_on_title(move |title| {
+                                    title_window.set_title(title.unwrap_or(WINDOW_TITLE));
+                                });
+                                let exit_window = window.clone();
+                                term.set_on_exit(move || exit_window.set_title(WINDOW_TITLE));
                                 ui_state.terminal = Some(term);
                                 ui_state.terminal_selection.clear();
                                 ui_state.terminal_exited = false;
@@ -1464,15 +3106,101 @@ fn main() {
                         }
 
                         // Process PTY output before rendering
+                        let mut resize_request = None;
                         if let Some(ref mut terminal) = ui_state.terminal {
                             let process_result = terminal.process_input();
+                            resize_request = process_result.resize_request;
+                            let executed = terminal.take_executed_commands();
+                            if ui_state.app_config.record_command_history && !executed.is_empty() {
+                                for (dir, command) in &executed {
+                                    ui_state.command_history.record(dir, command);
+                                }
+                                cmdhistory::save_history(&ui_state.command_history);
+                            }
+                            if let Some(done) = terminal.take_finished_commands().pop() {
+                                let failed = done.exit_code.is_some_and(|code| code != 0);
+                                let outcome = match done.exit_code {
+                                    Some(code) => format!("exit {}", code),
+                                    None => "done".to_string(),
+                                };
+                                ui_state.command_notice = Some((
+                                    format!(
+                                        "{}: {} after {}",
+                                        done.label,
+                                        outcome,
+                                        format_elapsed(done.elapsed)
+                                    ),
+                                    if failed {
+                                        egui::Color32::from_rgb(220, 90, 90)
+                                    } else {
+                                        egui::Color32::from_rgb(100, 200, 100)
+                                    },
+                                    Instant::now(),
+                                ));
+                                if !ui_state.window_focused {
+                                    window.request_user_attention(Some(if failed {
+                                        winit::window::UserAttentionType::Critical
+                                    } else {
+                                        winit::window::UserAttentionType::Informational
+                                    }));
+                                }
+                            }
+                            if process_result.bell
+                                && ui_state.app_config.bell_attention
+                                && !ui_state.window_focused
+                            {
+                                window.request_user_attention(Some(
+                                    winit::window::UserAttentionType::Informational,
+                                ));
+                            }
                             if process_result.had_input {
+                                ui_state.search_state.mark_buffer_changed();
+                                ui_state.last_output_at = Some(Instant::now());
+                            }
+                            // Entering or leaving the alternate screen swaps grids; start
+                            // from a fresh scroll state showing the live screen, like
+                            // Ctrl+L, so no stale offset from the other grid lingers.
+                            if process_result.alt_screen_toggled {
+                                ui_state.terminal_scroll_request =
+                                    Some(terminal::ScrollRequest::ScreenTop);
+                                ui_state.terminal_scroll_request_frames_left = 30;
+                                ui_state.terminal_scroll_id =
+                                    ui_state.terminal_scroll_id.wrapping_add(1);
+                            }
+                            // After `ESC[2J` show the cleared screen rather than the
+                            // cursor at the bottom under the old lines, which now sit
+                            // just above it in scrollback. `ESC[3J` shortened the
+                            // scrollback, so a fresh scroll state drops the stale
+                            // offset, and selection and copy mode rows no longer
+                            // match the text; copy mode ends with them.
+                            if process_result.scrollback_erased {
+                                ui_state.terminal_selection.exit_copy_mode();
+                            }
+                            if (process_result.screen_erased || process_result.scrollback_erased)
+                                && !ui_state.terminal_selection.copy_mode_active()
+                                && !ui_state.search_state.open
+                            {
+                                ui_state.terminal_scroll_request =
+                                    Some(terminal::ScrollRequest::ScreenTop);
+                                ui_state.terminal_scroll_request_frames_left = 30;
+                                ui_state.terminal_scroll_id =
+                                    ui_state.terminal_scroll_id.wrapping_add(1);
+                            }
+                            // Copy mode and search read scrollback; don't let output
+                            // yank the view.
+                            if process_result.had_input
+                                && !ui_state.terminal_selection.copy_mode_active()
+                                && !ui_state.search_state.open
+                            {
                                 // Don't downgrade a ScreenTop request (e.g. from Ctrl+L) to
                                 // CursorLine – the ScreenTop scroll must persist for its full
                                 // frame budget so the viewport stays at the right position.
                                 let has_screen_top = matches!(
                                     ui_state.terminal_scroll_request,
-                                    Some(terminal::ScrollRequest::ScreenTop)
+                                    Some(
+                                        terminal::ScrollRequest::ScreenTop
+                                            | terminal::ScrollRequest::ScrollbackTop
+                                    )
                                 ) && ui_state.terminal_scroll_request_frames_left > 0;
                                 if !has_screen_top {
                                     ui_state.terminal_scroll_request =
@@ -1481,18 +3209,141 @@ fn main() {
                                 }
                             }
                             if process_result.pty_closed || !terminal.is_alive() {
+                                if !ui_state.terminal_exited
+                                    && !ui_state.app_config.hold_open_on_exit
+                                {
+                                    ui_state.exit_close_at = Some(
+                                        Instant::now()
+                                            + Duration::from_secs(
+                                                ui_state.app_config.close_delay_secs as u64,
+                                            ),
+                                    );
+                                }
                                 ui_state.terminal_exited = true;
                                 ui_state.terminal_connecting = false;
                             }
                         }
+                        // The grid follows the window, so a request is applied by
+                        // resizing the window; a fixed control-protocol size wins.
+                        if let Some((rows, cols)) = resize_request {
+                            if ui_state.app_config.allow_resize_requests
+                                && ui_state.pty_size_override.is_none()
+                                && !window.is_maximized()
+                                && window.fullscreen().is_none()
+                            {
+                                let rows =
+                                    rows.clamp(terminal::MIN_GRID_ROWS, control::MAX_GRID_DIM);
+                                let cols =
+                                    cols.clamp(terminal::MIN_GRID_COLS, control::MAX_GRID_DIM);
+                                fit_window_to_grid(
+                                    &window,
+                                    &ui_state,
+                                    (rows, cols),
+                                    egui_ctx.pixels_per_point(),
+                                );
+                            }
+                        }
+
+                        // Pick up settings.json edited outside the app
+                        if let Some(config) = config_rx.try_iter().last() {
+                            if config != ui_state.app_config {
+                                ui_state.app_config = config;
+                                apply_app_config(&mut ui_state);
+                            }
+                        }
+
+                        // Apply automation commands read from stdin (--stdin-control)
+                        let mut quick_command_requests = Vec::new();
+                        if let (Some(rx), Some(terminal)) =
+                            (control_rx.as_ref(), ui_state.terminal.as_mut())
+                        {
+                            while let Ok(cmd) = rx.try_recv() {
+                                match cmd {
+                                    control::ControlCommand::Write { data } => {
+                                        if ui_state.terminal_exited {
+                                            control::reply_error("terminal exited");
+                                        } else if ui_state.terminal_read_only {
+                                            control::reply_error("terminal is read-only");
+                                        } else {
+                                            terminal.write_to_pty(data.as_bytes());
+                                        }
+                                    }
+                                    control::ControlCommand::Resize { rows, cols } => {
+                                        let rows =
+                                            rows.clamp(terminal::MIN_GRID_ROWS, control::MAX_GRID_DIM);
+                                        let cols =
+                                            cols.clamp(terminal::MIN_GRID_COLS, control::MAX_GRID_DIM);
+                                        ui_state.pty_size_override = Some((rows, cols));
+                                        terminal.resize(rows, cols);
+                                    }
+                                    control::ControlCommand::Snapshot => {
+                                        control::reply_snapshot(
+                                            terminal.rows(),
+                                            terminal.cols(),
+                                            &terminal.screen_text(),
+                                        );
+                                    }
+                                    control::ControlCommand::QuickCommand { id, name } => {
+                                        if ui_state.terminal_exited {
+                                            control::reply_error("terminal exited");
+                                        } else if ui_state.terminal_read_only {
+                                            control::reply_error("terminal is read-only");
+                                        } else {
+                                            quick_command_requests.push((id, name));
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                        // Queued after the loop: queueing needs all of `ui_state`.
+                        for (id, name) in quick_command_requests {
+                            let (lookup, what) = match (id.as_deref(), name.as_deref()) {
+                                (Some(id), _) => (QuickCommandLookup::Id(id), "id"),
+                                (None, Some(name)) => (QuickCommandLookup::Name(name), "name"),
+                                (None, None) => {
+                                    control::reply_error("quick_command needs an id or a name");
+                                    continue;
+                                }
+                            };
+                            if !queue_saved_quick_command(&mut ui_state, lookup) {
+                                control::reply_error(&format!(
+                                    "no quick command with {} {:?}",
+                                    what,
+                                    id.or(name).unwrap_or_default()
+                                ));
+                            }
+                        }
 
                         // Execute pending quick command (from UI click or keybinding)
-                        if let Some((cmd_text, auto_exec)) = ui_state.pending_quick_cmd.take() {
+                        if let Some((cmd_text, auto_exec, notify)) =
+                            ui_state.pending_quick_cmd.take()
+                        {
                             if let Some(ref mut terminal) = ui_state.terminal {
-                                if !ui_state.terminal_exited {
-                                    terminal.write_to_pty(cmd_text.as_bytes());
+                                if !ui_state.terminal_exited && !ui_state.terminal_read_only {
+                                    let notify = notify
+                                        .filter(|_| ui_state.app_config.quickcmd_finish_alerts);
+                                    if let Some(label) = notify {
+                                        if !terminal.watch_next_command(&label) {
+                                            let notice = format!(
+                                                "{}: not watched (no shell integration)",
+                                                label
+                                            );
+                                            ui_state.command_notice = Some((
+                                                notice,
+                                                egui::Color32::from_rgb(190, 150, 80),
+                                                Instant::now(),
+                                            ));
+                                        }
+                                    }
+                                    let (text, chars_after_marker) =
+                                        quickcmd::resolve_cursor_marker(&cmd_text);
+                                    terminal.write_to_pty(text.as_bytes());
                                     if auto_exec {
                                         terminal.write_to_pty(b"\r");
+                                    } else if chars_after_marker > 0 {
+                                        // Walk the cursor back to the `{|}` marker.
+                                        let left = b"\x1b[D".repeat(chars_after_marker);
+                                        terminal.write_to_pty(&left);
                                     }
                                     ui_state.terminal_scroll_request =
                                         Some(terminal::ScrollRequest::CursorLine);
@@ -1511,6 +3362,34 @@ fn main() {
                             elwt.exit();
                             return;
                         }
+                        if let Some(at) = ui_state.exit_close_at {
+                            let now = Instant::now();
+                            if now >= at {
+                                elwt.exit();
+                                return;
+                            }
+                            // Tick the countdown shown in the top bar.
+                            let remaining = at - now;
+                            let tick = Duration::from_nanos(
+                                (remaining.as_nanos() % 1_000_000_000) as u64,
+                            );
+                            egui_ctx.request_repaint_after(if tick.is_zero() {
+                                Duration::from_secs(1)
+                            } else {
+                                tick
+                            });
+                        }
+
+                        state.set_present_mode(ui_state.app_config.present_mode);
+
+                        let repaint_delay = full_output
+                            .viewport_output
+                            .get(&egui::ViewportId::ROOT)
+                            .map(|viewport| viewport.repaint_delay)
+                            .unwrap_or(Duration::MAX);
+                        if let Some(at) = Instant::now().checked_add(repaint_delay) {
+                            schedule_repaint(&mut repaint_at, at);
+                        }
 
                         egui_state
                             .handle_platform_output(window.as_ref(), full_output.platform_output);
@@ -1556,6 +3435,9 @@ fn main() {
                     _ => {}
                 }
             }
+            Event::UserEvent(UserEvent::Wakeup) => {
+                schedule_repaint(&mut repaint_at, Instant::now());
+            }
             Event::AboutToWait => {
                 // If the hidden window never gets a redraw while invisible on some platforms,
                 // force-show it here so rendering can proceed.
@@ -1563,16 +3445,47 @@ fn main() {
                     state.window().set_visible(true);
                     window_shown = true;
                 }
-                state.window().request_redraw();
+                let Some(mut next_frame_at) = repaint_at else {
+                    elwt.set_control_flow(ControlFlow::Wait);
+                    return;
+                };
+                if ui_state.app_config.max_fps > 0 {
+                    let interval =
+                        Duration::from_secs_f64(1.0 / ui_state.app_config.max_fps as f64);
+                    next_frame_at = next_frame_at.max(last_frame_at + interval);
+                }
+                if Instant::now() < next_frame_at {
+                    elwt.set_control_flow(ControlFlow::WaitUntil(next_frame_at));
+                } else {
+                    elwt.set_control_flow(ControlFlow::Wait);
+                    state.window().request_redraw();
+                }
             }
             _ => {}
         }
     });
 }
 
-fn resolve_startup_dir() -> PathBuf {
+/// Map the configured present mode onto one the surface supports. Fifo is
+/// guaranteed to be available, so it is the fallback.
+fn choose_present_mode(
+    supported: &[wgpu::PresentMode],
+    pref: appconfig::PresentMode,
+) -> wgpu::PresentMode {
+    let wanted = match pref {
+        appconfig::PresentMode::Fifo => wgpu::PresentMode::Fifo,
+        appconfig::PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+        appconfig::PresentMode::Immediate => wgpu::PresentMode::Immediate,
+    };
+    if supported.contains(&wanted) {
+        wanted
+    } else {
+        wgpu::PresentMode::Fifo
+    }
+}
+
+fn resolve_startup_dir(arg_dir: Option<PathBuf>) -> PathBuf {
     let default_dir = PathBuf::from("C:\\");
-    let arg_dir = std::env::args_os().nth(1).map(PathBuf::from);
 
     match arg_dir {
         Some(path) if path.is_dir() => path,
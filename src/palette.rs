@@ -0,0 +1,198 @@
+use egui::{self, Color32, Key, RichText};
+
+use crate::devtools::QuickCmdAction;
+use crate::quickcmd::QuickCommandConfig;
+
+/// Entries shown at most, after filtering.
+const MAX_VISIBLE_ITEMS: usize = 12;
+
+#[derive(Default)]
+pub struct PaletteState {
+    pub open: bool,
+    query: String,
+    selected: usize,
+    focus_pending: bool,
+}
+
+impl PaletteState {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.query.clear();
+        self.selected = 0;
+        self.focus_pending = true;
+    }
+}
+
+enum PaletteItem<'a> {
+    /// A command previously run in the current directory; pasted, not run.
+    History(&'a str),
+    Quick {
+        name: &'a str,
+        command: &'a str,
+        auto_execute: bool,
+        confirm: bool,
+        cwd: Option<&'a str>,
+        /// See `QuickCmdAction::notify`.
+        notify: Option<String>,
+    },
+}
+
+impl PaletteItem<'_> {
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        match self {
+            PaletteItem::History(command) => command.to_lowercase().contains(&query),
+            PaletteItem::Quick { name, command, .. } => {
+                name.to_lowercase().contains(&query) || command.to_lowercase().contains(&query)
+            }
+        }
+    }
+
+    fn action(&self) -> QuickCmdAction {
+        match self {
+            PaletteItem::History(command) => QuickCmdAction {
+                command: command.to_string(),
+                auto_execute: false,
+                confirm: false,
+                cwd: None,
+                notify: None,
+            },
+            PaletteItem::Quick {
+                command,
+                auto_execute,
+                confirm,
+                cwd,
+                notify,
+                ..
+            } => QuickCmdAction {
+                command: command.to_string(),
+                auto_execute: *auto_execute,
+                confirm: *confirm,
+                cwd: cwd.map(str::to_string),
+                notify: notify.clone(),
+            },
+        }
+    }
+}
+
+/// Command palette (Ctrl+Shift+P): directory history suggestions first, then
+/// quick commands. Returns the command picked this frame, if any.
+pub fn render(
+    ctx: &egui::Context,
+    state: &mut PaletteState,
+    quickcmds: &QuickCommandConfig,
+    dir_history: &[String],
+) -> Option<QuickCmdAction> {
+    if !state.open {
+        return None;
+    }
+
+    let items: Vec<PaletteItem> = dir_history
+        .iter()
+        .map(|c| PaletteItem::History(c))
+        .chain(quickcmds.commands.iter().map(|c| PaletteItem::Quick {
+            name: &c.name,
+            command: &c.command,
+            auto_execute: c.auto_execute,
+            confirm: c.confirm,
+            cwd: c.cwd.as_deref(),
+            notify: c.notify_label(),
+        }))
+        .filter(|item| item.matches(&state.query))
+        .take(MAX_VISIBLE_ITEMS)
+        .collect();
+
+    let (escape, up, down, enter) = ctx.input(|i| {
+        (
+            i.key_pressed(Key::Escape),
+            i.key_pressed(Key::ArrowUp),
+            i.key_pressed(Key::ArrowDown),
+            i.key_pressed(Key::Enter),
+        )
+    });
+    if escape {
+        state.open = false;
+        return None;
+    }
+    if down && state.selected + 1 < items.len() {
+        state.selected += 1;
+    }
+    if up {
+        state.selected = state.selected.saturating_sub(1);
+    }
+    state.selected = state.selected.min(items.len().saturating_sub(1));
+
+    let mut picked = None;
+    if enter {
+        picked = items.get(state.selected).map(PaletteItem::action);
+    }
+
+    let screen_rect = ctx.screen_rect();
+    let width = 480.0_f32.min(screen_rect.width() - 40.0).max(200.0);
+    egui::Window::new("Command Palette")
+        .id(egui::Id::new("command_palette"))
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .fixed_pos(egui::pos2(
+            screen_rect.center().x - width * 0.5,
+            screen_rect.top() + 60.0,
+        ))
+        .fixed_size(egui::vec2(width, 0.0))
+        .frame(
+            egui::Frame::none()
+                .fill(Color32::from_rgb(28, 28, 28))
+                .stroke(egui::Stroke::new(1.0, Color32::from_gray(80)))
+                .rounding(egui::Rounding::same(6.0))
+                .inner_margin(egui::Margin::same(8.0)),
+        )
+        .show(ctx, |ui| {
+            let query = ui.add(
+                egui::TextEdit::singleline(&mut state.query)
+                    .hint_text("Type to filter commands...")
+                    .font(egui::TextStyle::Monospace)
+                    .desired_width(f32::INFINITY),
+            );
+            if state.focus_pending {
+                query.request_focus();
+                state.focus_pending = false;
+            }
+            if query.changed() {
+                state.selected = 0;
+            }
+
+            ui.add_space(4.0);
+            if items.is_empty() {
+                ui.label(
+                    RichText::new("No matching commands")
+                        .monospace()
+                        .size(12.0)
+                        .color(Color32::from_gray(120)),
+                );
+            }
+            for (idx, item) in items.iter().enumerate() {
+                let (badge, text) = match item {
+                    PaletteItem::History(command) => ("↺", command.to_string()),
+                    PaletteItem::Quick { name, command, .. } => {
+                        ("⚡", format!("{}  {}", name, command))
+                    }
+                };
+                let label = RichText::new(format!("{} {}", badge, text))
+                    .monospace()
+                    .size(12.0)
+                    .color(Color32::from_gray(210));
+                let response = ui.add_sized(
+                    egui::vec2(ui.available_width(), 20.0),
+                    egui::SelectableLabel::new(idx == state.selected, label),
+                );
+                if response.clicked() {
+                    picked = Some(item.action());
+                }
+            }
+        });
+
+    if picked.is_some() {
+        state.open = false;
+    }
+    picked
+}
@@ -1,13 +1,77 @@
+use std::io;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::appconfig::PromptIntegration;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub struct PtySize {
     pub rows: u16,
     pub cols: u16,
 }
 
+/// What to launch inside the PTY.
+#[derive(Clone, Debug, Default)]
+pub struct ShellConfig {
+    /// Shell executable; `None` runs the default PowerShell.
+    pub program: Option<String>,
+    /// How PowerShell's prompt is hooked to report the CWD.
+    pub prompt_integration: PromptIntegration,
+    /// Bytes requested per read from the PTY.
+    pub read_buffer_size: usize,
+    /// Reads queued for the UI before the reader thread blocks.
+    pub queue_capacity: usize,
+    /// Variables set on top of the inherited environment.
+    pub env: Vec<(String, String)>,
+}
+
+/// Command-line syntax of the configured shell, for text typed into it on the
+/// user's behalf.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShellSyntax {
+    PowerShell,
+    Cmd,
+    /// bash, zsh, sh and other POSIX-style shells.
+    Posix,
+}
+
+impl ShellSyntax {
+    /// Keys that discard the line being edited. Escape is RevertLine in
+    /// PSReadLine's default (Windows) edit mode and clears cmd's line;
+    /// readline-style editors get Ctrl+E Ctrl+U (end of line, kill to start),
+    /// so text right of the cursor goes too.
+    pub fn clear_line_keys(self) -> &'static [u8] {
+        match self {
+            ShellSyntax::PowerShell | ShellSyntax::Cmd => b"\x1b",
+            ShellSyntax::Posix => b"\x05\x15",
+        }
+    }
+}
+
+impl ShellConfig {
+    pub fn syntax(&self) -> ShellSyntax {
+        let Some(program) = self.program.as_deref() else {
+            return ShellSyntax::PowerShell;
+        };
+        let stem = Path::new(program)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match stem.as_str() {
+            "powershell" | "pwsh" => ShellSyntax::PowerShell,
+            "cmd" => ShellSyntax::Cmd,
+            _ => ShellSyntax::Posix,
+        }
+    }
+}
+
 #[cfg(windows)]
 mod platform {
     use std::io::{self, Read, Write};
     use std::path::Path;
 
+    use crate::appconfig::PromptIntegration;
+
     /// Readable end of the PTY — goes to the background reader thread.
     pub struct PtyReader {
         reader: conpty::io::PipeReader,
@@ -15,8 +79,8 @@ mod platform {
 
     unsafe impl Send for PtyReader {}
 
-    impl PtyReader {
-        pub fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+    impl Read for PtyReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
             self.reader.read(buf)
         }
     }
@@ -44,15 +108,61 @@ mod platform {
         }
     }
 
-    pub fn spawn(size: super::PtySize, startup_dir: &Path) -> io::Result<(PtyReader, PtyWriter)> {
-        let mut shell = std::process::Command::new("powershell.exe");
+    const DEFAULT_SHELL: &str = "powershell.exe";
+
+    /// Reports the last history entry once as OSC 633;D with its exit code
+    /// (from `$ok` and `$lec`, the prompt's captures of `$?` and
+    /// `$LASTEXITCODE` before anything else ran), then as OSC 633;E,
+    /// escaping `\`, `;` and control characters as `\xNN`. Sent before the CWD
+    /// mark so the command is attributed to the directory it ran in.
+    const COMMAND_MARK: &str = "$h = Get-History -Count 1; if ($h -and $h.Id -ne $global:__terminrt_last_id) { $global:__terminrt_last_id = $h.Id; $code = if ($ok) { 0 } elseif ($lec) { $lec } else { 1 }; Write-Host -NoNewline ($esc + ']633;D;' + $code + $bel); $c = $h.CommandLine -replace '\\\\','\\\\' -replace ';','\\x3b' -replace [char]10,'\\x0a' -replace [char]13,'\\x0d' -replace [char]7,'\\x07' -replace [char]27,'\\x1b'; Write-Host -NoNewline ($esc + ']633;E;' + $c + $bel) }";
+    const CWD_MARK: &str =
+        "Write-Host -NoNewline ($esc + ']633;CWD=' + (Get-Location).Path + $bel)";
+    /// Wraps PSReadLine's line editor to send OSC 633;C once Enter is pressed,
+    /// so the UI knows a command is running until the next prompt.
+    const COMMAND_START_HOOK: &str = "if (Test-Path Function:\\PSConsoleHostReadLine) { $global:__terminrt_readline = $function:PSConsoleHostReadLine; function global:PSConsoleHostReadLine { $line = & $global:__terminrt_readline; [Console]::Write([char]27 + ']633;C' + [char]7); $line } }";
+
+    fn prompt_script(integration: PromptIntegration) -> Option<String> {
+        match integration {
+            // Run whatever prompt the profile defined, then emit the marks. The
+            // captures reset `$?`, so a failure is replayed with a no-op error
+            // first; `$LASTEXITCODE` is put back for the next command line.
+            PromptIntegration::Wrap => Some(format!(
+                "{}; $global:__terminrt_prompt = $function:prompt; function global:prompt {{ $ok=$global:?; $lec=$global:LASTEXITCODE; $esc=[char]27; $bel=[char]7; if (-not $ok) {{ Write-Error '' -ErrorAction Ignore }}; $p = & $global:__terminrt_prompt; {}; {}; $global:LASTEXITCODE=$lec; $p }}",
+                COMMAND_START_HOOK, COMMAND_MARK, CWD_MARK
+            )),
+            // Emit the marks, then a plain `PS <dir>> ` prompt.
+            PromptIntegration::Replace => Some(format!(
+                "{}; function global:prompt {{ $ok=$global:?; $lec=$global:LASTEXITCODE; $esc=[char]27; $bel=[char]7; {}; {}; $global:LASTEXITCODE=$lec; 'PS ' + (Get-Location).Path + '> ' }}",
+                COMMAND_START_HOOK, COMMAND_MARK, CWD_MARK
+            )),
+            PromptIntegration::Off => None,
+        }
+    }
+
+    pub fn spawn(
+        size: super::PtySize,
+        startup_dir: &Path,
+        shell_config: &super::ShellConfig,
+    ) -> io::Result<(PtyReader, PtyWriter)> {
+        let program = shell_config.program.as_deref().unwrap_or(DEFAULT_SHELL);
+        let mut shell = std::process::Command::new(program);
 
-        shell
-            .arg("-NoLogo")
-            .arg("-NoExit")
-            .arg("-Command")
-            .arg("function global:prompt { $p=(Get-Location).Path; $esc=[char]27; $bel=[char]7; Write-Host -NoNewline ($esc + ']633;CWD=' + $p + $bel); 'PS ' + $p + '> ' }")
-            .current_dir(startup_dir);
+        // The CWD-reporting prompt is PowerShell syntax; other shells run as-is.
+        // `-Command` runs after the profile, so `Wrap` sees the user's prompt.
+        if shell_config.syntax() == super::ShellSyntax::PowerShell {
+            shell.arg("-NoLogo");
+            if let Some(script) = prompt_script(shell_config.prompt_integration) {
+                shell.arg("-NoExit").arg("-Command").arg(script);
+            }
+        }
+        shell.current_dir(startup_dir);
+        if !shell_config.env.is_empty() {
+            // conpty hands only the explicitly set variables to the child, so
+            // start from a copy of our own environment.
+            shell.envs(std::env::vars_os());
+            shell.envs(shell_config.env.iter().map(|(k, v)| (k, v)));
+        }
 
         let mut process = conpty::ProcessOptions::default()
             .set_console_size(Some((size.cols as i16, size.rows as i16)))
@@ -77,8 +187,8 @@ mod platform {
 
     pub struct PtyReader;
 
-    impl PtyReader {
-        pub fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+    impl io::Read for PtyReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
             unimplemented!("PTY not yet implemented for this platform")
         }
     }
@@ -99,7 +209,11 @@ mod platform {
         }
     }
 
-    pub fn spawn(_size: super::PtySize, _startup_dir: &Path) -> io::Result<(PtyReader, PtyWriter)> {
+    pub fn spawn(
+        _size: super::PtySize,
+        _startup_dir: &Path,
+        _shell_config: &super::ShellConfig,
+    ) -> io::Result<(PtyReader, PtyWriter)> {
         // TODO: implement Unix PTY (e.g. using nix or rustix)
         Err(io::Error::new(
             io::ErrorKind::Unsupported,
@@ -108,5 +222,130 @@ mod platform {
     }
 }
 
-pub use platform::spawn as spawn_pty;
-pub use platform::{PtyReader, PtyWriter};
+use platform::{PtyReader, PtyWriter};
+
+/// What `TerminalInstance` needs from a PTY. `SystemPty` is the real one,
+/// `MemoryPty` replays canned output without a shell.
+///
+/// Reads block, so the output side is handed to the reader thread once with
+/// `take_reader`; the rest is only called from the thread owning the terminal.
+pub trait PtyBackend: Send {
+    /// The output stream. Called once; later calls get an empty reader.
+    fn take_reader(&mut self) -> Box<dyn io::Read + Send>;
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()>;
+    fn resize(&mut self, size: PtySize) -> io::Result<()>;
+    fn is_alive(&self) -> bool;
+}
+
+/// The platform PTY running the configured shell (ConPTY on Windows).
+pub struct SystemPty {
+    reader: Option<PtyReader>,
+    writer: PtyWriter,
+}
+
+impl SystemPty {
+    pub fn spawn(
+        size: PtySize,
+        startup_dir: &Path,
+        shell_config: &ShellConfig,
+    ) -> io::Result<Self> {
+        let (reader, writer) = platform::spawn(size, startup_dir, shell_config)?;
+        Ok(Self {
+            reader: Some(reader),
+            writer,
+        })
+    }
+}
+
+impl PtyBackend for SystemPty {
+    fn take_reader(&mut self) -> Box<dyn io::Read + Send> {
+        match self.reader.take() {
+            Some(reader) => Box::new(reader),
+            None => Box::new(io::empty()),
+        }
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.writer.write_all(data)
+    }
+
+    fn resize(&mut self, size: PtySize) -> io::Result<()> {
+        self.writer.resize(size)
+    }
+
+    fn is_alive(&self) -> bool {
+        self.writer.is_alive()
+    }
+}
+
+/// Called with every write to a `MemoryPty`, after it has been recorded.
+type InputHook = Box<dyn FnMut(&[u8]) + Send>;
+
+/// A PTY without a process: output comes from any reader (usually a byte
+/// buffer), input is collected for inspection, and resizes are recorded.
+/// The terminal sees the PTY close when the output runs out.
+pub struct MemoryPty {
+    output: Option<Box<dyn io::Read + Send>>,
+    input: Arc<Mutex<Vec<u8>>>,
+    on_input: Option<InputHook>,
+    size: Arc<Mutex<Option<PtySize>>>,
+}
+
+impl MemoryPty {
+    pub fn new(output: impl io::Read + Send + 'static) -> Self {
+        Self {
+            output: Some(Box::new(output)),
+            input: Arc::default(),
+            on_input: None,
+            size: Arc::default(),
+        }
+    }
+
+    /// Also pass every write to `hook`, e.g. to echo it back through the
+    /// output reader.
+    pub fn with_input_hook(mut self, hook: impl FnMut(&[u8]) + Send + 'static) -> Self {
+        self.on_input = Some(Box::new(hook));
+        self
+    }
+
+    /// Everything written to the PTY so far (keystrokes, query replies). The
+    /// handle stays valid after the backend moves into a `TerminalInstance`.
+    #[cfg(test)]
+    pub fn input(&self) -> Arc<Mutex<Vec<u8>>> {
+        self.input.clone()
+    }
+
+    /// Size from the last `resize`, if any. Like `input`, the handle stays
+    /// valid after the backend moves into a `TerminalInstance`.
+    #[cfg(test)]
+    pub fn size(&self) -> Arc<Mutex<Option<PtySize>>> {
+        self.size.clone()
+    }
+}
+
+impl PtyBackend for MemoryPty {
+    fn take_reader(&mut self) -> Box<dyn io::Read + Send> {
+        self.output.take().unwrap_or_else(|| Box::new(io::empty()))
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        if let Ok(mut input) = self.input.lock() {
+            input.extend_from_slice(data);
+        }
+        if let Some(hook) = self.on_input.as_mut() {
+            hook(data);
+        }
+        Ok(())
+    }
+
+    fn resize(&mut self, size: PtySize) -> io::Result<()> {
+        if let Ok(mut current) = self.size.lock() {
+            *current = Some(size);
+        }
+        Ok(())
+    }
+
+    fn is_alive(&self) -> bool {
+        true
+    }
+}
@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::BTreeSet;
 use std::path::PathBuf;
 
+use crate::pty::ShellSyntax;
+
 // ---------------------------------------------------------------------------
 // Data model
 // ---------------------------------------------------------------------------
@@ -40,6 +42,39 @@ impl KeyBinding {
     }
 }
 
+/// What a quick command's key binding does with the key press.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyBindingMode {
+    /// Run the command; the terminal never sees the key.
+    #[default]
+    Always,
+    /// Run only while the shell waits at a prompt; otherwise the key goes to
+    /// the terminal, so programs keep their own use of it.
+    AtPrompt,
+    /// Run the command and send the key to the terminal as well.
+    PassThrough,
+}
+
+impl KeyBindingMode {
+    pub const ALL: [KeyBindingMode; 3] = [
+        KeyBindingMode::Always,
+        KeyBindingMode::AtPrompt,
+        KeyBindingMode::PassThrough,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            KeyBindingMode::Always => "Always",
+            KeyBindingMode::AtPrompt => "At a prompt",
+            KeyBindingMode::PassThrough => "Also send key",
+        }
+    }
+}
+
+/// Longest icon accepted by the editor, in characters; enough for an emoji
+/// with a modifier or a couple of symbols.
+pub const MAX_ICON_CHARS: usize = 4;
+
 /// A single quick command entry.
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct QuickCommand {
@@ -55,6 +90,25 @@ pub struct QuickCommand {
     pub tag: String,
     /// Optional keyboard shortcut.
     pub keybinding: KeyBinding,
+    /// Whether the shortcut also reaches the terminal.
+    #[serde(default)]
+    pub keybinding_mode: KeyBindingMode,
+    /// If true, ask for confirmation before sending (for destructive commands).
+    #[serde(default)]
+    pub confirm: bool,
+    /// Directory to change to before running the command.
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Watch for the command to finish (shell integration's `633;D` mark) and
+    /// notify with its exit code. Only for auto-executed commands.
+    #[serde(default)]
+    pub notify_when_done: bool,
+    /// Short emoji or symbol shown before the name; empty for none.
+    #[serde(default)]
+    pub icon: String,
+    /// Button color; `None` keeps the plain style.
+    #[serde(default)]
+    pub color: Option<[u8; 3]>,
 }
 
 impl QuickCommand {
@@ -66,7 +120,62 @@ impl QuickCommand {
             auto_execute: true,
             tag: "default".to_string(),
             keybinding: KeyBinding::default(),
+            keybinding_mode: KeyBindingMode::Always,
+            confirm: false,
+            cwd: None,
+            notify_when_done: false,
+            icon: String::new(),
+            color: None,
+        }
+    }
+
+    /// Name with the icon in front, as shown on buttons and in lists.
+    pub fn label(&self) -> String {
+        let icon = self.icon.trim();
+        if icon.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} {}", icon, self.name)
+        }
+    }
+
+    /// Label to report the command under when it finishes, if it asks to be.
+    pub fn notify_label(&self) -> Option<String> {
+        (self.notify_when_done && self.auto_execute).then(|| self.label())
+    }
+}
+
+/// Prefix `command` with a change to `dir`, on the same line so a paste-only
+/// command is still one prompt entry. The command is skipped if the directory
+/// change fails.
+pub fn with_working_dir(command: &str, dir: &str, syntax: ShellSyntax) -> String {
+    match syntax {
+        ShellSyntax::PowerShell => format!(
+            "Set-Location -LiteralPath '{}' -ErrorAction Stop; {}",
+            dir.replace('\'', "''"),
+            command
+        ),
+        // Windows paths cannot contain `"`.
+        ShellSyntax::Cmd => format!("cd /d \"{}\" && {}", dir, command),
+        ShellSyntax::Posix => format!("cd -- '{}' && {}", dir.replace('\'', "'\\''"), command),
+    }
+}
+
+/// Marker inside a command that says where the shell cursor should land.
+pub const CURSOR_MARKER: &str = "{|}";
+
+/// Remove the first cursor marker from `command`. Returns the text to send and
+/// how many characters follow the marker, i.e. how many Left-arrow presses are
+/// needed to move the cursor back to it. This assumes a line editor (readline,
+/// PSReadLine) that moves one character per `ESC[D`.
+pub fn resolve_cursor_marker(command: &str) -> (String, usize) {
+    match command.find(CURSOR_MARKER) {
+        Some(idx) => {
+            let before = &command[..idx];
+            let after = &command[idx + CURSOR_MARKER.len()..];
+            (format!("{}{}", before, after), after.chars().count())
         }
+        None => (command.to_string(), 0),
     }
 }
 
@@ -91,14 +200,20 @@ impl QuickCommandConfig {
         set.into_iter().collect()
     }
 
-    pub fn commands_by_tag(&self, tag: &str) -> Vec<&QuickCommand> {
-        self.commands.iter().filter(|c| c.tag == tag).collect()
-    }
-
     pub fn remove_by_id(&mut self, id: &str) {
         self.commands.retain(|c| c.id != id);
     }
 
+    pub fn find_by_id(&self, id: &str) -> Option<&QuickCommand> {
+        self.commands.iter().find(|c| c.id == id)
+    }
+
+    /// First command with this display name. Names are not required to be
+    /// unique, so prefer `find_by_id` when the caller has one.
+    pub fn find_by_name(&self, name: &str) -> Option<&QuickCommand> {
+        self.commands.iter().find(|c| c.name == name)
+    }
+
     pub fn find_by_keybinding(&self, kb: &KeyBinding) -> Option<&QuickCommand> {
         if kb.is_empty() {
             return None;
@@ -112,15 +227,83 @@ fn config_path() -> PathBuf {
     base.join("terminrt").join("quickcmds.json")
 }
 
-pub fn load_config() -> QuickCommandConfig {
+/// Where the original file is copied when it could not be loaded intact.
+fn backup_path() -> PathBuf {
+    config_path().with_extension("json.bak")
+}
+
+/// Load the quick commands. A file that does not parse as a whole is loaded
+/// leniently: every command that still deserializes is kept, the original is
+/// copied to `quickcmds.json.bak`, and the returned message tells the user what
+/// was lost.
+pub fn load_config() -> (QuickCommandConfig, Option<String>) {
     let path = config_path();
     if !path.exists() {
-        return QuickCommandConfig::default();
+        return (QuickCommandConfig::default(), None);
     }
-    match std::fs::read_to_string(&path) {
-        Ok(data) => serde_json::from_str(&data).unwrap_or_default(),
-        Err(_) => QuickCommandConfig::default(),
+    let data = match std::fs::read_to_string(&path) {
+        Ok(data) => data,
+        Err(e) => {
+            return (
+                QuickCommandConfig::default(),
+                Some(format!("Cannot read {}: {}", path.display(), e)),
+            );
+        }
+    };
+    let strict_error = match serde_json::from_str::<QuickCommandConfig>(&data) {
+        Ok(config) => return (config, None),
+        Err(e) => e,
+    };
+
+    let backup = backup_path();
+    let backup_note = match std::fs::write(&backup, &data) {
+        Ok(()) => format!("The original was saved to {}.", backup.display()),
+        Err(e) => format!("Backing it up to {} failed: {}.", backup.display(), e),
+    };
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(&data) else {
+        // Not JSON at all; nothing can be salvaged.
+        return (
+            QuickCommandConfig::default(),
+            Some(format!(
+                "{} is not valid JSON ({}); no quick commands were loaded. {}",
+                path.display(),
+                strict_error,
+                backup_note
+            )),
+        );
+    };
+
+    let entries = value
+        .get("commands")
+        .and_then(|c| c.as_array())
+        .map(Vec::as_slice)
+        .unwrap_or(&[]);
+    let mut config = QuickCommandConfig::default();
+    let mut skipped = 0;
+    for entry in entries {
+        match QuickCommand::deserialize(entry) {
+            Ok(cmd) => config.commands.push(cmd),
+            Err(_) => skipped += 1,
+        }
     }
+    let message = if skipped > 0 {
+        format!(
+            "{} of {} quick commands in {} could not be loaded and were skipped. {}",
+            skipped,
+            entries.len(),
+            path.display(),
+            backup_note
+        )
+    } else {
+        format!(
+            "{} did not load cleanly ({}); {} quick commands were recovered. {}",
+            path.display(),
+            strict_error,
+            config.commands.len(),
+            backup_note
+        )
+    };
+    (config, Some(message))
 }
 
 pub fn save_config(config: &QuickCommandConfig) {
@@ -0,0 +1,415 @@
+use std::time::{Duration, Instant};
+
+use alacritty_terminal::grid::Dimensions;
+use alacritty_terminal::index::{Column, Line};
+use alacritty_terminal::term::cell::Flags as CellFlags;
+use egui::{self, Color32, Key, RichText};
+use regex::{Regex, RegexBuilder};
+
+use crate::terminal::TerminalInstance;
+
+/// Wait this long after the last edit (or output burst) before rescanning.
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(150);
+/// Grid rows scanned per frame; long scrollback finishes over several frames.
+const ROWS_PER_FRAME: usize = 2000;
+/// Matches collected at most; the counter shows "N+" past this.
+const MAX_MATCHES: usize = 10_000;
+
+/// Inclusive cell range of one match as (absolute row, column) pairs, the same
+/// coordinates `TerminalSelectionState` uses.
+type SearchMatch = ((usize, usize), (usize, usize));
+
+/// Scrollback search (Ctrl+Shift+F). The grid is scanned incrementally after a
+/// short debounce, so typing never blocks on a full-buffer scan.
+#[derive(Default)]
+pub struct SearchState {
+    pub open: bool,
+    query: String,
+    use_regex: bool,
+    /// Compile error for the current pattern, shown instead of the counter.
+    error: Option<String>,
+    matches: Vec<SearchMatch>,
+    current: Option<usize>,
+    scan: Option<Scan>,
+    /// Set when the query or the buffer changed; scanning starts once
+    /// `SEARCH_DEBOUNCE` has passed.
+    dirty_since: Option<Instant>,
+    focus_pending: bool,
+    /// Top visible row when search opened: Esc scrolls back to it, and results
+    /// start from the first match at or after it.
+    origin_row: usize,
+    viewport_top_row: usize,
+    /// Cell (row, column) the renderer should bring into view, and how to align it.
+    scroll_to: Option<((usize, usize), egui::Align)>,
+}
+
+struct Scan {
+    regex: Regex,
+    next_row: usize,
+    matches: Vec<SearchMatch>,
+}
+
+impl SearchState {
+    pub fn open(&mut self) {
+        self.open = true;
+        self.focus_pending = true;
+        self.origin_row = self.viewport_top_row;
+        if !self.query.is_empty() {
+            self.dirty_since = Some(Instant::now());
+        }
+    }
+
+    /// Close and scroll back to where the view was when search opened.
+    pub fn close(&mut self) {
+        self.open = false;
+        self.matches.clear();
+        self.current = None;
+        self.scan = None;
+        self.dirty_since = None;
+        self.scroll_to = Some(((self.origin_row, 0), egui::Align::Min));
+    }
+
+    /// The buffer changed (new output); rescan soon. Not pushed back by further
+    /// output, so a busy shell still rescans every `SEARCH_DEBOUNCE`.
+    pub fn mark_buffer_changed(&mut self) {
+        if self.open && !self.query.is_empty() {
+            self.dirty_since.get_or_insert_with(Instant::now);
+        }
+    }
+
+    /// Whether (row, col) is inside a match, and if so whether it is the
+    /// current one.
+    pub fn match_at(&self, row: usize, col: usize) -> Option<bool> {
+        if self.matches.is_empty() {
+            return None;
+        }
+        let idx = self
+            .matches
+            .partition_point(|(start, _)| *start <= (row, col));
+        let idx = idx.checked_sub(1)?;
+        let (_, end) = self.matches[idx];
+        if (row, col) <= end {
+            Some(self.current == Some(idx))
+        } else {
+            None
+        }
+    }
+
+    /// Called by the renderer with the first visible row.
+    pub fn set_viewport_top_row(&mut self, row: usize) {
+        self.viewport_top_row = row;
+    }
+
+    /// First row shown in the last frame, counted from the top of scrollback.
+    pub fn viewport_top_row(&self) -> usize {
+        self.viewport_top_row
+    }
+
+    /// Cell the renderer should scroll to, and how to align it.
+    pub fn take_scroll_to(&mut self) -> Option<((usize, usize), egui::Align)> {
+        self.scroll_to.take()
+    }
+
+    fn select(&mut self, idx: usize) {
+        if let Some((start, _)) = self.matches.get(idx) {
+            self.current = Some(idx);
+            self.scroll_to = Some((*start, egui::Align::Center));
+        }
+    }
+
+    fn step_match(&mut self, forward: bool) {
+        let len = self.matches.len();
+        if len == 0 {
+            return;
+        }
+        let next = match self.current {
+            Some(idx) if forward => (idx + 1) % len,
+            Some(idx) => (idx + len - 1) % len,
+            None => 0,
+        };
+        self.select(next);
+    }
+
+    /// Start a pending rescan and advance the running one by one frame's worth
+    /// of rows. Requests repaints while work is left.
+    pub fn step(&mut self, ctx: &egui::Context, terminal: &TerminalInstance) {
+        if !self.open {
+            return;
+        }
+        if let Some(since) = self.dirty_since {
+            let elapsed = since.elapsed();
+            if elapsed < SEARCH_DEBOUNCE {
+                ctx.request_repaint_after(SEARCH_DEBOUNCE - elapsed);
+                return;
+            }
+            self.dirty_since = None;
+            self.start_scan();
+        }
+
+        let Some(scan) = self.scan.as_mut() else {
+            return;
+        };
+        let term = terminal.term();
+        let grid = term.grid();
+        let total_lines = grid.total_lines();
+        let num_cols = term.columns();
+        let top_line = -(grid.history_size() as i32);
+        let budget_end = scan.next_row.saturating_add(ROWS_PER_FRAME);
+
+        let mut text = String::new();
+        let mut cells: Vec<(usize, (usize, usize))> = Vec::new();
+        while scan.next_row < total_lines
+            && scan.next_row < budget_end
+            && scan.matches.len() < MAX_MATCHES
+        {
+            // Join soft-wrapped rows so matches can cross the wrap.
+            text.clear();
+            cells.clear();
+            let mut row_idx = scan.next_row;
+            loop {
+                let row = &grid[Line(top_line + row_idx as i32)];
+                for col_idx in 0..num_cols {
+                    let cell = &row[Column(col_idx)];
+                    if cell.flags.intersects(
+                        CellFlags::WIDE_CHAR_SPACER | CellFlags::LEADING_WIDE_CHAR_SPACER,
+                    ) {
+                        continue;
+                    }
+                    cells.push((text.len(), (row_idx, col_idx)));
+                    text.push(if cell.c == '\0' || cell.c == '\t' {
+                        ' '
+                    } else {
+                        cell.c
+                    });
+                }
+                let wrapped = num_cols > 0
+                    && row[Column(num_cols - 1)]
+                        .flags
+                        .contains(CellFlags::WRAPLINE);
+                row_idx += 1;
+                if !wrapped || row_idx >= total_lines {
+                    break;
+                }
+            }
+            scan.next_row = row_idx;
+
+            let cell_at = |byte: usize| {
+                let idx = cells.partition_point(|(offset, _)| *offset <= byte);
+                cells[idx.saturating_sub(1)].1
+            };
+            for m in scan.regex.find_iter(&text) {
+                if m.start() == m.end() {
+                    continue;
+                }
+                scan.matches
+                    .push((cell_at(m.start()), cell_at(m.end() - 1)));
+                if scan.matches.len() >= MAX_MATCHES {
+                    break;
+                }
+            }
+        }
+
+        if scan.next_row < total_lines && scan.matches.len() < MAX_MATCHES {
+            ctx.request_repaint();
+            return;
+        }
+        let scan = self.scan.take().expect("scan in progress");
+        let previous = self.current.and_then(|idx| self.matches.get(idx).copied());
+        self.matches = scan.matches;
+        self.current = None;
+        // A rescan for new output keeps the match the user is on, without
+        // scrolling; it only moves when that match is gone.
+        if let Some(previous) = previous {
+            if let Ok(idx) = self.matches.binary_search(&previous) {
+                self.current = Some(idx);
+                return;
+            }
+        }
+        // Nearest match at or below where the user was looking, else the last.
+        let nearest = self
+            .matches
+            .partition_point(|((row, _), _)| *row < self.origin_row)
+            .min(self.matches.len().saturating_sub(1));
+        self.select(nearest);
+    }
+
+    fn start_scan(&mut self) {
+        self.scan = None;
+        self.error = None;
+        if self.query.is_empty() {
+            self.matches.clear();
+            self.current = None;
+            return;
+        }
+        let pattern = if self.use_regex {
+            self.query.clone()
+        } else {
+            regex::escape(&self.query)
+        };
+        // Plain text is matched case-insensitively; regex users can add (?i).
+        match RegexBuilder::new(&pattern)
+            .case_insensitive(!self.use_regex)
+            .build()
+        {
+            Ok(regex) => {
+                self.scan = Some(Scan {
+                    regex,
+                    next_row: 0,
+                    matches: Vec::new(),
+                });
+            }
+            Err(e) => {
+                self.matches.clear();
+                self.current = None;
+                let message = e.to_string();
+                // regex errors are multi-line with a caret diagram; the last line
+                // names the problem.
+                self.error = Some(
+                    message
+                        .lines()
+                        .rev()
+                        .find(|l| !l.trim().is_empty())
+                        .unwrap_or("invalid pattern")
+                        .trim()
+                        .to_string(),
+                );
+            }
+        }
+    }
+
+    fn counter_text(&self) -> String {
+        if self.query.is_empty() {
+            String::new()
+        } else if self.scan.is_some() || self.dirty_since.is_some() {
+            "Searching...".to_string()
+        } else if self.matches.is_empty() {
+            "No results".to_string()
+        } else {
+            let total = if self.matches.len() >= MAX_MATCHES {
+                format!("{}+", MAX_MATCHES)
+            } else {
+                self.matches.len().to_string()
+            };
+            match self.current {
+                Some(idx) => format!("{} of {}", idx + 1, total),
+                None => total,
+            }
+        }
+    }
+}
+
+/// Search bar in the top-right corner. Enter / F3 go to the next match,
+/// Shift+Enter / Shift+F3 to the previous one, Esc closes.
+pub fn render(ctx: &egui::Context, state: &mut SearchState) {
+    if !state.open {
+        return;
+    }
+
+    let (escape, enter, f3, shift) = ctx.input(|i| {
+        (
+            i.key_pressed(Key::Escape),
+            i.key_pressed(Key::Enter),
+            i.key_pressed(Key::F3),
+            i.modifiers.shift,
+        )
+    });
+    if escape {
+        state.close();
+        return;
+    }
+    if enter || f3 {
+        state.step_match(!shift);
+        // Enter makes a single-line TextEdit drop focus; keep typing possible.
+        state.focus_pending = true;
+    }
+
+    let screen_rect = ctx.screen_rect();
+    let width = 420.0_f32.min(screen_rect.width() - 40.0).max(240.0);
+    egui::Window::new("Search")
+        .id(egui::Id::new("terminal_search"))
+        .title_bar(false)
+        .collapsible(false)
+        .resizable(false)
+        .fixed_pos(egui::pos2(
+            screen_rect.right() - width - 24.0,
+            screen_rect.top() + 40.0,
+        ))
+        .fixed_size(egui::vec2(width, 0.0))
+        .frame(
+            egui::Frame::none()
+                .fill(Color32::from_rgb(28, 28, 28))
+                .stroke(egui::Stroke::new(1.0, Color32::from_gray(80)))
+                .rounding(egui::Rounding::same(6.0))
+                .inner_margin(egui::Margin::same(6.0)),
+        )
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let query = ui.add(
+                    egui::TextEdit::singleline(&mut state.query)
+                        .hint_text("Search scrollback")
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(170.0),
+                );
+                if state.focus_pending {
+                    query.request_focus();
+                    state.focus_pending = false;
+                }
+                if query.changed() {
+                    state.dirty_since = Some(Instant::now());
+                }
+
+                let regex_toggle = ui
+                    .add(egui::SelectableLabel::new(
+                        state.use_regex,
+                        RichText::new(".*").monospace().size(12.0),
+                    ))
+                    .on_hover_text("Regular expression");
+                if regex_toggle.clicked() {
+                    state.use_regex = !state.use_regex;
+                    state.dirty_since = Some(Instant::now());
+                    state.focus_pending = true;
+                }
+
+                match &state.error {
+                    Some(error) => {
+                        ui.label(
+                            RichText::new("Invalid")
+                                .monospace()
+                                .size(12.0)
+                                .color(Color32::from_rgb(230, 90, 90)),
+                        )
+                        .on_hover_text(error);
+                    }
+                    None => {
+                        ui.label(
+                            RichText::new(state.counter_text())
+                                .monospace()
+                                .size(12.0)
+                                .color(Color32::from_gray(160)),
+                        );
+                    }
+                }
+
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.small_button("✕").on_hover_text("Close (Esc)").clicked() {
+                        state.close();
+                    }
+                    let has_matches = !state.matches.is_empty();
+                    if ui
+                        .add_enabled(has_matches, egui::Button::new("▼").small())
+                        .on_hover_text("Next (Enter)")
+                        .clicked()
+                    {
+                        state.step_match(true);
+                    }
+                    if ui
+                        .add_enabled(has_matches, egui::Button::new("▲").small())
+                        .on_hover_text("Previous (Shift+Enter)")
+                        .clicked()
+                    {
+                        state.step_match(false);
+                    }
+                });
+            });
+        });
+}
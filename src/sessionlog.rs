@@ -0,0 +1,127 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Appends PTY output to a file, either raw or with escape sequences stripped.
+pub struct SessionLog {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    stripper: Option<AnsiStripper>,
+    last_flush: Instant,
+}
+
+impl SessionLog {
+    pub fn open(path: &Path, clean: bool) -> io::Result<Self> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            writer: BufWriter::new(file),
+            stripper: clean.then(AnsiStripper::default),
+            last_flush: Instant::now(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn is_clean(&self) -> bool {
+        self.stripper.is_some()
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> io::Result<()> {
+        match self.stripper.as_mut() {
+            Some(stripper) => {
+                let clean = stripper.strip(data);
+                self.writer.write_all(&clean)
+            }
+            None => self.writer.write_all(data),
+        }
+    }
+
+    /// Flush buffered output if the flush interval has elapsed.
+    pub fn maybe_flush(&mut self) -> io::Result<()> {
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.last_flush = Instant::now();
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SessionLog {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum StripState {
+    #[default]
+    Ground,
+    Escape,
+    /// ESC ( / ESC ) ...: one designator byte follows.
+    Charset,
+    Csi,
+    /// OSC / DCS / SOS / PM / APC string, ended by BEL or ST.
+    String,
+    StringEscape,
+}
+
+/// Small state machine that drops escape sequences and control characters
+/// (except `\n` and `\t`), carrying state across chunk boundaries.
+#[derive(Default)]
+struct AnsiStripper {
+    state: StripState,
+}
+
+impl AnsiStripper {
+    fn strip(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::with_capacity(data.len());
+        for &byte in data {
+            self.state = match self.state {
+                StripState::Ground => match byte {
+                    0x1b => StripState::Escape,
+                    b'\n' | b'\t' => {
+                        out.push(byte);
+                        StripState::Ground
+                    }
+                    0x00..=0x1f | 0x7f => StripState::Ground,
+                    _ => {
+                        out.push(byte);
+                        StripState::Ground
+                    }
+                },
+                StripState::Escape => match byte {
+                    b'[' => StripState::Csi,
+                    b']' | b'P' | b'X' | b'^' | b'_' => StripState::String,
+                    b'(' | b')' | b'*' | b'+' => StripState::Charset,
+                    _ => StripState::Ground,
+                },
+                StripState::Charset => StripState::Ground,
+                StripState::Csi => match byte {
+                    0x40..=0x7e => StripState::Ground,
+                    _ => StripState::Csi,
+                },
+                StripState::String => match byte {
+                    0x07 => StripState::Ground,
+                    0x1b => StripState::StringEscape,
+                    _ => StripState::String,
+                },
+                StripState::StringEscape => match byte {
+                    b'\\' => StripState::Ground,
+                    _ => StripState::String,
+                },
+            };
+        }
+        out
+    }
+}
@@ -1,5 +1,12 @@
+use crate::appconfig::{
+    AppConfig, BackgroundFit, CloseConfirm, CopyFilter, CtrlCBehavior, CursorShape, InputLog,
+    PasteLineEnding, PresentMode, PromptIntegration, ReduceMotion, AUTO_LOCK_CHOICES,
+    CLOSE_DELAY_CHOICES, DEFAULT_CURSOR_COLOR, DEFAULT_SELECTION_COLOR, DEFAULT_WORD_SEPARATORS,
+    MAX_COPY_BYTES_CHOICES, MAX_FPS_CHOICES, MAX_INITIAL_GRID, MAX_TERMINAL_PADDING,
+    PTY_QUEUE_CHOICES, PTY_READ_BUFFER_CHOICES, TAB_WIDTH_CHOICES,
+};
+use crate::quickcmd::{self, KeyBinding, KeyBindingMode, QuickCommand, QuickCommandConfig};
 use egui::{self, Color32, RichText, Stroke};
-use crate::quickcmd::{KeyBinding, QuickCommand, QuickCommandConfig};
 
 // ---------------------------------------------------------------------------
 // Settings state
@@ -8,6 +15,7 @@ use crate::quickcmd::{KeyBinding, QuickCommand, QuickCommandConfig};
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum SettingsTab {
     QuickCommands,
+    General,
 }
 
 pub struct SettingsState {
@@ -21,6 +29,22 @@ pub struct SettingsState {
     pub creating_new: bool,
     /// True when we are recording a keybinding.
     pub recording_keybinding: bool,
+    /// Pending "Reset to defaults" confirmation and whether it covers every
+    /// tab or only the active one.
+    pub reset_confirm: Option<ResetScope>,
+    /// Problem found while loading `quickcmds.json`, shown until dismissed.
+    pub quickcmd_load_warning: Option<String>,
+    /// Problem found while loading `settings.json`, shown until dismissed.
+    pub settings_load_warning: Option<String>,
+}
+
+/// What a confirmed "Reset to defaults" wipes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResetScope {
+    /// Only the tab the reset was started from.
+    Tab(SettingsTab),
+    /// Quick commands and general preferences.
+    Everything,
 }
 
 impl Default for SettingsState {
@@ -32,27 +56,39 @@ impl Default for SettingsState {
             editing: None,
             creating_new: false,
             recording_keybinding: false,
+            reset_confirm: None,
+            quickcmd_load_warning: None,
+            settings_load_warning: None,
         }
     }
 }
 
+/// Which configs were modified this frame (caller should persist them).
+#[derive(Default, Clone, Copy)]
+pub struct SettingsAction {
+    pub quickcmd_dirty: bool,
+    pub app_config_dirty: bool,
+    pub clear_command_history: bool,
+}
+
 // ---------------------------------------------------------------------------
 // Public render entry
 // ---------------------------------------------------------------------------
 
-/// Render the settings modal window. Returns true if the config was modified
-/// (caller should persist).
+/// Render the settings modal window.
 pub fn render_settings(
     ctx: &egui::Context,
     settings: &mut SettingsState,
     config: &mut QuickCommandConfig,
-) -> bool {
+    app_config: &mut AppConfig,
+    terminal_read_only: &mut bool,
+    command_history_empty: bool,
+) -> SettingsAction {
+    let mut action = SettingsAction::default();
     if !settings.open {
-        return false;
+        return action;
     }
 
-    let mut dirty = false;
-
     // Dim background
     let screen_rect = ctx.screen_rect();
     let blocker_layer = egui::LayerId::new(
@@ -65,8 +101,8 @@ pub fn render_settings(
         Color32::from_rgba_unmultiplied(0, 0, 0, 120),
     );
 
-    let win_w = (screen_rect.width() * 0.72).min(820.0).max(480.0);
-    let win_h = (screen_rect.height() * 0.78).min(640.0).max(360.0);
+    let win_w = (screen_rect.width() * 0.72).clamp(480.0, 820.0);
+    let win_h = (screen_rect.height() * 0.78).clamp(360.0, 640.0);
     let center = screen_rect.center();
 
     egui::Window::new("Settings")
@@ -76,6 +112,7 @@ pub fn render_settings(
         .fixed_size(egui::vec2(win_w, win_h))
         .default_pos(egui::pos2(center.x - win_w * 0.5, center.y - win_h * 0.5))
         .movable(true)
+        .enabled(settings.reset_confirm.is_none())
         .show(ctx, |ui| {
             // Tab row
             ui.horizontal(|ui| {
@@ -85,6 +122,11 @@ pub fn render_settings(
                     SettingsTab::QuickCommands,
                     RichText::new("⚡ Quick Commands").monospace().size(13.0),
                 );
+                ui.selectable_value(
+                    &mut settings.active_tab,
+                    SettingsTab::General,
+                    RichText::new("⚙ General").monospace().size(13.0),
+                );
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     if ui
                         .add(
@@ -101,6 +143,22 @@ pub fn render_settings(
                         settings.open = false;
                         settings.editing = None;
                         settings.creating_new = false;
+                        settings.reset_confirm = None;
+                    }
+                    if ui
+                        .add(
+                            egui::Button::new(
+                                RichText::new("Reset to defaults")
+                                    .monospace()
+                                    .size(12.0)
+                                    .color(Color32::from_gray(180)),
+                            )
+                            .frame(false),
+                        )
+                        .on_hover_text("Reset the settings on this tab")
+                        .clicked()
+                    {
+                        settings.reset_confirm = Some(ResetScope::Tab(settings.active_tab));
                     }
                 });
             });
@@ -108,12 +166,1026 @@ pub fn render_settings(
 
             match settings.active_tab {
                 SettingsTab::QuickCommands => {
-                    dirty = render_quick_commands_tab(ui, settings, config);
+                    action.quickcmd_dirty = render_quick_commands_tab(ui, settings, config);
+                }
+                SettingsTab::General => {
+                    egui::ScrollArea::vertical()
+                        .auto_shrink([false, false])
+                        .show(ui, |ui| {
+                            render_load_warning(ui, &mut settings.settings_load_warning);
+                            render_general_tab(
+                                ui,
+                                app_config,
+                                terminal_read_only,
+                                command_history_empty,
+                                &mut action,
+                            );
+                        });
                 }
             }
         });
 
-    dirty
+    render_reset_confirm(ctx, settings, config, app_config, &mut action);
+
+    action
+}
+
+// ---------------------------------------------------------------------------
+// Reset to defaults
+// ---------------------------------------------------------------------------
+
+fn reset_scope_text(scope: ResetScope) -> &'static str {
+    match scope {
+        ResetScope::Tab(SettingsTab::QuickCommands) => "All quick commands will be deleted.",
+        ResetScope::Tab(SettingsTab::General) => {
+            "All General preferences go back to their defaults."
+        }
+        ResetScope::Everything => {
+            "All quick commands will be deleted and all General preferences go back to \
+             their defaults."
+        }
+    }
+}
+
+/// Confirmation for "Reset to defaults". Defaults to the tab it was opened
+/// from; resetting everything has to be picked explicitly.
+fn render_reset_confirm(
+    ctx: &egui::Context,
+    settings: &mut SettingsState,
+    config: &mut QuickCommandConfig,
+    app_config: &mut AppConfig,
+    action: &mut SettingsAction,
+) {
+    let Some(scope) = settings.reset_confirm else {
+        return;
+    };
+    let tab = match scope {
+        ResetScope::Tab(tab) => tab,
+        ResetScope::Everything => settings.active_tab,
+    };
+
+    let window_size = egui::vec2(380.0, 150.0);
+    let center = ctx.screen_rect().center();
+    let mut confirmed = false;
+    let mut cancelled = false;
+    let mut everything = scope == ResetScope::Everything;
+    egui::Window::new("Reset to Defaults")
+        .id(egui::Id::new("settings_reset_confirm"))
+        .collapsible(false)
+        .resizable(false)
+        .fixed_size(window_size)
+        .default_pos(egui::pos2(
+            center.x - window_size.x * 0.5,
+            center.y - window_size.y * 0.5,
+        ))
+        .show(ctx, |ui| {
+            // Stay above the settings window even if it was clicked.
+            ctx.move_to_top(ui.layer_id());
+            ui.spacing_mut().item_spacing = egui::vec2(10.0, 8.0);
+            let tab_name = match tab {
+                SettingsTab::QuickCommands => "Quick Commands",
+                SettingsTab::General => "General",
+            };
+            ui.radio_value(
+                &mut everything,
+                false,
+                RichText::new(format!("Only the {} tab", tab_name))
+                    .monospace()
+                    .size(12.0),
+            );
+            ui.radio_value(
+                &mut everything,
+                true,
+                RichText::new("All settings").monospace().size(12.0),
+            );
+            let shown_scope = if everything {
+                ResetScope::Everything
+            } else {
+                ResetScope::Tab(tab)
+            };
+            ui.label(
+                RichText::new(reset_scope_text(shown_scope))
+                    .monospace()
+                    .size(11.0)
+                    .color(Color32::from_gray(130)),
+            );
+            ui.add_space(4.0);
+            ui.horizontal(|ui| {
+                let reset_button =
+                    egui::Button::new(RichText::new("Reset").color(Color32::WHITE).strong())
+                        .min_size(egui::vec2(92.0, 28.0))
+                        .fill(Color32::from_rgb(190, 60, 60))
+                        .stroke(Stroke::new(1.0, Color32::from_rgb(230, 100, 100)));
+                if ui.add(reset_button).clicked() {
+                    confirmed = true;
+                }
+                if ui
+                    .add(egui::Button::new("Cancel").min_size(egui::vec2(92.0, 28.0)))
+                    .clicked()
+                {
+                    cancelled = true;
+                }
+            });
+        });
+
+    let scope = if everything {
+        ResetScope::Everything
+    } else {
+        ResetScope::Tab(tab)
+    };
+    if cancelled || ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+        settings.reset_confirm = None;
+        return;
+    }
+    settings.reset_confirm = Some(scope);
+    if !confirmed {
+        return;
+    }
+
+    settings.reset_confirm = None;
+    if matches!(
+        scope,
+        ResetScope::Tab(SettingsTab::QuickCommands) | ResetScope::Everything
+    ) {
+        *config = QuickCommandConfig::default();
+        settings.filter_tag.clear();
+        settings.editing = None;
+        settings.creating_new = false;
+        settings.recording_keybinding = false;
+        action.quickcmd_dirty = true;
+    }
+    if matches!(
+        scope,
+        ResetScope::Tab(SettingsTab::General) | ResetScope::Everything
+    ) {
+        *app_config = AppConfig::default();
+        action.app_config_dirty = true;
+    }
+}
+
+// ---------------------------------------------------------------------------
+// General tab
+// ---------------------------------------------------------------------------
+
+fn render_general_tab(
+    ui: &mut egui::Ui,
+    app_config: &mut AppConfig,
+    terminal_read_only: &mut bool,
+    command_history_empty: bool,
+    action: &mut SettingsAction,
+) {
+    let mut dirty = false;
+
+    section_header(ui, "Session");
+
+    egui::Grid::new("session_settings_grid")
+        .num_columns(2)
+        .spacing([12.0, 8.0])
+        .show(ui, |ui| {
+            setting_row(
+                ui,
+                "Read-only",
+                "Ignore input to the terminal (Ctrl+Shift+R)",
+                |ui| {
+                    ui.checkbox(terminal_read_only, "");
+                },
+            );
+
+            setting_row(
+                ui,
+                "Auto-lock",
+                "Go read-only after this long without typing",
+                |ui| {
+                    egui::ComboBox::from_id_source("auto_lock_mins")
+                        .selected_text(auto_lock_label(app_config.auto_lock_mins))
+                        .show_ui(ui, |ui| {
+                            for mins in AUTO_LOCK_CHOICES {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.auto_lock_mins,
+                                        mins,
+                                        auto_lock_label(mins),
+                                    )
+                                    .changed();
+                            }
+                        });
+                },
+            );
+
+            setting_row(ui, "Confirm close", "Ask before closing the window", |ui| {
+                egui::ComboBox::from_id_source("confirm_close")
+                    .selected_text(app_config.confirm_close.label())
+                    .show_ui(ui, |ui| {
+                        for mode in CloseConfirm::ALL {
+                            dirty |= ui
+                                .selectable_value(&mut app_config.confirm_close, mode, mode.label())
+                                .changed();
+                        }
+                    });
+            });
+
+            setting_row(
+                ui,
+                "Hold open on exit",
+                "Keep the last screen after the shell exits",
+                |ui| {
+                    dirty |= ui.checkbox(&mut app_config.hold_open_on_exit, "").changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Keep history",
+                "Reconnect keeps the old output in scrollback",
+                |ui| {
+                    dirty |= ui
+                        .checkbox(&mut app_config.keep_history_on_reconnect, "")
+                        .changed();
+                },
+            );
+
+            setting_row(ui, "Close after exit", "Any key or click cancels", |ui| {
+                ui.add_enabled_ui(!app_config.hold_open_on_exit, |ui| {
+                    egui::ComboBox::from_id_source("close_delay_secs")
+                        .selected_text(close_delay_label(app_config.close_delay_secs))
+                        .show_ui(ui, |ui| {
+                            for secs in CLOSE_DELAY_CHOICES {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.close_delay_secs,
+                                        secs,
+                                        close_delay_label(secs),
+                                    )
+                                    .changed();
+                            }
+                        });
+                });
+            });
+
+            setting_row(
+                ui,
+                "Bell attention",
+                "Flash the taskbar when the bell rings in the background",
+                |ui| {
+                    dirty |= ui.checkbox(&mut app_config.bell_attention, "").changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Finish alerts",
+                "Report quick commands marked \"Notify\" when they finish",
+                |ui| {
+                    dirty |= ui
+                        .checkbox(&mut app_config.quickcmd_finish_alerts, "")
+                        .changed();
+                },
+            );
+        });
+
+    ui.add_space(12.0);
+
+    section_header(ui, "Shell");
+
+    egui::Grid::new("shell_settings_grid")
+        .num_columns(2)
+        .spacing([12.0, 8.0])
+        .show(ui, |ui| {
+            setting_row(
+                ui,
+                "Prompt integration",
+                "PowerShell CWD tracking; applies on reconnect",
+                |ui| {
+                    egui::ComboBox::from_id_source("prompt_integration")
+                        .selected_text(app_config.prompt_integration.label())
+                        .show_ui(ui, |ui| {
+                            for mode in PromptIntegration::ALL {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.prompt_integration,
+                                        mode,
+                                        mode.label(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                },
+            );
+
+            setting_row(ui, "Prompt pattern", "", |ui| {
+                dirty |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut app_config.prompt_regex)
+                            .desired_width(160.0)
+                            .font(egui::FontId::monospace(12.0))
+                            .hint_text("e.g. PS .*?> "),
+                    )
+                    .changed();
+                let invalid = !app_config.prompt_regex.is_empty()
+                    && regex::Regex::new(&app_config.prompt_regex).is_err();
+                let (hint, color) = if invalid {
+                    ("Invalid regex", Color32::from_rgb(220, 90, 90))
+                } else {
+                    (
+                        "Finds prompts for shells without OSC 633 (best effort)",
+                        Color32::from_gray(130),
+                    )
+                };
+                ui.label(hint_text(hint).color(color));
+            });
+
+            setting_row(
+                ui,
+                "Tab width",
+                "Tab stops in the emulator; ConPTY may pre-expand console tabs",
+                |ui| {
+                    egui::ComboBox::from_id_source("tab_width")
+                        .selected_text(app_config.tab_width.to_string())
+                        .show_ui(ui, |ui| {
+                            for width in TAB_WIDTH_CHOICES {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.tab_width,
+                                        width,
+                                        width.to_string(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                },
+            );
+
+            let hint = if app_config.profiles.is_empty() {
+                "Add profiles to settings.json (\"profiles\")"
+            } else {
+                "Launched when --profile is not given"
+            };
+            setting_row(ui, "Profile", hint, |ui| {
+                let selected = if app_config.default_profile.is_empty() {
+                    "Ask at startup"
+                } else {
+                    app_config.default_profile.as_str()
+                };
+                let mut default_profile = app_config.default_profile.clone();
+                egui::ComboBox::from_id_source("default_profile")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut default_profile, String::new(), "Ask at startup");
+                        for profile in &app_config.profiles {
+                            ui.selectable_value(
+                                &mut default_profile,
+                                profile.name.clone(),
+                                &profile.name,
+                            );
+                        }
+                    });
+                if default_profile != app_config.default_profile {
+                    app_config.default_profile = default_profile;
+                    dirty = true;
+                }
+            });
+
+            setting_row(
+                ui,
+                "Initial size",
+                "Columns × rows at startup; 0 fits the window",
+                |ui| {
+                    let fit = |value: f64, _| {
+                        if value == 0.0 {
+                            "fit".to_string()
+                        } else {
+                            value.to_string()
+                        }
+                    };
+                    dirty |= ui
+                        .add(
+                            egui::DragValue::new(&mut app_config.initial_cols)
+                                .clamp_range(0..=MAX_INITIAL_GRID)
+                                .custom_formatter(fit),
+                        )
+                        .changed();
+                    ui.label(RichText::new("×").monospace().size(12.0));
+                    dirty |= ui
+                        .add(
+                            egui::DragValue::new(&mut app_config.initial_rows)
+                                .clamp_range(0..=MAX_INITIAL_GRID)
+                                .custom_formatter(fit),
+                        )
+                        .changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Resize requests",
+                "When a program sends ESC[8;rows;cols t",
+                |ui| {
+                    dirty |= ui
+                        .checkbox(&mut app_config.allow_resize_requests, "Resize the window")
+                        .changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Read buffer",
+                "Bytes per PTY read; new sessions",
+                |ui| {
+                    egui::ComboBox::from_id_source("pty_read_buffer_size")
+                        .selected_text(format!("{} KiB", app_config.pty_read_buffer_size / 1024))
+                        .show_ui(ui, |ui| {
+                            for size in PTY_READ_BUFFER_CHOICES {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.pty_read_buffer_size,
+                                        size,
+                                        format!("{} KiB", size / 1024),
+                                    )
+                                    .changed();
+                            }
+                        });
+                },
+            );
+
+            setting_row(
+                ui,
+                "Output queue",
+                "Reads buffered before the shell is paused; new sessions",
+                |ui| {
+                    egui::ComboBox::from_id_source("pty_queue_capacity")
+                        .selected_text(app_config.pty_queue_capacity.to_string())
+                        .show_ui(ui, |ui| {
+                            for capacity in PTY_QUEUE_CHOICES {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.pty_queue_capacity,
+                                        capacity,
+                                        capacity.to_string(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                },
+            );
+        });
+
+    ui.add_space(12.0);
+
+    section_header(ui, "Keyboard");
+
+    egui::Grid::new("general_settings_grid")
+        .num_columns(2)
+        .spacing([12.0, 8.0])
+        .show(ui, |ui| {
+            setting_row(
+                ui,
+                "Alt sends ESC",
+                "Alt+key sends ESC + key (readline/emacs Meta)",
+                |ui| {
+                    dirty |= ui.checkbox(&mut app_config.alt_sends_esc, "").changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Command keys",
+                "Quick command key bindings; off passes keys to the terminal",
+                |ui| {
+                    dirty |= ui
+                        .checkbox(&mut app_config.quickcmd_keybindings, "")
+                        .changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Local echo",
+                "Show typing dimmed until the shell echoes it (slow/remote PTYs)",
+                |ui| {
+                    dirty |= ui.checkbox(&mut app_config.local_echo, "").changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Password prompts",
+                "No local echo or input logging on lines like \"Password:\"",
+                |ui| {
+                    dirty |= ui.checkbox(&mut app_config.password_guard, "").changed();
+                },
+            );
+        });
+
+    ui.add_space(12.0);
+    section_header(ui, "Paste");
+
+    egui::Grid::new("paste_settings_grid")
+        .num_columns(2)
+        .spacing([12.0, 8.0])
+        .show(ui, |ui| {
+            setting_row(ui, "Line endings", "", |ui| {
+                egui::ComboBox::from_id_source("paste_line_ending")
+                    .selected_text(app_config.paste_line_ending.label())
+                    .show_ui(ui, |ui| {
+                        for ending in PasteLineEnding::ALL {
+                            dirty |= ui
+                                .selectable_value(
+                                    &mut app_config.paste_line_ending,
+                                    ending,
+                                    ending.label(),
+                                )
+                                .changed();
+                        }
+                    });
+            });
+
+            setting_row(
+                ui,
+                "Trailing newline",
+                "Strip it so a paste never runs the command",
+                |ui| {
+                    dirty |= ui
+                        .checkbox(&mut app_config.paste_strip_trailing_newline, "")
+                        .changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Bracketed paste",
+                "Off pastes literally even when the program asks for markers",
+                |ui| {
+                    dirty |= ui.checkbox(&mut app_config.bracketed_paste, "").changed();
+                },
+            );
+        });
+
+    ui.add_space(12.0);
+    section_header(ui, "Copy");
+
+    egui::Grid::new("copy_settings_grid")
+        .num_columns(2)
+        .spacing([12.0, 8.0])
+        .show(ui, |ui| {
+            setting_row(
+                ui,
+                "Filter",
+                "Control chars and U+FFFD in copied text",
+                |ui| {
+                    egui::ComboBox::from_id_source("copy_filter")
+                        .selected_text(app_config.copy_filter.label())
+                        .show_ui(ui, |ui| {
+                            for filter in CopyFilter::ALL {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.copy_filter,
+                                        filter,
+                                        filter.label(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                },
+            );
+
+            setting_row(
+                ui,
+                "Box drawing",
+                "Also filter TUI borders (U+2500-U+259F)",
+                |ui| {
+                    ui.add_enabled_ui(app_config.copy_filter != CopyFilter::Off, |ui| {
+                        dirty |= ui
+                            .checkbox(&mut app_config.copy_filter_box_drawing, "")
+                            .changed();
+                    });
+                },
+            );
+
+            setting_row(
+                ui,
+                "Word separators",
+                "End a double-click word, besides whitespace",
+                |ui| {
+                    dirty |= ui
+                        .add(
+                            egui::TextEdit::singleline(&mut app_config.word_separators)
+                                .desired_width(160.0)
+                                .font(egui::FontId::monospace(12.0)),
+                        )
+                        .changed();
+                    if app_config.word_separators != DEFAULT_WORD_SEPARATORS
+                        && ui.small_button("Reset").clicked()
+                    {
+                        app_config.word_separators = DEFAULT_WORD_SEPARATORS.to_string();
+                        dirty = true;
+                    }
+                },
+            );
+
+            setting_row(
+                ui,
+                "Copy word",
+                "Copy a word as soon as a double-click selects it",
+                |ui| {
+                    dirty |= ui
+                        .checkbox(&mut app_config.copy_on_double_click, "")
+                        .changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Copy limit",
+                "Longer selections are cut off, with a status bar notice",
+                |ui| {
+                    egui::ComboBox::from_id_source("max_copy_bytes")
+                        .selected_text(format!("{} MiB", app_config.max_copy_bytes / (1024 * 1024)))
+                        .show_ui(ui, |ui| {
+                            for size in MAX_COPY_BYTES_CHOICES {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.max_copy_bytes,
+                                        size,
+                                        format!("{} MiB", size / (1024 * 1024)),
+                                    )
+                                    .changed();
+                            }
+                        });
+                },
+            );
+
+            setting_row(ui, "Ctrl+C", "Ctrl+Shift+C always copies", |ui| {
+                egui::ComboBox::from_id_source("ctrl_c_behavior")
+                    .selected_text(app_config.ctrl_c.label())
+                    .show_ui(ui, |ui| {
+                        for behavior in CtrlCBehavior::ALL {
+                            dirty |= ui
+                                .selectable_value(
+                                    &mut app_config.ctrl_c,
+                                    behavior,
+                                    behavior.label(),
+                                )
+                                .changed();
+                        }
+                    });
+            });
+        });
+
+    ui.add_space(12.0);
+    section_header(ui, "Appearance");
+
+    egui::Grid::new("appearance_settings_grid")
+        .num_columns(2)
+        .spacing([12.0, 8.0])
+        .show(ui, |ui| {
+            for (label, color, default) in [
+                (
+                    "Selection",
+                    &mut app_config.selection_color,
+                    DEFAULT_SELECTION_COLOR,
+                ),
+                ("Cursor", &mut app_config.cursor_color, DEFAULT_CURSOR_COLOR),
+            ] {
+                setting_row(ui, label, "", |ui| {
+                    dirty |= ui.color_edit_button_srgb(color).changed();
+                    if ui
+                        .add_enabled(*color != default, egui::Button::new("Reset").small())
+                        .clicked()
+                    {
+                        *color = default;
+                        dirty = true;
+                    }
+                });
+            }
+
+            setting_row(
+                ui,
+                "Cursor shape",
+                "Programs may override both (DECSCUSR)",
+                |ui| {
+                    egui::ComboBox::from_id_source("cursor_shape")
+                        .selected_text(app_config.cursor_shape.label())
+                        .show_ui(ui, |ui| {
+                            for shape in CursorShape::ALL {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.cursor_shape,
+                                        shape,
+                                        shape.label(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                    dirty |= ui.checkbox(&mut app_config.cursor_blink, "Blink").changed();
+                },
+            );
+
+            setting_row(ui, "Padding", "Left and right of the terminal text", |ui| {
+                dirty |= ui
+                    .add(
+                        egui::DragValue::new(&mut app_config.terminal_padding)
+                            .clamp_range(0..=MAX_TERMINAL_PADDING)
+                            .suffix(" pt"),
+                    )
+                    .changed();
+            });
+
+            setting_row(
+                ui,
+                "Side panel",
+                "Left panel with Settings and DevTools (Ctrl+Shift+B)",
+                |ui| {
+                    dirty |= ui.checkbox(&mut app_config.show_left_panel, "").changed();
+                },
+            );
+
+            setting_row(ui, "Background", "", |ui| {
+                dirty |= ui
+                    .add(
+                        egui::TextEdit::singleline(&mut app_config.background_image)
+                            .desired_width(220.0)
+                            .font(egui::FontId::monospace(12.0))
+                            .hint_text("Image path (PNG or JPEG)"),
+                    )
+                    .changed();
+                if ui
+                    .add_enabled(
+                        !app_config.background_image.is_empty(),
+                        egui::Button::new("Clear").small(),
+                    )
+                    .clicked()
+                {
+                    app_config.background_image.clear();
+                    dirty = true;
+                }
+                if !app_config.background_image.is_empty()
+                    && !std::path::Path::new(&app_config.background_image).is_file()
+                {
+                    ui.label(hint_text("File not found").color(Color32::from_rgb(220, 90, 90)));
+                }
+            });
+
+            setting_row(
+                ui,
+                "Image fit",
+                "Opacity; colored cell backgrounds paint over it",
+                |ui| {
+                    egui::ComboBox::from_id_source("background_fit")
+                        .selected_text(app_config.background_fit.label())
+                        .show_ui(ui, |ui| {
+                            for fit in BackgroundFit::ALL {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.background_fit,
+                                        fit,
+                                        fit.label(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                    dirty |= ui
+                        .add(
+                            egui::DragValue::new(&mut app_config.background_opacity)
+                                .clamp_range(0..=100)
+                                .suffix("%"),
+                        )
+                        .changed();
+                },
+            );
+        });
+    ui.label(hint_text(
+        "Text on these switches between dark and light for contrast",
+    ));
+
+    ui.add_space(12.0);
+    section_header(ui, "Rendering");
+
+    egui::Grid::new("rendering_settings_grid")
+        .num_columns(2)
+        .spacing([12.0, 8.0])
+        .show(ui, |ui| {
+            setting_row(
+                ui,
+                "Present mode",
+                "Falls back to Fifo if the GPU lacks it",
+                |ui| {
+                    egui::ComboBox::from_id_source("present_mode")
+                        .selected_text(app_config.present_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in PresentMode::ALL {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.present_mode,
+                                        mode,
+                                        mode.label(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                },
+            );
+
+            setting_row(ui, "Frame cap", "", |ui| {
+                egui::ComboBox::from_id_source("max_fps")
+                    .selected_text(fps_label(app_config.max_fps))
+                    .show_ui(ui, |ui| {
+                        for fps in MAX_FPS_CHOICES {
+                            dirty |= ui
+                                .selectable_value(&mut app_config.max_fps, fps, fps_label(fps))
+                                .changed();
+                        }
+                    });
+            });
+
+            setting_row(
+                ui,
+                "Smooth scrolling",
+                "Animate jumps such as Ctrl+L; off snaps instantly",
+                |ui| {
+                    dirty |= ui.checkbox(&mut app_config.animate_scrolling, "").changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Reduce motion",
+                "Steady cursor, instant scrolls, no startup animation",
+                |ui| {
+                    egui::ComboBox::from_id_source("reduce_motion")
+                        .selected_text(app_config.reduce_motion.label())
+                        .show_ui(ui, |ui| {
+                            for mode in ReduceMotion::ALL {
+                                dirty |= ui
+                                    .selectable_value(
+                                        &mut app_config.reduce_motion,
+                                        mode,
+                                        mode.label(),
+                                    )
+                                    .changed();
+                            }
+                        });
+                },
+            );
+
+            setting_row(
+                ui,
+                "Mode indicators",
+                "Show paste, alt screen, mouse and key modes in the status bar",
+                |ui| {
+                    dirty |= ui
+                        .checkbox(&mut app_config.show_terminal_modes, "")
+                        .changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Running command",
+                "Spinner and elapsed time while a command runs",
+                |ui| {
+                    dirty |= ui
+                        .checkbox(&mut app_config.show_running_command, "")
+                        .changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Session uptime",
+                "Time since the shell started; resets on reconnect",
+                |ui| {
+                    dirty |= ui
+                        .checkbox(&mut app_config.show_session_uptime, "")
+                        .changed();
+                },
+            );
+
+            setting_row(
+                ui,
+                "Cursor locator",
+                "Ctrl+Shift+L flashes guides through the cursor",
+                |ui| {
+                    dirty |= ui.checkbox(&mut app_config.cursor_locator, "").changed();
+                },
+            );
+        });
+
+    ui.add_space(12.0);
+    section_header(ui, "History");
+
+    egui::Grid::new("history_settings_grid")
+        .num_columns(2)
+        .spacing([12.0, 8.0])
+        .show(ui, |ui| {
+            setting_row(
+                ui,
+                "Per-directory",
+                "Remember commands per directory for the palette (Ctrl+Shift+P)",
+                |ui| {
+                    dirty |= ui
+                        .checkbox(&mut app_config.record_command_history, "")
+                        .changed();
+                },
+            );
+
+            setting_row(ui, "Log input", "", |ui| {
+                egui::ComboBox::from_id_source("input_log")
+                    .selected_text(app_config.input_log.label())
+                    .show_ui(ui, |ui| {
+                        for mode in InputLog::ALL {
+                            dirty |= ui
+                                .selectable_value(&mut app_config.input_log, mode, mode.label())
+                                .changed();
+                        }
+                    });
+                let (hint, color) = if app_config.input_log == InputLog::Raw {
+                    (
+                        "Typed secrets end up in the log and its exports",
+                        Color32::from_rgb(220, 90, 90),
+                    )
+                } else {
+                    (
+                        "Typed and pasted bytes in the DevTools VT Stream",
+                        Color32::from_gray(130),
+                    )
+                };
+                ui.label(hint_text(hint).color(color));
+            });
+
+            ui.label("");
+            let clear = ui.add_enabled(
+                !command_history_empty,
+                egui::Button::new(RichText::new("Clear history").monospace().size(12.0)),
+            );
+            if clear.clicked() {
+                action.clear_command_history = true;
+            }
+            ui.end_row();
+        });
+
+    action.app_config_dirty = dirty;
+}
+
+/// A section title above one of the General tab's grids.
+fn section_header(ui: &mut egui::Ui, title: &str) {
+    ui.label(
+        RichText::new(title)
+            .monospace()
+            .size(14.0)
+            .color(Color32::from_gray(220))
+            .strong(),
+    );
+    ui.add_space(6.0);
+}
+
+/// One row of a settings grid: the setting's name, then the control `add`
+/// draws, followed by `hint` unless it is empty.
+fn setting_row(ui: &mut egui::Ui, label: &str, hint: &str, add: impl FnOnce(&mut egui::Ui)) {
+    ui.label(
+        RichText::new(label)
+            .monospace()
+            .size(12.0)
+            .color(Color32::from_gray(160)),
+    );
+    ui.horizontal(|ui| {
+        add(ui);
+        if !hint.is_empty() {
+            ui.label(hint_text(hint));
+        }
+    });
+    ui.end_row();
+}
+
+/// The small gray text that explains a setting.
+fn hint_text(text: &str) -> RichText {
+    RichText::new(text)
+        .monospace()
+        .size(11.0)
+        .color(Color32::from_gray(130))
+}
+
+fn fps_label(fps: u32) -> String {
+    if fps == 0 {
+        "Uncapped".to_string()
+    } else {
+        format!("{} fps", fps)
+    }
+}
+
+fn auto_lock_label(mins: u32) -> String {
+    if mins == 0 {
+        "Off".to_string()
+    } else {
+        format!("{} min", mins)
+    }
+}
+
+fn close_delay_label(secs: u32) -> String {
+    if secs == 0 {
+        "Immediately".to_string()
+    } else {
+        format!("{} s", secs)
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -133,6 +1205,48 @@ fn render_quick_commands_tab(
     }
 }
 
+/// Notice about quick commands that could not be loaded, until dismissed.
+/// Shown in the settings list and the right panel.
+pub fn render_quickcmd_load_warning(ui: &mut egui::Ui, settings: &mut SettingsState) {
+    render_load_warning(ui, &mut settings.quickcmd_load_warning);
+}
+
+/// Notice about settings that could not be loaded, until dismissed. Shown on
+/// the General tab and the right panel.
+pub fn render_settings_load_warning(ui: &mut egui::Ui, settings: &mut SettingsState) {
+    render_load_warning(ui, &mut settings.settings_load_warning);
+}
+
+fn render_load_warning(ui: &mut egui::Ui, slot: &mut Option<String>) {
+    let Some(warning) = slot.as_deref() else {
+        return;
+    };
+    let mut dismissed = false;
+    egui::Frame::none()
+        .fill(Color32::from_rgb(60, 45, 20))
+        .stroke(Stroke::new(1.0, Color32::from_rgb(150, 110, 40)))
+        .rounding(egui::Rounding::same(4.0))
+        .inner_margin(egui::Margin::symmetric(8.0, 6.0))
+        .show(ui, |ui| {
+            ui.add(
+                egui::Label::new(
+                    RichText::new(format!("⚠ {}", warning))
+                        .monospace()
+                        .size(11.0)
+                        .color(Color32::from_rgb(235, 200, 130)),
+                )
+                .wrap(true),
+            );
+            if ui.small_button("Dismiss").clicked() {
+                dismissed = true;
+            }
+        });
+    ui.add_space(4.0);
+    if dismissed {
+        *slot = None;
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Command list with tag filter
 // ---------------------------------------------------------------------------
@@ -145,6 +1259,8 @@ fn render_command_list(
     let mut dirty = false;
     let tags = config.tags();
 
+    render_quickcmd_load_warning(ui, settings);
+
     // Top toolbar: tag filter + add button
     ui.horizontal(|ui| {
         ui.label(RichText::new("Tag:").monospace().size(12.0).color(Color32::from_gray(160)));
@@ -264,13 +1380,21 @@ fn render_command_row(
         ui.horizontal(|ui| {
             // Left side: name + info
             ui.vertical(|ui| {
-                ui.label(
-                    RichText::new(&cmd.name)
-                        .monospace()
-                        .size(13.0)
-                        .color(Color32::from_gray(220))
-                        .strong(),
-                );
+                ui.horizontal(|ui| {
+                    if let Some([r, g, b]) = cmd.color {
+                        let (rect, _) =
+                            ui.allocate_exact_size(egui::vec2(10.0, 10.0), egui::Sense::hover());
+                        ui.painter()
+                            .rect_filled(rect, 2.0, Color32::from_rgb(r, g, b));
+                    }
+                    ui.label(
+                        RichText::new(cmd.label())
+                            .monospace()
+                            .size(13.0)
+                            .color(Color32::from_gray(220))
+                            .strong(),
+                    );
+                });
                 ui.horizontal(|ui| {
                     // Tag badge
                     let tag_frame = egui::Frame::none()
@@ -302,6 +1426,24 @@ fn render_command_row(
                         );
                     }
 
+                    if cmd.confirm {
+                        ui.label(
+                            RichText::new("[confirm]")
+                                .monospace()
+                                .size(10.0)
+                                .color(Color32::from_rgb(230, 130, 90)),
+                        );
+                    }
+
+                    if cmd.notify_label().is_some() {
+                        ui.label(
+                            RichText::new("[notify]")
+                                .monospace()
+                                .size(10.0)
+                                .color(Color32::from_rgb(140, 180, 255)),
+                        );
+                    }
+
                     if !cmd.keybinding.is_empty() {
                         ui.label(
                             RichText::new(format!("[{}]", cmd.keybinding.display()))
@@ -398,7 +1540,84 @@ fn render_edit_form(
                     .desired_width(300.0)
                     .font(egui::FontId::monospace(12.0))
                     .hint_text("e.g., ls -la"),
+            )
+            .on_hover_text(
+                "Put {|} where the cursor should land when pasting without auto execute \
+                 (needs a line editor such as PSReadLine or readline).",
+            );
+            ui.end_row();
+
+            // Working directory
+            ui.label(
+                RichText::new("Directory")
+                    .monospace()
+                    .size(12.0)
+                    .color(Color32::from_gray(160)),
+            );
+            let mut cwd = cmd.cwd.clone().unwrap_or_default();
+            let cwd_edit = ui
+                .add(
+                    egui::TextEdit::singleline(&mut cwd)
+                        .desired_width(300.0)
+                        .font(egui::FontId::monospace(12.0))
+                        .hint_text("optional; run in the current directory"),
+                )
+                .on_hover_text("Change to this directory before the command runs.");
+            if cwd_edit.changed() {
+                cmd.cwd = if cwd.trim().is_empty() {
+                    None
+                } else {
+                    Some(cwd)
+                };
+            }
+            ui.end_row();
+
+            // Icon
+            ui.label(
+                RichText::new("Icon")
+                    .monospace()
+                    .size(12.0)
+                    .color(Color32::from_gray(160)),
             );
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut cmd.icon)
+                        .desired_width(60.0)
+                        .char_limit(quickcmd::MAX_ICON_CHARS)
+                        .hint_text("🚀"),
+                );
+                ui.label(
+                    RichText::new("optional emoji or symbol before the name")
+                        .monospace()
+                        .size(11.0)
+                        .color(Color32::from_gray(130)),
+                );
+            });
+            ui.end_row();
+
+            // Color
+            ui.label(
+                RichText::new("Color")
+                    .monospace()
+                    .size(12.0)
+                    .color(Color32::from_gray(160)),
+            );
+            ui.horizontal(|ui| {
+                let mut colored = cmd.color.is_some();
+                if ui.checkbox(&mut colored, "").changed() {
+                    cmd.color = colored.then_some([45, 125, 235]);
+                }
+                if let Some(color) = cmd.color.as_mut() {
+                    ui.color_edit_button_srgb(color);
+                } else {
+                    ui.label(
+                        RichText::new("Default button style")
+                            .monospace()
+                            .size(11.0)
+                            .color(Color32::from_gray(130)),
+                    );
+                }
+            });
             ui.end_row();
 
             // Tag
@@ -432,6 +1651,50 @@ fn render_edit_form(
             });
             ui.end_row();
 
+            // Confirm before sending
+            ui.label(
+                RichText::new("Confirm")
+                    .monospace()
+                    .size(12.0)
+                    .color(Color32::from_gray(160)),
+            );
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut cmd.confirm, "");
+                ui.label(
+                    RichText::new("Ask before sending (destructive commands)")
+                        .monospace()
+                        .size(11.0)
+                        .color(Color32::from_gray(130)),
+                );
+            });
+            ui.end_row();
+
+            // Notify when done
+            ui.label(
+                RichText::new("Notify")
+                    .monospace()
+                    .size(12.0)
+                    .color(Color32::from_gray(160)),
+            );
+            ui.horizontal(|ui| {
+                ui.add_enabled(
+                    cmd.auto_execute,
+                    egui::Checkbox::without_text(&mut cmd.notify_when_done),
+                );
+                ui.label(
+                    RichText::new(if cmd.auto_execute {
+                        "Report the exit code when it finishes (needs shell integration)"
+                    } else {
+                        "Only for auto-executed commands"
+                    })
+                    .monospace()
+                    .size(11.0)
+                    .color(Color32::from_gray(130)),
+                )
+                .on_hover_text("Also turn on Settings → General → Session → Finish alerts.");
+            });
+            ui.end_row();
+
             // Keybinding
             ui.label(
                 RichText::new("Shortcut Key")
@@ -521,6 +1784,35 @@ fn render_edit_form(
                 }
             });
             ui.end_row();
+
+            ui.label(
+                RichText::new("Key Mode")
+                    .monospace()
+                    .size(12.0)
+                    .color(Color32::from_gray(160)),
+            );
+            ui.horizontal(|ui| {
+                ui.add_enabled_ui(!cmd.keybinding.is_empty(), |ui| {
+                    egui::ComboBox::from_id_source("keybinding_mode")
+                        .selected_text(cmd.keybinding_mode.label())
+                        .show_ui(ui, |ui| {
+                            for mode in KeyBindingMode::ALL {
+                                ui.selectable_value(&mut cmd.keybinding_mode, mode, mode.label());
+                            }
+                        });
+                });
+                ui.label(
+                    RichText::new(match cmd.keybinding_mode {
+                        KeyBindingMode::Always => "The terminal never gets the key",
+                        KeyBindingMode::AtPrompt => "Only at an idle prompt; else sent on",
+                        KeyBindingMode::PassThrough => "Run it and send the key too",
+                    })
+                    .monospace()
+                    .size(11.0)
+                    .color(Color32::from_gray(130)),
+                );
+            });
+            ui.end_row();
         });
 
     ui.add_space(12.0);
@@ -555,7 +1847,8 @@ fn render_edit_form(
 
         let save_resp = ui.add_enabled(can_save, save_btn);
         if save_resp.clicked() {
-            let edited = settings.editing.take().unwrap();
+            let mut edited = settings.editing.take().unwrap();
+            edited.icon = edited.icon.trim().to_string();
             if settings.creating_new {
                 config.commands.push(edited);
             } else {
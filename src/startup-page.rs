@@ -13,13 +13,76 @@ fn animation_total_secs() -> f32 {
     (char_count.saturating_sub(1) as f32 * CHAR_STEP_SECS) + CHAR_FADE_SECS + END_HOLD_SECS
 }
 
-pub fn is_animation_done(elapsed_secs: f32) -> bool {
-    elapsed_secs >= animation_total_secs()
+/// With `reduce_motion` the animation is skipped outright.
+pub fn is_animation_done(elapsed_secs: f32, reduce_motion: bool) -> bool {
+    reduce_motion || elapsed_secs >= animation_total_secs()
 }
 
-pub fn render(ui: &mut egui::Ui, started_at: Instant, error: Option<&str>) {
-    let elapsed = started_at.elapsed().as_secs_f32();
-    if !is_animation_done(elapsed) {
+pub fn render(ui: &mut egui::Ui, started_at: Instant, error: Option<&str>, reduce_motion: bool) {
+    let bar_rect = render_banner(ui, started_at, reduce_motion);
+    let status = if let Some(err) = error {
+        format!("PTY start failed: {}", err)
+    } else {
+        "Initializing terminal... dev by wqz".to_string()
+    };
+    let status_color = if error.is_some() {
+        egui::Color32::from_rgb(220, 90, 90)
+    } else {
+        egui::Color32::from_gray(145)
+    };
+    ui.painter().text(
+        egui::pos2(bar_rect.center().x, bar_rect.bottom() + 22.0),
+        egui::Align2::CENTER_CENTER,
+        status,
+        egui::FontId::monospace(13.0),
+        status_color,
+    );
+}
+
+/// The banner with a button per saved profile under it; returns the index of
+/// the one clicked.
+pub fn render_profile_picker(
+    ui: &mut egui::Ui,
+    started_at: Instant,
+    reduce_motion: bool,
+    names: &[&str],
+) -> Option<usize> {
+    let bar_rect = render_banner(ui, started_at, reduce_motion);
+    let list_rect = egui::Rect::from_min_max(
+        egui::pos2(bar_rect.left(), bar_rect.bottom() + 12.0),
+        egui::pos2(bar_rect.right(), ui.max_rect().bottom()),
+    );
+    let mut picked = None;
+    ui.allocate_ui_at_rect(list_rect, |ui| {
+        ui.vertical_centered(|ui| {
+            ui.label(
+                egui::RichText::new("Choose a profile")
+                    .monospace()
+                    .size(13.0)
+                    .color(egui::Color32::from_gray(145)),
+            );
+            ui.add_space(6.0);
+            for (idx, name) in names.iter().enumerate() {
+                let button = egui::Button::new(egui::RichText::new(*name).monospace().size(14.0))
+                    .min_size(egui::vec2(220.0, 28.0));
+                if ui.add(button).clicked() {
+                    picked = Some(idx);
+                }
+            }
+        });
+    });
+    picked
+}
+
+/// Draws the animated title bar centered in the panel and returns its rect.
+fn render_banner(ui: &mut egui::Ui, started_at: Instant, reduce_motion: bool) -> egui::Rect {
+    // Reduced motion draws the finished frame straight away.
+    let elapsed = if reduce_motion {
+        animation_total_secs()
+    } else {
+        started_at.elapsed().as_secs_f32()
+    };
+    if !is_animation_done(elapsed, reduce_motion) {
         ui.ctx().request_repaint();
     }
 
@@ -56,22 +119,5 @@ pub fn render(ui: &mut egui::Ui, started_at: Instant, error: Option<&str>) {
             color,
         );
     }
-
-    let status = if let Some(err) = error {
-        format!("PTY start failed: {}", err)
-    } else {
-        "Initializing terminal... dev by wqz".to_string()
-    };
-    let status_color = if error.is_some() {
-        egui::Color32::from_rgb(220, 90, 90)
-    } else {
-        egui::Color32::from_gray(145)
-    };
-    ui.painter().text(
-        egui::pos2(center.x, bar_rect.bottom() + 22.0),
-        egui::Align2::CENTER_CENTER,
-        status,
-        egui::FontId::monospace(13.0),
-        status_color,
-    );
+    bar_rect
 }
@@ -1,27 +1,110 @@
-use std::collections::VecDeque;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write as _;
+use std::hash::{Hash, Hasher};
 use std::io;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Duration, Instant};
 
-use alacritty_terminal::event::VoidListener;
+use alacritty_terminal::event::{Event as TermEvent, EventListener};
 use alacritty_terminal::grid::Dimensions;
 use alacritty_terminal::index::{Column, Line, Point};
 use alacritty_terminal::term::cell::Flags as CellFlags;
+use alacritty_terminal::term::color::Colors;
 use alacritty_terminal::term::{Config, Term, TermMode};
-use alacritty_terminal::vte::ansi::{self, Color as TermColor, NamedColor};
+use alacritty_terminal::vte::ansi::{self, Color as TermColor, Handler as _, NamedColor, Rgb};
 
-use winit::keyboard::{Key, NamedKey};
+use regex::Regex;
+use winit::keyboard::{Key, KeyCode, KeyLocation, NamedKey, PhysicalKey};
 
-use crate::pty::{self, PtySize, PtyWriter};
+use crate::appconfig::{CopyFilter, CursorShape, InputLog, PasteLineEnding};
+use crate::pty::{PtyBackend, PtySize, ShellConfig, SystemPty};
+use crate::search::SearchState;
+use crate::sessionlog::SessionLog;
 
 pub const TERM_FONT_SIZE: f32 = 14.0;
+/// Default terminal background; cells without an explicit bg show the panel fill.
+pub const TERM_BACKGROUND: egui::Color32 = egui::Color32::from_rgb(18, 18, 18);
+/// Default text color.
+const TERM_FOREGROUND: egui::Color32 = egui::Color32::from_rgb(204, 204, 204);
+/// Smallest grid the PTY is ever resized to. Shells and TUIs misbehave at 1×1,
+/// and a window dragged to its minimum should not reflow everything to 1 column.
+pub const MIN_GRID_ROWS: u16 = 2;
+pub const MIN_GRID_COLS: u16 = 10;
 const VT_LOG_MAX_LINES: usize = 2000;
-const MAX_SELECTION_COPY_BYTES: usize = 2 * 1024 * 1024;
-const CWD_OSC_PREFIX: &[u8] = b"\x1b]633;CWD=";
+/// Smallest PTY read buffer accepted from the config.
+const MIN_READ_BUFFER_SIZE: usize = 1024;
+/// Ceiling on the configurable copy size, so one huge selection cannot
+/// balloon memory or the clipboard.
+pub const MAX_SELECTION_COPY_LIMIT: usize = 64 * 1024 * 1024;
+/// Shell-integration marks: `633;CWD=<dir>`, `633;E;<escaped command line>`,
+/// `633;C` (command started) and `633;A` / `633;D[;exit]` (prompt, command done).
+const SHELL_INTEGRATION_OSC_PREFIX: &[u8] = b"\x1b]633;";
+/// XTMODKEYS: `CSI > 4 ; n m` sets xterm's modifyOtherKeys level to n, `CSI > 4 m`
+/// resets it. alacritty parses but ignores it, so `process_input` tracks it.
+const MODIFY_OTHER_KEYS_PREFIX: &[u8] = b"\x1b[>4";
+/// XTWINOPS `CSI 8 ; rows ; cols t` asks for a text area size in cells (0 or
+/// empty keeps that dimension). alacritty ignores it, so `process_input`
+/// reports it for the embedder to apply or not.
+const RESIZE_REQUEST_PREFIX: &[u8] = b"\x1b[8;";
+/// Longest unterminated `rows;cols` kept across chunks; two `u16`s need 11.
+const RESIZE_REQUEST_MAX_PARAMS: usize = 16;
+/// Erase in display: `CSI 2 J` clears the screen, whose lines alacritty keeps
+/// in scrollback, and `CSI 3 J` drops the scrollback. `process_input` reports
+/// both so the view can move to the cleared screen.
+const ERASE_SCREEN: &[u8] = b"\x1b[2J";
+const ERASE_SCROLLBACK: &[u8] = b"\x1b[3J";
+/// DECSET / DECRST 1049, the only alternate screen switch alacritty honors
+/// (it ignores 47 and 1047). Tracked so each erase is credited to the screen
+/// it hit; the mode may share a sequence with others (`ESC[?1049;25h`).
+const PRIVATE_MODE_PREFIX: &[u8] = b"\x1b[?";
+const ALT_SCREEN_MODE: u16 = 1049;
+/// An unfinished private mode sequence longer than this isn't carried over.
+const PRIVATE_MODE_MAX_LEN: usize = 64;
 const OSC_BEL: u8 = 0x07;
 const OSC_ST: &[u8] = b"\x1b\\";
+/// Throughput is averaged over windows of this length.
+const THROUGHPUT_WINDOW: Duration = Duration::from_secs(1);
+/// Output rate above which the status bar shows the flood indicator.
+const HEAVY_OUTPUT_BYTES_PER_SEC: usize = 256 * 1024;
+/// Predicted characters the shell has not echoed within this long are
+/// dropped, and prediction pauses until Enter (likely a password prompt).
+const LOCAL_ECHO_TIMEOUT: Duration = Duration::from_secs(1);
+/// How long the scrollbar stays up after the view scrolls without the
+/// pointer over the terminal.
+const SCROLLBAR_LINGER: Duration = Duration::from_secs(1);
+/// Shortest gap between layout-driven resizes. Output ConPTY formatted for
+/// the previous size can land after each resize; fewer resizes during a
+/// window drag leave fewer chances for it to garble the grid.
+const RESIZE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Callback a background thread uses to wake the UI event loop.
+pub type Wakeup = Arc<dyn Fn() + Send + Sync>;
+
+/// Observer callbacks set on a `TerminalInstance`. They run on whichever thread
+/// calls `process_input` (the UI thread in terminrt), synchronously, while the
+/// instance is mutably borrowed: keep them short and don't block. `Send` is
+/// required only because the instance is built on a spawn thread.
+pub type TitleCallback = Box<dyn FnMut(Option<&str>) + Send>;
+pub type CwdCallback = Box<dyn FnMut(&str) + Send>;
+pub type ExitCallback = Box<dyn FnMut() + Send>;
+
+/// Collects events alacritty raises while parsing, for `process_input` to
+/// dispatch once the chunk is done.
+#[derive(Clone, Default)]
+pub struct TermEventQueue(Arc<Mutex<Vec<TermEvent>>>);
+
+impl EventListener for TermEventQueue {
+    fn send_event(&self, event: TermEvent) {
+        if let Ok(mut events) = self.0.lock() {
+            events.push(event);
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub enum VtLogEntry {
@@ -29,11 +112,30 @@ pub enum VtLogEntry {
     Output(String),
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Debug, Default)]
 pub struct TerminalSelectionState {
     anchor: Option<(usize, usize)>,
     focus: Option<(usize, usize)>,
     dragging: bool,
+    /// Keyboard cursor while copy mode is active (absolute row, column).
+    copy_cursor: Option<(usize, usize)>,
+    /// Set by `v` / Shift+movement: cursor moves extend the selection.
+    copy_selecting: bool,
+    /// The copy cursor moved; scroll it into view on the next render.
+    copy_scroll_pending: bool,
+    /// Characters that end a double-click word selection, besides whitespace.
+    word_separators: String,
+    /// A double-click selected a word since the last `take_word_selected`.
+    word_selected: bool,
+}
+
+/// Result of a key press in copy mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CopyModeAction {
+    None,
+    /// Copy the selection and leave copy mode.
+    Copy,
+    Exit,
 }
 
 impl TerminalSelectionState {
@@ -47,6 +149,10 @@ impl TerminalSelectionState {
         self.anchor = Some((row, col));
         self.focus = Some((row, col));
         self.dragging = true;
+        if self.copy_cursor.is_some() {
+            self.copy_cursor = Some((row, col));
+            self.copy_selecting = false;
+        }
     }
 
     fn update(&mut self, row: usize, col: usize) {
@@ -59,6 +165,79 @@ impl TerminalSelectionState {
         self.dragging = false;
     }
 
+    pub fn set_word_separators(&mut self, separators: &str) {
+        if self.word_separators != separators {
+            self.word_separators = separators.to_string();
+        }
+    }
+
+    /// Whether a double-click selected a word since the last call, for
+    /// copying it right away.
+    pub fn take_word_selected(&mut self) -> bool {
+        std::mem::take(&mut self.word_selected)
+    }
+
+    fn is_word_separator(&self, c: char) -> bool {
+        c == ' ' || c == '\t' || c == '\0' || self.word_separators.contains(c)
+    }
+
+    /// Select the word under (row, col), following soft-wrapped lines. A
+    /// click on a separator selects nothing.
+    fn select_word(&mut self, term: &Term<TermEventQueue>, row: usize, col: usize) {
+        let grid = term.grid();
+        let total_lines = grid.total_lines();
+        let num_cols = term.columns();
+        if row >= total_lines || col >= num_cols {
+            return;
+        }
+        let top_line = -(grid.history_size() as i32);
+        let cell = |row: usize, col: usize| &grid[Line(top_line + row as i32)][Column(col)];
+        let wraps = |row: usize| cell(row, num_cols - 1).flags.contains(CellFlags::WRAPLINE);
+        // The trailing half of a wide character belongs to the word before it.
+        let in_word = |row: usize, col: usize| {
+            let cell = cell(row, col);
+            cell.flags.contains(CellFlags::WIDE_CHAR_SPACER) || !self.is_word_separator(cell.c)
+        };
+        if !in_word(row, col) {
+            self.clear();
+            return;
+        }
+
+        let mut start = (row, col);
+        loop {
+            let prev = if start.1 > 0 {
+                (start.0, start.1 - 1)
+            } else if start.0 > 0 && wraps(start.0 - 1) {
+                (start.0 - 1, num_cols - 1)
+            } else {
+                break;
+            };
+            if !in_word(prev.0, prev.1) {
+                break;
+            }
+            start = prev;
+        }
+        let mut end = (row, col);
+        loop {
+            let next = if end.1 + 1 < num_cols {
+                (end.0, end.1 + 1)
+            } else if end.0 + 1 < total_lines && wraps(end.0) {
+                (end.0 + 1, 0)
+            } else {
+                break;
+            };
+            if !in_word(next.0, next.1) {
+                break;
+            }
+            end = next;
+        }
+
+        self.anchor = Some(start);
+        self.focus = Some(end);
+        self.dragging = false;
+        self.word_selected = true;
+    }
+
     fn normalized(&self) -> Option<((usize, usize), (usize, usize))> {
         let mut start = self.anchor?;
         let mut end = self.focus?;
@@ -72,16 +251,126 @@ impl TerminalSelectionState {
         matches!(self.normalized(), Some((start, end)) if start != end)
     }
 
+    pub fn copy_mode_active(&self) -> bool {
+        self.copy_cursor.is_some()
+    }
+
+    /// Enter copy mode with the keyboard cursor on the terminal cursor.
+    pub fn enter_copy_mode(&mut self, terminal: &TerminalInstance) {
+        let term = terminal.term();
+        let grid = term.grid();
+        let cursor = grid.cursor.point;
+        let row = (cursor.line.0 + grid.history_size() as i32).max(0) as usize;
+        self.clear();
+        self.copy_cursor = Some((row, cursor.column.0));
+        self.copy_selecting = false;
+        self.copy_scroll_pending = true;
+    }
+
+    pub fn exit_copy_mode(&mut self) {
+        self.clear();
+        self.copy_cursor = None;
+        self.copy_selecting = false;
+        self.copy_scroll_pending = false;
+    }
+
+    /// Arrows / hjkl move, PageUp/PageDown move a screen, Home/End jump within
+    /// the line. Shift+movement or `v` selects; Enter/`y` copy; Esc exits.
+    pub fn handle_copy_mode_key(
+        &mut self,
+        key: &Key,
+        shift: bool,
+        terminal: &TerminalInstance,
+    ) -> CopyModeAction {
+        let Some((row, col)) = self.copy_cursor else {
+            return CopyModeAction::None;
+        };
+        let term = terminal.term();
+        let last_row = term.grid().total_lines().saturating_sub(1);
+        let last_col = term.columns().saturating_sub(1);
+        let page = term.screen_lines().max(1);
+        // The grid may have shrunk (resize, clear) since the cursor was placed.
+        let (row, col) = (row.min(last_row), col.min(last_col));
+
+        let target = match key {
+            Key::Named(NamedKey::Escape) => return CopyModeAction::Exit,
+            Key::Named(NamedKey::Enter) => return CopyModeAction::Copy,
+            Key::Character(c) if c.eq_ignore_ascii_case("y") => return CopyModeAction::Copy,
+            Key::Character(c) if c.eq_ignore_ascii_case("v") => {
+                if self.copy_selecting {
+                    self.copy_selecting = false;
+                    self.anchor = None;
+                    self.focus = None;
+                } else {
+                    self.copy_selecting = true;
+                    self.anchor = Some((row, col));
+                    self.focus = Some((row, col));
+                }
+                return CopyModeAction::None;
+            }
+            Key::Named(NamedKey::ArrowLeft) => (row, col.saturating_sub(1)),
+            Key::Named(NamedKey::ArrowRight) => (row, (col + 1).min(last_col)),
+            Key::Named(NamedKey::ArrowUp) => (row.saturating_sub(1), col),
+            Key::Named(NamedKey::ArrowDown) => ((row + 1).min(last_row), col),
+            Key::Named(NamedKey::PageUp) => (row.saturating_sub(page), col),
+            Key::Named(NamedKey::PageDown) => ((row + page).min(last_row), col),
+            Key::Named(NamedKey::Home) => (row, 0),
+            Key::Named(NamedKey::End) => (row, last_col),
+            Key::Character(c) => match c.as_str() {
+                "h" => (row, col.saturating_sub(1)),
+                "l" => (row, (col + 1).min(last_col)),
+                "k" => (row.saturating_sub(1), col),
+                "j" => ((row + 1).min(last_row), col),
+                _ => return CopyModeAction::None,
+            },
+            _ => return CopyModeAction::None,
+        };
+
+        if shift && !self.copy_selecting {
+            self.copy_selecting = true;
+            self.anchor = Some((row, col));
+        }
+        if self.copy_selecting {
+            self.focus = Some(target);
+        }
+        self.copy_cursor = Some(target);
+        self.copy_scroll_pending = true;
+        CopyModeAction::None
+    }
+}
+
+/// Laid-out row galleys from previous frames, keyed by absolute row index.
+///
+/// Each entry stores a hash of the row's resolved (char, fg, bg) cells, so rows
+/// whose content, cursor and selection are unchanged reuse their galley instead
+/// of rebuilding a `LayoutJob` and going through text layout again.
+#[derive(Default)]
+pub struct TerminalRenderCache {
+    rows: HashMap<usize, (u64, Arc<egui::Galley>)>,
+    /// Vertical scroll offset last frame, and when it last changed; keeps the
+    /// scrollbar visible for a moment after any scroll.
+    scroll_offset: f32,
+    scrolled_at: Option<Instant>,
+}
+
+/// In-progress IME composition (winit `Ime::Preedit`), drawn inline at the cursor.
+#[derive(Clone, Debug, Default)]
+pub struct ImePreedit {
+    pub text: String,
+    /// Byte range of the IME cursor/selection within `text`.
+    pub cursor: Option<(usize, usize)>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum ScrollRequest {
     /// Scroll so the top of the terminal screen (after scrollback) is visible.
     ScreenTop,
-    /// Scroll so the current cursor line is aligned to the top.
-    CursorTop,
     /// Scroll so the current cursor line is visible while typing.
     CursorLine,
+    /// Scroll to the oldest line of scrollback.
+    ScrollbackTop,
+    /// Align this row, counted from the top of scrollback, to the top.
+    Row(usize),
 }
 
 #[derive(Copy, Clone)]
@@ -102,34 +391,224 @@ impl Dimensions for TermDims {
     }
 }
 
+/// PTY output rate as measured by `process_input`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct OutputStats {
+    pub bytes_per_sec: usize,
+    /// Some frame had more output queued than it could parse, so the screen
+    /// skipped intermediate states.
+    pub coalesced: bool,
+}
+
+/// A watched command that finished (see `TerminalInstance::watch_next_command`).
+#[derive(Clone, Debug)]
+pub struct FinishedCommand {
+    pub label: String,
+    /// From `633;D;<code>`; `None` when the shell did not report one.
+    pub exit_code: Option<i32>,
+    pub elapsed: Duration,
+}
+
+/// Command waiting for its completion mark; `started_at` is set by the next
+/// `633;C` (or at once when the shell never sends that mark).
+struct WatchedCommand {
+    label: String,
+    started_at: Option<Instant>,
+}
+
+#[derive(Default)]
+struct ThroughputMeter {
+    window_start: Option<Instant>,
+    window_bytes: usize,
+    window_coalesced: bool,
+    /// Last completed window and when it ended.
+    last: OutputStats,
+    last_at: Option<Instant>,
+}
+
+/// Typed characters drawn ahead of the PTY's echo (`local_echo` setting).
+/// Each is confirmed once the same character lands in the grid where it was
+/// predicted; anything else the screen does discards the prediction.
+#[derive(Default)]
+struct LocalEcho {
+    /// Printable ASCII typed but not yet echoed, drawn from `anchor`.
+    pending: String,
+    anchor: Point,
+    /// When the oldest still-pending character was typed or last confirmed.
+    since: Option<Instant>,
+    /// An echo never came; no predictions until the next Enter.
+    suspended: bool,
+}
+
+impl LocalEcho {
+    fn clear(&mut self) {
+        self.pending.clear();
+        self.since = None;
+    }
+}
+
+impl ThroughputMeter {
+    fn record(&mut self, bytes: usize, coalesced: bool) {
+        let now = Instant::now();
+        if self.window_bytes == 0 && bytes > 0 {
+            // Start timing at the first byte, not at the end of an idle spell.
+            self.window_start = Some(now);
+        }
+        self.window_bytes += bytes;
+        self.window_coalesced |= coalesced;
+        let Some(start) = self.window_start else {
+            return;
+        };
+        let elapsed = now - start;
+        if elapsed >= THROUGHPUT_WINDOW {
+            self.last = OutputStats {
+                bytes_per_sec: (self.window_bytes as f64 / elapsed.as_secs_f64()) as usize,
+                coalesced: self.window_coalesced,
+            };
+            self.last_at = Some(now);
+            self.window_start = Some(now);
+            self.window_bytes = 0;
+            self.window_coalesced = false;
+        }
+    }
+}
+
 pub struct TerminalInstance {
-    term: Term<VoidListener>,
+    term: Term<TermEventQueue>,
+    term_events: TermEventQueue,
     processor: ansi::Processor,
     rx: mpsc::Receiver<Vec<u8>>,
-    pty_writer: Arc<Mutex<PtyWriter>>,
+    /// Bound of `rx`; also the most chunks parsed per `process_input`.
+    queue_capacity: usize,
+    throughput: ThroughputMeter,
+    wakeup: Wakeup,
+    /// Set by the reader thread when it has woken the UI; cleared before draining
+    /// so the next chunk wakes it again.
+    wake_pending: Arc<AtomicBool>,
+    pty: Box<dyn PtyBackend>,
     vt_lines: VecDeque<VtLogEntry>,
     vt_pending: String,
     osc_tracking_buffer: Vec<u8>,
+    /// modifyOtherKeys level requested by the application (0 = off).
+    modify_other_keys: u8,
+    /// Unfinished XTMODKEYS sequence from the end of the previous chunk.
+    modify_other_keys_carry: Vec<u8>,
+    /// Latest XTWINOPS resize request not yet returned by `process_input`.
+    resize_request: Option<(u16, u16)>,
+    /// Unfinished resize request from the end of the previous chunk.
+    resize_request_carry: Vec<u8>,
+    /// Unfinished erase or alternate screen switch at the end of a chunk.
+    erase_display_carry: Vec<u8>,
+    /// Profile foreground and background, used where no OSC 10/11 color is set.
+    theme_colors: [Option<[u8; 3]>; 2],
     current_dir: String,
+    /// Set once the shell reports its directory via OSC 633; until then
+    /// `current_dir` is only the startup directory.
+    current_dir_reported: bool,
+    /// `(cwd, command line)` pairs from `633;E` marks, drained by the UI.
+    executed_commands: Vec<(String, String)>,
+    /// When the foreground command started (`633;C`); cleared by the next
+    /// prompt or `633;D`.
+    command_started_at: Option<Instant>,
+    /// Whether the shell has sent a `633;C` mark this session.
+    command_start_marks: bool,
+    /// Command to report once it finishes, and those finished since the
+    /// last `take_finished_commands`.
+    watched_command: Option<WatchedCommand>,
+    finished_commands: Vec<FinishedCommand>,
+    /// Cursor style restored by DECSCUSR 0 (`ESC[0 q`); from the settings.
+    default_cursor_style: ansi::CursorStyle,
+    /// `prompt_regex` setting as last applied, and its compiled form (`None`
+    /// when empty or invalid). Marks prompts when the shell sends no OSC 633.
+    prompt_pattern: String,
+    prompt_regex: Option<Regex>,
+    /// Optional file that receives every chunk of PTY output.
+    session_log: Option<SessionLog>,
+    /// Window title set by the application (OSC 0/2), if any.
+    title: Option<String>,
+    on_title: Option<TitleCallback>,
+    on_cwd_change: Option<CwdCallback>,
+    on_exit: Option<ExitCallback>,
+    /// When the session was created, and when the PTY closed (after which
+    /// `on_exit` has run).
+    started_at: Instant,
+    exited_at: Option<Instant>,
+    /// When `request_resize` last resized the grid.
+    resized_at: Option<Instant>,
+    /// Columns between tab stops; alacritty itself always starts with 8.
+    tab_width: usize,
+    local_echo: LocalEcho,
+    /// How input is recorded in the VT log (`input_log` setting).
+    input_log: InputLog,
+    /// `password_guard` setting: no prediction or logging at password prompts.
+    password_guard: bool,
     _reader_thread: thread::JoinHandle<()>,
 }
 
 pub struct ProcessInputResult {
     pub had_input: bool,
     pub pty_closed: bool,
+    /// The application entered or left the alternate screen (`ESC[?1049h/l`).
+    pub alt_screen_toggled: bool,
+    /// The application rang the bell (BEL).
+    pub bell: bool,
+    /// Grid size (rows, cols) the application last asked for with
+    /// `ESC[8;rows;cols t`; not applied to the grid.
+    pub resize_request: Option<(u16, u16)>,
+    /// The main screen was erased with `ESC[2J`; its old lines are now the
+    /// newest scrollback.
+    pub screen_erased: bool,
+    /// The scrollback was dropped with `ESC[3J`.
+    pub scrollback_erased: bool,
 }
 
 impl TerminalInstance {
-    pub fn new(rows: u16, cols: u16, startup_dir: PathBuf) -> io::Result<Self> {
-        let size = PtySize { rows, cols };
-        let (mut reader, writer) = pty::spawn_pty(size, &startup_dir)?;
-        let pty_writer = Arc::new(Mutex::new(writer));
+    pub fn new(
+        rows: u16,
+        cols: u16,
+        startup_dir: PathBuf,
+        shell_config: &ShellConfig,
+        wakeup: Wakeup,
+    ) -> io::Result<Self> {
+        let pty = SystemPty::spawn(PtySize { rows, cols }, &startup_dir, shell_config)?;
+        Ok(Self::with_backend(
+            rows,
+            cols,
+            startup_dir,
+            pty,
+            shell_config,
+            wakeup,
+        ))
+    }
+
+    /// Like `new`, but on any PTY backend, such as a `MemoryPty` replaying
+    /// recorded output. Only the reader settings of `shell_config` are used;
+    /// the backend is expected to be running already.
+    pub fn with_backend(
+        rows: u16,
+        cols: u16,
+        startup_dir: PathBuf,
+        mut pty: impl PtyBackend + 'static,
+        shell_config: &ShellConfig,
+        wakeup: Wakeup,
+    ) -> Self {
+        let mut reader = pty.take_reader();
 
-        let (tx, rx) = mpsc::channel::<Vec<u8>>();
+        // Bounded, so a program that outputs faster than the UI can parse (`yes`)
+        // blocks in its own writes instead of growing our memory. A longer queue
+        // absorbs bigger bursts before that backpressure kicks in; a shorter one
+        // keeps less unparsed output around.
+        let queue_capacity = shell_config.queue_capacity.max(1);
+        let (tx, rx) = mpsc::sync_channel::<Vec<u8>>(queue_capacity);
+        let wake_pending = Arc::new(AtomicBool::new(false));
+        let reader_wake_pending = wake_pending.clone();
+        let reader_wakeup = wakeup.clone();
+        let read_buffer_size = shell_config.read_buffer_size.max(MIN_READ_BUFFER_SIZE);
 
-        // Reader thread owns the PtyReader directly — no mutex needed
+        // Reader thread owns the reader directly — no mutex needed
         let reader_thread = thread::spawn(move || {
-            let mut buf = vec![0u8; 4096];
+            let wakeup = reader_wakeup;
+            let mut buf = vec![0u8; read_buffer_size];
             loop {
                 match reader.read(&mut buf) {
                     Ok(0) => break,
@@ -137,10 +616,17 @@ impl TerminalInstance {
                         if tx.send(buf[..n].to_vec()).is_err() {
                             break;
                         }
+                        // One wakeup per drain is enough; skip it while one is queued.
+                        if !reader_wake_pending.swap(true, Ordering::AcqRel) {
+                            wakeup();
+                        }
                     }
                     Err(_) => break,
                 }
             }
+            // Let the UI notice the closed channel.
+            drop(tx);
+            wakeup();
         });
 
         let config = Config::default();
@@ -148,61 +634,395 @@ impl TerminalInstance {
             cols: cols as usize,
             rows: rows as usize,
         };
-        let term = Term::new(config, &dims, VoidListener);
+        let term_events = TermEventQueue::default();
+        let term = Term::new(config, &dims, term_events.clone());
         let processor = ansi::Processor::new();
 
-        Ok(Self {
+        Self {
             term,
+            term_events,
             processor,
             rx,
-            pty_writer,
+            queue_capacity,
+            throughput: ThroughputMeter::default(),
+            wakeup,
+            wake_pending,
+            pty: Box::new(pty),
             vt_lines: VecDeque::new(),
             vt_pending: String::new(),
             osc_tracking_buffer: Vec::new(),
+            modify_other_keys: 0,
+            modify_other_keys_carry: Vec::new(),
+            resize_request: None,
+            resize_request_carry: Vec::new(),
+            erase_display_carry: Vec::new(),
+            theme_colors: [None; 2],
             current_dir: startup_dir.display().to_string(),
+            current_dir_reported: false,
+            executed_commands: Vec::new(),
+            command_started_at: None,
+            command_start_marks: false,
+            watched_command: None,
+            finished_commands: Vec::new(),
+            default_cursor_style: ansi::CursorStyle::default(),
+            prompt_pattern: String::new(),
+            prompt_regex: None,
+            session_log: None,
+            title: None,
+            on_title: None,
+            on_cwd_change: None,
+            on_exit: None,
+            started_at: Instant::now(),
+            exited_at: None,
+            resized_at: None,
+            tab_width: 8,
+            local_echo: LocalEcho::default(),
+            input_log: InputLog::Masked,
+            password_guard: true,
             _reader_thread: reader_thread,
-        })
+        }
     }
 
     /// Process pending PTY output, feeding bytes into the terminal emulator.
     pub fn process_input(&mut self) -> ProcessInputResult {
         let mut had_input = false;
         let mut pty_closed = false;
-        loop {
+        let mut bell = false;
+        let mut screen_erased = false;
+        let mut scrollback_erased = false;
+        let was_alt_screen = self.is_alt_screen();
+        self.wake_pending.store(false, Ordering::Release);
+        // The reader refills the queue while we parse; stop after one queue's
+        // worth so a flood still lets the frame render, and come back next frame.
+        let mut backlog = true;
+        let mut bytes = 0;
+        for _ in 0..self.queue_capacity {
             match self.rx.try_recv() {
                 Ok(data) => {
                     had_input = true;
-                    self.update_current_dir_from_osc(&data);
+                    bytes += data.len();
+                    self.write_session_log(&data);
+                    self.update_shell_integration_from_osc(&data);
+                    self.update_modify_other_keys(&data);
+                    self.update_resize_request(&data);
+                    // Full-screen apps erase the alternate screen all the time;
+                    // only the main screen has scrollback to keep or drop.
+                    let (erase_screen, erase_scrollback) =
+                        self.scan_erase_display(&data, self.is_alt_screen());
+                    screen_erased |= erase_screen;
+                    scrollback_erased |= erase_scrollback;
                     self.append_vt_log(&data);
-                    self.processor.advance(&mut self.term, &data);
+                    // Answer each color query before the rest of the chunk can
+                    // change the color it asked about.
+                    for part in split_after_osc_queries(&data) {
+                        self.processor.advance(&mut self.term, part);
+                        bell |= self.dispatch_term_events();
+                    }
+                }
+                Err(mpsc::TryRecvError::Empty) => {
+                    backlog = false;
+                    break;
                 }
-                Err(mpsc::TryRecvError::Empty) => break,
                 Err(mpsc::TryRecvError::Disconnected) => {
+                    backlog = false;
                     pty_closed = true;
                     break;
                 }
             }
         }
+        if backlog {
+            (self.wakeup)();
+        }
+        self.throughput.record(bytes, backlog);
+        self.reconcile_local_echo(had_input);
+        // Without OSC 633, output that leaves the cursor on a prompt line ends
+        // whatever command was running.
+        if had_input && self.command_started_at.is_some() {
+            let cursor_line = self.term.grid().cursor.point.line;
+            let at_prompt = self.prompt_fallback().is_some_and(|re| {
+                self.prompt_at(re, cursor_line, &mut String::new())
+                    .is_some()
+            });
+            if at_prompt {
+                self.command_started_at = None;
+            }
+        }
+        if pty_closed && self.exited_at.is_none() {
+            self.exited_at = Some(Instant::now());
+            if let Some(on_exit) = self.on_exit.as_mut() {
+                on_exit();
+            }
+        }
+        if let Some(log) = self.session_log.as_mut() {
+            if let Err(e) = log.maybe_flush() {
+                eprintln!("Session log flush failed: {}", e);
+                self.session_log = None;
+            }
+        }
         ProcessInputResult {
             had_input,
             pty_closed,
+            alt_screen_toggled: self.is_alt_screen() != was_alt_screen,
+            bell,
+            resize_request: self.resize_request.take(),
+            screen_erased,
+            scrollback_erased,
+        }
+    }
+
+    /// Handle events the emulator queued while parsing; returns whether one
+    /// was the bell.
+    fn dispatch_term_events(&mut self) -> bool {
+        let events = match self.term_events.0.lock() {
+            Ok(mut events) => std::mem::take(&mut *events),
+            Err(_) => return false,
+        };
+        let mut bell = false;
+        for event in events {
+            let title = match event {
+                TermEvent::Bell => {
+                    bell = true;
+                    continue;
+                }
+                TermEvent::Title(title) => Some(title),
+                TermEvent::ResetTitle => None,
+                TermEvent::ColorRequest(index, formatter) => {
+                    // OSC 4/10/11/12 with `?`: programs ask for the background to
+                    // pick a light or dark theme.
+                    let color = self.reported_color(index);
+                    let reply = formatter(Rgb {
+                        r: color.r(),
+                        g: color.g(),
+                        b: color.b(),
+                    });
+                    let _ = self.pty.write_all(reply.as_bytes());
+                    continue;
+                }
+                TermEvent::PtyWrite(reply) => {
+                    // Replies alacritty builds itself, such as the `ESC[18t` size
+                    // report (`ESC[8;rows;cols t`) and cursor position reports.
+                    let _ = self.pty.write_all(reply.as_bytes());
+                    continue;
+                }
+                _ => continue,
+            };
+            if title == self.title {
+                continue;
+            }
+            self.title = title;
+            if let Some(on_title) = self.on_title.as_mut() {
+                on_title(self.title.as_deref());
+            }
+        }
+        bell
+    }
+
+    /// Called with the new title when the application sets it (OSC 0/2), or
+    /// `None` when it resets it.
+    pub fn set_on_title(&mut self, callback: impl FnMut(Option<&str>) + Send + 'static) {
+        self.on_title = Some(Box::new(callback));
+    }
+
+    /// Called when the shell reports a different working directory (OSC 633).
+    #[allow(dead_code)] // embedding API; terminrt itself polls `current_dir`
+    pub fn set_on_cwd_change(&mut self, callback: impl FnMut(&str) + Send + 'static) {
+        self.on_cwd_change = Some(Box::new(callback));
+    }
+
+    /// Called once, when the PTY output closes.
+    pub fn set_on_exit(&mut self, callback: impl FnMut() + Send + 'static) {
+        self.on_exit = Some(Box::new(callback));
+    }
+
+    /// Start appending PTY output to `path` (escapes stripped when `clean`).
+    pub fn start_session_log(&mut self, path: &Path, clean: bool) -> io::Result<()> {
+        self.session_log = Some(SessionLog::open(path, clean)?);
+        Ok(())
+    }
+
+    pub fn stop_session_log(&mut self) {
+        self.session_log = None;
+    }
+
+    pub fn session_log(&self) -> Option<&SessionLog> {
+        self.session_log.as_ref()
+    }
+
+    fn write_session_log(&mut self, data: &[u8]) {
+        if let Some(log) = self.session_log.as_mut() {
+            if let Err(e) = log.write(data) {
+                eprintln!("Session log write failed: {}", e);
+                self.session_log = None;
+            }
+        }
+    }
+
+    /// Write keyboard input, first predicting its echo when `local_echo` is on.
+    /// Only printable ASCII on the primary screen is predicted; Enter, editing
+    /// and control keys drop the prediction, since where they leave the line
+    /// is up to the shell. At a password prompt nothing is predicted or logged.
+    pub fn write_keystrokes(&mut self, data: &[u8], local_echo: bool) {
+        let secret = self.input_is_secret();
+        self.predict_echo(data, local_echo && !secret);
+        self.note_command_start(data);
+        self.write_input(data, secret);
+    }
+
+    /// Whether typed or pasted input should be kept out of the log and echo
+    /// prediction: the guard is on and the cursor sits at a password prompt.
+    fn input_is_secret(&self) -> bool {
+        self.password_guard && self.at_password_prompt()
+    }
+
+    /// Write user input to the PTY, logging it unless it is `secret`.
+    fn write_input(&mut self, data: &[u8], secret: bool) {
+        let _ = self.pty.write_all(data);
+        if !secret {
+            self.push_input_log(data);
+        }
+    }
+
+    /// Whether the cursor's line reads like a password prompt (`Password:`,
+    /// `[sudo] password for ...:`, `Enter passphrase for key ...:`), possibly
+    /// followed by the `*` some programs echo. ConPTY does not pass on the
+    /// tty's echo flag, so this goes by the text.
+    fn at_password_prompt(&self) -> bool {
+        static PASSWORD_PROMPT: std::sync::OnceLock<Regex> = std::sync::OnceLock::new();
+        let re = PASSWORD_PROMPT.get_or_init(|| {
+            Regex::new(r"(?i)\b(password|passphrase|passcode|pin)\b.*:\s*\**$")
+                .expect("valid password prompt regex")
+        });
+        let line = self.row_text(self.term.grid().cursor.point.line);
+        re.is_match(line.trim_end())
+    }
+
+    pub fn set_input_logging(&mut self, input_log: InputLog, password_guard: bool) {
+        self.input_log = input_log;
+        self.password_guard = password_guard;
+    }
+
+    /// Enter on a prompt line with something typed after the prompt starts a
+    /// command, for sessions that rely on the prompt pattern.
+    fn note_command_start(&mut self, data: &[u8]) {
+        if !data.contains(&b'\r') {
+            return;
+        }
+        let Some(re) = self.prompt_fallback() else {
+            return;
+        };
+        let cursor_line = self.term.grid().cursor.point.line;
+        let mut text = String::new();
+        if let Some(prompt_end) = self.prompt_at(re, cursor_line, &mut text) {
+            if !text[prompt_end..].trim().is_empty() {
+                self.command_started_at = Some(Instant::now());
+            }
         }
     }
 
+    fn predict_echo(&mut self, data: &[u8], enabled: bool) {
+        let echo = &mut self.local_echo;
+        if !enabled || data.contains(&b'\r') {
+            echo.clear();
+            echo.suspended = false;
+            return;
+        }
+        let printable = !data.is_empty() && data.iter().all(|b| (0x20..0x7f).contains(b));
+        let mode = *self.term.mode();
+        if !printable
+            || echo.suspended
+            || mode.contains(TermMode::ALT_SCREEN)
+            || !mode.contains(TermMode::SHOW_CURSOR)
+        {
+            echo.clear();
+            return;
+        }
+        let cursor = self.term.grid().cursor.point;
+        if echo.pending.is_empty() {
+            echo.anchor = cursor;
+            echo.since = Some(Instant::now());
+        }
+        // Stay on the cursor's row; wrapping is the shell's business.
+        let end = echo.anchor.column.0 + echo.pending.len() + data.len();
+        if end >= self.term.columns() {
+            return;
+        }
+        echo.pending.extend(data.iter().map(|&b| b as char));
+    }
+
+    /// Confirm predicted characters the PTY has since echoed, and give up on
+    /// the rest once the screen goes elsewhere or the echo is overdue.
+    fn reconcile_local_echo(&mut self, had_input: bool) {
+        let echo = &mut self.local_echo;
+        if echo.pending.is_empty() {
+            return;
+        }
+        if had_input {
+            let grid = self.term.grid();
+            let confirmed = echo
+                .pending
+                .chars()
+                .zip(echo.anchor.column.0..grid.columns())
+                .take_while(|&(ch, col)| grid[echo.anchor.line][Column(col)].c == ch)
+                .count();
+            if confirmed > 0 {
+                echo.pending.drain(..confirmed);
+                echo.anchor.column += confirmed;
+                echo.since = (!echo.pending.is_empty()).then(Instant::now);
+            }
+            if grid.cursor.point != echo.anchor {
+                echo.clear();
+                return;
+            }
+        }
+        if echo
+            .since
+            .is_some_and(|since| since.elapsed() >= LOCAL_ECHO_TIMEOUT)
+        {
+            echo.clear();
+            echo.suspended = true;
+        }
+    }
+
+    /// Predicted text still waiting for its echo, drawn from the cursor.
+    pub fn local_echo_preview(&self) -> &str {
+        &self.local_echo.pending
+    }
+
     /// Write user input to the PTY.
     pub fn write_to_pty(&mut self, data: &[u8]) {
-        if let Ok(mut writer) = self.pty_writer.lock() {
-            let _ = writer.write_all(data);
-        }
-        
-        // Log input
+        self.write_input(data, false);
+    }
+
+    /// Append input to the VT log. Masking happens here, so the raw bytes
+    /// never reach `vt_lines` (and with it the DevTools view and exports).
+    fn push_input_log(&mut self, data: &[u8]) {
+        let masked = match self.input_log {
+            InputLog::Off => return,
+            InputLog::Masked => true,
+            InputLog::Raw => false,
+        };
         let mut log_str = String::new();
+        // Inside a CSI/SS3 key sequence (arrows, function keys), which stays
+        // readable when masked: 1 after `ESC`, 2 after `ESC [` / `ESC O`.
+        let mut in_key_sequence = 0u8;
         for &b in data {
-             match b {
+            let key_sequence = in_key_sequence;
+            in_key_sequence = match (key_sequence, b) {
+                (_, 0x1b) => 1,
+                (1, b'[' | b'O') => 2,
+                (2, 0x20..=0x3f) => 2,
+                _ => 0,
+            };
+            match b {
                 b'\n' => log_str.push_str("\\n"),
                 b'\r' => log_str.push_str("\\r"),
                 b'\t' => log_str.push_str("\\t"),
                 0x1b => log_str.push_str("\\x1b"),
+                b'[' | b'O' if key_sequence == 1 => log_str.push(b as char),
+                0x20..=0x7e if key_sequence == 2 => log_str.push(b as char),
+                // One dot per character: UTF-8 continuation bytes add none.
+                0x80..=0xbf if masked => {}
+                0x20..=0x7e | 0x80..=0xff if masked => log_str.push('•'),
                 0x20..=0x7e => log_str.push(b as char),
                 _ => log_str.push_str(&format!("\\x{:02X}", b)),
             }
@@ -213,41 +1033,515 @@ impl TerminalInstance {
         }
     }
 
-    /// Resize both the terminal grid and the underlying PTY.
+    /// Paste clipboard text: normalize line endings, then wrap the body in
+    /// bracketed-paste markers if `bracketed` allows it and the application
+    /// enabled that mode.
+    ///
+    /// With `run`, trailing newlines are dropped and a single Enter is sent after
+    /// the paste (outside the bracketed-paste markers) so the last line executes.
+    /// A paste at a password prompt is kept out of the log like typed input.
+    pub fn paste_text(
+        &mut self,
+        text: &str,
+        line_ending: PasteLineEnding,
+        strip_trailing_newline: bool,
+        bracketed: bool,
+        run: bool,
+    ) {
+        let body = normalize_paste(text, line_ending, strip_trailing_newline || run);
+        if body.is_empty() {
+            return;
+        }
+        let secret = self.input_is_secret();
+        if bracketed && self.is_bracketed_paste_enabled() {
+            let mut bytes = Vec::with_capacity(body.len() + 12);
+            bytes.extend_from_slice(b"\x1b[200~");
+            bytes.extend_from_slice(body.as_bytes());
+            bytes.extend_from_slice(b"\x1b[201~");
+            self.write_input(&bytes, secret);
+        } else {
+            self.write_input(body.as_bytes(), secret);
+        }
+        if run {
+            self.write_input(b"\r", secret);
+        }
+    }
+
+    /// Set the tab stop interval. Tab (HT) expansion happens in the emulator
+    /// grid, not the renderer, so this changes where both program output and
+    /// echoed tabs land. Console programs under ConPTY may have their tabs
+    /// expanded by conhost before the output reaches us.
+    pub fn set_tab_width(&mut self, width: usize) {
+        if width == 0 || width == self.tab_width {
+            return;
+        }
+        self.tab_width = width;
+        self.apply_tab_stops();
+    }
+
+    /// Cursor shape and blinking used while no program has set its own with
+    /// DECSCUSR. A program's choice wins until it sends `ESC[0 q`.
+    pub fn set_default_cursor_style(&mut self, shape: CursorShape, blinking: bool) {
+        let style = ansi::CursorStyle {
+            shape: match shape {
+                CursorShape::Block => ansi::CursorShape::Block,
+                CursorShape::Underline => ansi::CursorShape::Underline,
+                CursorShape::Bar => ansi::CursorShape::Beam,
+            },
+            blinking,
+        };
+        if style == self.default_cursor_style {
+            return;
+        }
+        self.default_cursor_style = style;
+        self.term.set_options(Config {
+            default_cursor_style: style,
+            ..Config::default()
+        });
+    }
+
+    /// Reprogram every tab stop as HTS would, without touching the cursor state
+    /// applications see. Needed after resize too: new columns get alacritty's
+    /// built-in 8-column stops. A full reset (RIS) also restores those.
+    fn apply_tab_stops(&mut self) {
+        let saved_column = self.term.grid().cursor.point.column;
+        self.term.clear_tabs(ansi::TabulationClearMode::All);
+        for col in (0..self.term.columns()).step_by(self.tab_width) {
+            self.term.grid_mut().cursor.point.column = Column(col);
+            self.term.set_horizontal_tabstop();
+        }
+        self.term.grid_mut().cursor.point.column = saved_column;
+    }
+
+    /// Resize for a layout change, at most once per `RESIZE_INTERVAL`. Returns
+    /// `None` once the size is applied, or how long to wait before asking
+    /// again with the latest size; the last size of a drag is always applied.
+    pub fn request_resize(&mut self, rows: u16, cols: u16) -> Option<Duration> {
+        let since = self.resized_at.map_or(RESIZE_INTERVAL, |at| at.elapsed());
+        if since < RESIZE_INTERVAL {
+            return Some(RESIZE_INTERVAL - since);
+        }
+        self.resize(rows, cols);
+        self.resized_at = Some(Instant::now());
+        None
+    }
+
+    /// Resize both the terminal grid and the underlying PTY. The size is
+    /// clamped with `clamp_grid_size`. alacritty rewraps soft-wrapped lines on
+    /// the primary screen, scrollback included, so narrowing and then widening
+    /// restores the original lines; the alternate screen is left to the app.
     pub fn resize(&mut self, rows: u16, cols: u16) {
+        let (rows, cols) = clamp_grid_size(rows, cols);
         let dims = TermDims {
             cols: cols as usize,
             rows: rows as usize,
         };
         self.term.resize(dims);
-        if let Ok(mut writer) = self.pty_writer.lock() {
-            let _ = writer.resize(PtySize { rows, cols });
+        self.local_echo.clear();
+        if self.tab_width != 8 {
+            self.apply_tab_stops();
         }
+        let _ = self.pty.resize(PtySize { rows, cols });
     }
 
     pub fn is_alive(&self) -> bool {
-        if let Ok(writer) = self.pty_writer.lock() {
-            writer.is_alive()
+        self.pty.is_alive()
+    }
+
+    /// Get a reference to the underlying Term for rendering.
+    pub fn term(&self) -> &Term<TermEventQueue> {
+        &self.term
+    }
+
+    /// Output rate over the last second, only while output is heavy enough to
+    /// make the screen jump (or frames were coalesced). `None` once it calms
+    /// down.
+    pub fn heavy_output_stats(&self) -> Option<OutputStats> {
+        let at = self.throughput.last_at?;
+        if at.elapsed() > THROUGHPUT_WINDOW * 2 {
+            return None;
+        }
+        let stats = self.throughput.last;
+        (stats.coalesced || stats.bytes_per_sec >= HEAVY_OUTPUT_BYTES_PER_SEC).then_some(stats)
+    }
+
+    /// Cursor color requested by the application via OSC 12, until OSC 112.
+    pub fn cursor_color(&self) -> Option<[u8; 3]> {
+        self.term.colors()[NamedColor::Cursor].map(|rgb| [rgb.r, rgb.g, rgb.b])
+    }
+
+    /// Default background, as changed by OSC 11.
+    pub fn default_background(&self) -> egui::Color32 {
+        self.default_colors().bg
+    }
+
+    fn default_colors(&self) -> DefaultColors<'_> {
+        // OSC 10/11/12 colors live in alacritty's color table until OSC 110-112.
+        let set = |named: NamedColor| {
+            self.term.colors()[named].map(|rgb| egui::Color32::from_rgb(rgb.r, rgb.g, rgb.b))
+        };
+        let theme =
+            |idx: usize| self.theme_colors[idx].map(|[r, g, b]| egui::Color32::from_rgb(r, g, b));
+        let fg = set(NamedColor::Foreground)
+            .or(theme(0))
+            .unwrap_or(TERM_FOREGROUND);
+        DefaultColors {
+            fg,
+            bg: set(NamedColor::Background)
+                .or(theme(1))
+                .unwrap_or(TERM_BACKGROUND),
+            cursor: set(NamedColor::Cursor).unwrap_or(fg),
+            colors: self.term.colors(),
+        }
+    }
+
+    /// Default foreground and background from the active profile; `None`
+    /// keeps the built-in color.
+    pub fn set_theme_colors(&mut self, fg: Option<[u8; 3]>, bg: Option<[u8; 3]>) {
+        self.theme_colors = [fg, bg];
+    }
+
+    /// Color reported back for an OSC color query (alacritty's color index).
+    fn reported_color(&self, index: usize) -> egui::Color32 {
+        let defaults = self.default_colors();
+        match index {
+            i if i == NamedColor::Foreground as usize => defaults.fg,
+            i if i == NamedColor::Background as usize => defaults.bg,
+            i if i == NamedColor::Cursor as usize => defaults.cursor,
+            i if i < 256 => defaults.indexed(i as u8),
+            _ => defaults.fg,
+        }
+    }
+
+    /// Short names of the modes that change how keys, mouse and paste reach
+    /// the application, in a fixed order; empty when none is set.
+    pub fn active_mode_labels(&self) -> Vec<&'static str> {
+        let mode = *self.term.mode();
+        [
+            (TermMode::BRACKETED_PASTE, "paste"),
+            (TermMode::ALT_SCREEN, "alt"),
+            (TermMode::MOUSE_MODE, "mouse"),
+            (TermMode::APP_CURSOR, "appcur"),
+            (TermMode::APP_KEYPAD, "appkp"),
+            (TermMode::INSERT, "ins"),
+        ]
+        .into_iter()
+        .filter(|(flag, _)| mode.intersects(*flag))
+        .map(|(_, label)| label)
+        .collect()
+    }
+
+    pub fn is_alt_screen(&self) -> bool {
+        self.term.mode().contains(TermMode::ALT_SCREEN)
+    }
+
+    pub fn rows(&self) -> usize {
+        self.term.screen_lines()
+    }
+
+    pub fn cols(&self) -> usize {
+        self.term.columns()
+    }
+
+    /// Scrollback and screen re-encoded as text with SGR attributes, for
+    /// `seed_history`. Soft-wrapped rows are joined so the receiving grid
+    /// rewraps them at its own width; blank rows at the end are dropped.
+    pub fn dump_scrollback(&self) -> Vec<u8> {
+        let grid = self.term.grid();
+        let num_cols = self.term.columns();
+        let top_line = -(grid.history_size() as i32);
+        let mut out = Vec::new();
+        let mut sgr = String::new();
+        let mut last_sgr = String::new();
+        let mut content_end = 0;
+        for row_idx in 0..grid.total_lines() {
+            let row = &grid[Line(top_line + row_idx as i32)];
+            let wrapped = num_cols > 0
+                && row[Column(num_cols - 1)]
+                    .flags
+                    .contains(CellFlags::WRAPLINE);
+            // Trailing blanks in the default background aren't content.
+            let mut end = num_cols;
+            while !wrapped && end > 0 {
+                let cell = &row[Column(end - 1)];
+                let blank = matches!(cell.c, ' ' | '\0' | '\t')
+                    && cell.bg == TermColor::Named(NamedColor::Background)
+                    && !cell.flags.contains(CellFlags::INVERSE);
+                if !blank {
+                    break;
+                }
+                end -= 1;
+            }
+            last_sgr.clear();
+            last_sgr.push('0');
+            for col_idx in 0..end {
+                let cell = &row[Column(col_idx)];
+                if cell
+                    .flags
+                    .intersects(CellFlags::WIDE_CHAR_SPACER | CellFlags::LEADING_WIDE_CHAR_SPACER)
+                {
+                    continue;
+                }
+                cell_sgr(cell, &mut sgr);
+                if sgr != last_sgr {
+                    out.extend_from_slice(b"\x1b[");
+                    out.extend_from_slice(sgr.as_bytes());
+                    out.push(b'm');
+                    std::mem::swap(&mut sgr, &mut last_sgr);
+                }
+                let ch = if cell.c == '\0' || cell.c == '\t' {
+                    ' '
+                } else {
+                    cell.c
+                };
+                let mut buf = [0u8; 4];
+                out.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+                for mark in cell.zerowidth().unwrap_or(&[]) {
+                    out.extend_from_slice(mark.encode_utf8(&mut buf).as_bytes());
+                }
+            }
+            if last_sgr != "0" {
+                out.extend_from_slice(b"\x1b[0m");
+            }
+            if end > 0 {
+                content_end = out.len();
+            }
+            if !wrapped {
+                out.extend_from_slice(b"\r\n");
+            }
+        }
+        out.truncate(content_end);
+        out
+    }
+
+    /// Put a previous session's `dump_scrollback` above this one's output,
+    /// followed by a marker line. Called before any PTY output is processed;
+    /// everything is then scrolled off the screen so the shell, which draws
+    /// at absolute positions, starts on a blank screen as usual.
+    pub fn seed_history(&mut self, dump: &[u8]) {
+        if dump.is_empty() {
+            return;
+        }
+        self.processor.advance(&mut self.term, dump);
+        let marker = "\x1b[0m\r\n\x1b[90m\u{2014} session restarted \u{2014}\x1b[0m";
+        self.processor.advance(&mut self.term, marker.as_bytes());
+        let scroll_out = "\n".repeat(self.term.screen_lines());
+        self.processor
+            .advance(&mut self.term, scroll_out.as_bytes());
+        self.processor.advance(&mut self.term, b"\r\x1b[H");
+        // Titles or color queries in the dump belong to the old session.
+        if let Ok(mut events) = self.term_events.0.lock() {
+            events.clear();
+        }
+    }
+
+    /// Plain text of the visible screen (no scrollback), one line per row with
+    /// trailing blanks trimmed.
+    pub fn screen_text(&self) -> String {
+        let mut out = String::new();
+        for row_idx in 0..self.term.screen_lines() {
+            self.push_row_text(Line(row_idx as i32), &mut out);
+            out.truncate(out.trim_end_matches(' ').len());
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Plain text of one grid row, blanks included: a prompt line's trailing
+    /// space is part of what the prompt pattern matches.
+    fn row_text(&self, line: Line) -> String {
+        let mut out = String::new();
+        self.push_row_text(line, &mut out);
+        out
+    }
+
+    /// `row_text` appended to `out`, for loops over many rows that reuse one
+    /// buffer.
+    fn push_row_text(&self, line: Line, out: &mut String) {
+        let row = &self.term.grid()[line];
+        for col_idx in 0..self.term.columns() {
+            let cell = &row[Column(col_idx)];
+            if cell
+                .flags
+                .intersects(CellFlags::WIDE_CHAR_SPACER | CellFlags::LEADING_WIDE_CHAR_SPACER)
+            {
+                continue;
+            }
+            out.push(if cell.c == '\0' || cell.c == '\t' {
+                ' '
+            } else {
+                cell.c
+            });
+            if let Some(zerowidth) = cell.zerowidth() {
+                out.extend(zerowidth);
+            }
+        }
+    }
+
+    /// Apply the `prompt_regex` setting; empty turns the fallback off.
+    pub fn set_prompt_regex(&mut self, pattern: &str) {
+        if pattern == self.prompt_pattern {
+            return;
+        }
+        self.prompt_pattern = pattern.to_string();
+        self.prompt_regex = if pattern.is_empty() {
+            None
         } else {
-            false
+            match Regex::new(pattern) {
+                Ok(re) => Some(re),
+                Err(e) => {
+                    eprintln!("Ignoring prompt pattern: {}", e);
+                    None
+                }
+            }
+        };
+    }
+
+    /// The prompt pattern, while no OSC 633 marks have been seen: once the shell
+    /// reports its directory, its own command marks are trusted instead.
+    fn prompt_fallback(&self) -> Option<&Regex> {
+        self.prompt_regex
+            .as_ref()
+            .filter(|_| !self.current_dir_reported)
+    }
+
+    /// Where the prompt ends on `line`, if the pattern matches at its start. A
+    /// row continuing a soft-wrapped line is never a prompt. `text` is scratch
+    /// space; after a match it holds the row's text.
+    fn prompt_at(&self, re: &Regex, line: Line, text: &mut String) -> Option<usize> {
+        let grid = self.term.grid();
+        let last_col = Column(self.term.columns().checked_sub(1)?);
+        if line > Line(-(grid.history_size() as i32))
+            && grid[Line(line.0 - 1)][last_col]
+                .flags
+                .contains(CellFlags::WRAPLINE)
+        {
+            return None;
+        }
+        text.clear();
+        self.push_row_text(line, text);
+        re.find(text).filter(|m| m.start() == 0).map(|m| m.end())
+    }
+
+    /// Rows (counted from the top of scrollback, as in `ScrollRequest::Row`)
+    /// that start with a match of the prompt pattern. Empty without one.
+    pub fn prompt_rows(&self) -> Vec<usize> {
+        let Some(re) = self.prompt_regex.as_ref() else {
+            return Vec::new();
+        };
+        let history = self.term.grid().history_size();
+        let mut text = String::new();
+        (0..self.term.grid().total_lines())
+            .filter(|&row| {
+                self.prompt_at(re, Line(row as i32 - history as i32), &mut text)
+                    .is_some()
+            })
+            .collect()
+    }
+
+    /// Output of the last finished command: the rows between the two most
+    /// recent prompts, after the first prompt's (possibly wrapped) command line.
+    pub fn last_command_output(&self) -> Option<String> {
+        let prompts = self.prompt_rows();
+        let [.., start, end] = prompts[..] else {
+            return None;
+        };
+        let grid = self.term.grid();
+        let history = grid.history_size() as i32;
+        let last_col = Column(self.term.columns().checked_sub(1)?);
+        let wrapped = |row: usize| {
+            grid[Line(row as i32 - history)][last_col]
+                .flags
+                .contains(CellFlags::WRAPLINE)
+        };
+        let mut row = start;
+        while row < end && wrapped(row) {
+            row += 1;
         }
+        let mut out = String::new();
+        for row in row + 1..end {
+            let start = out.len();
+            self.push_row_text(Line(row as i32 - history), &mut out);
+            if !wrapped(row) {
+                out.truncate(start + out[start..].trim_end_matches(' ').len());
+                out.push('\n');
+            }
+        }
+        Some(out.trim_end_matches('\n').to_string())
+    }
+
+    /// Whether command starts and ends are known, from OSC 633 marks or the
+    /// prompt pattern.
+    pub fn tracks_commands(&self) -> bool {
+        self.current_dir_reported || self.prompt_regex.is_some()
+    }
+
+    pub fn current_dir(&self) -> &str {
+        &self.current_dir
+    }
+
+    /// Whether `current_dir` comes from the shell rather than the startup directory.
+    pub fn current_dir_reported(&self) -> bool {
+        self.current_dir_reported
+    }
+
+    /// How long the session has been up; stops counting once the PTY closes.
+    pub fn session_elapsed(&self) -> Duration {
+        self.exited_at
+            .unwrap_or_else(Instant::now)
+            .saturating_duration_since(self.started_at)
     }
 
-    /// Get a reference to the underlying Term for rendering.
-    pub fn term(&self) -> &Term<VoidListener> {
-        &self.term
+    /// Start of the command the shell is running, if shell integration
+    /// reported one (`633;C`), or Enter was pressed on a line matching the
+    /// prompt pattern, and no prompt has followed yet.
+    pub fn running_command_since(&self) -> Option<Instant> {
+        self.command_started_at
     }
 
-    pub fn rows(&self) -> usize {
-        self.term.screen_lines()
+    /// Commands the shell reported as executed since the last call, each with
+    /// the directory it ran in.
+    pub fn take_executed_commands(&mut self) -> Vec<(String, String)> {
+        std::mem::take(&mut self.executed_commands)
     }
 
-    pub fn cols(&self) -> usize {
-        self.term.columns()
+    /// Report the next command the shell runs under `label` once it finishes
+    /// (see `take_finished_commands`). Call right before sending it; replaces
+    /// any earlier watch. Needs OSC 633 marks, so returns `false` and watches
+    /// nothing until the shell has reported its directory.
+    pub fn watch_next_command(&mut self, label: &str) -> bool {
+        if !self.current_dir_reported {
+            return false;
+        }
+        // Without start marks, a prompt is the only boundary we will see, so
+        // the command counts as started once it is sent.
+        let started_at = (!self.command_start_marks).then(Instant::now);
+        self.watched_command = Some(WatchedCommand {
+            label: label.to_string(),
+            started_at,
+        });
+        true
     }
 
-    pub fn current_dir(&self) -> &str {
-        &self.current_dir
+    /// Watched commands that finished since the last call.
+    pub fn take_finished_commands(&mut self) -> Vec<FinishedCommand> {
+        std::mem::take(&mut self.finished_commands)
+    }
+
+    /// A completion mark (`633;D`, or a prompt for shells that skip it) ends
+    /// the watched command once its start was seen.
+    fn finish_watched_command(&mut self, exit_code: Option<i32>) {
+        match self.watched_command.take() {
+            Some(WatchedCommand {
+                label,
+                started_at: Some(started_at),
+            }) => self.finished_commands.push(FinishedCommand {
+                label,
+                exit_code,
+                elapsed: started_at.elapsed(),
+            }),
+            pending => self.watched_command = pending,
+        }
     }
 
     pub fn is_bracketed_paste_enabled(&self) -> bool {
@@ -258,6 +1552,16 @@ impl TerminalInstance {
         self.term.mode().contains(TermMode::FOCUS_IN_OUT)
     }
 
+    /// Whether the application turned on xterm's modifyOtherKeys (level 1 or 2).
+    pub fn is_modify_other_keys_enabled(&self) -> bool {
+        self.modify_other_keys > 0
+    }
+
+    /// Current terminal modes (DECCKM, DECKPAM, ...) used for key encoding.
+    pub fn mode(&self) -> TermMode {
+        *self.term.mode()
+    }
+
     pub fn vt_log_lines_len(&self) -> usize {
         self.vt_lines.len() + if self.vt_pending.is_empty() { 0 } else { 1 }
     }
@@ -295,7 +1599,7 @@ impl TerminalInstance {
             '\u{1b}' => self.vt_pending.push_str("\\x1b"),
             c if c.is_control() => {
                 let code = c as u32;
-                self.vt_pending.push_str(&format!("\\u{{{:04X}}}", code));
+                let _ = write!(self.vt_pending, "\\u{{{:04X}}}", code);
             }
             _ => self.vt_pending.push(ch),
         }
@@ -311,7 +1615,9 @@ impl TerminalInstance {
             b'\t' => self.vt_pending.push_str("\\t"),
             0x1b => self.vt_pending.push_str("\\x1b"),
             0x20..=0x7e => self.vt_pending.push(byte as char),
-            _ => self.vt_pending.push_str(&format!("\\x{:02X}", byte)),
+            _ => {
+                let _ = write!(self.vt_pending, "\\x{:02X}", byte);
+            }
         }
     }
 
@@ -323,22 +1629,22 @@ impl TerminalInstance {
         }
     }
 
-    fn update_current_dir_from_osc(&mut self, data: &[u8]) {
+    fn update_shell_integration_from_osc(&mut self, data: &[u8]) {
         self.osc_tracking_buffer.extend_from_slice(data);
         let mut cursor = 0usize;
 
         loop {
             let slice = &self.osc_tracking_buffer[cursor..];
-            let Some(rel_start) = find_subslice(slice, CWD_OSC_PREFIX) else {
+            let Some(rel_start) = find_subslice(slice, SHELL_INTEGRATION_OSC_PREFIX) else {
                 let remaining = &self.osc_tracking_buffer[cursor..];
-                let keep = trailing_partial_marker_len(remaining, CWD_OSC_PREFIX);
+                let keep = trailing_partial_marker_len(remaining, SHELL_INTEGRATION_OSC_PREFIX);
                 self.osc_tracking_buffer =
                     remaining[remaining.len().saturating_sub(keep)..].to_vec();
                 return;
             };
 
             let start_idx = cursor + rel_start;
-            let content_start = start_idx + CWD_OSC_PREFIX.len();
+            let content_start = start_idx + SHELL_INTEGRATION_OSC_PREFIX.len();
             let after_start = &self.osc_tracking_buffer[content_start..];
 
             let (end_idx, terminator_len) =
@@ -351,14 +1657,239 @@ impl TerminalInstance {
                     return;
                 };
 
-            let cwd_bytes = &self.osc_tracking_buffer[content_start..end_idx];
-            if !cwd_bytes.is_empty() {
-                self.current_dir = String::from_utf8_lossy(cwd_bytes).to_string();
+            let payload = &self.osc_tracking_buffer[content_start..end_idx];
+            if payload == b"C" {
+                self.command_started_at = Some(Instant::now());
+                self.command_start_marks = true;
+                if let Some(watch) = self.watched_command.as_mut() {
+                    watch.started_at.get_or_insert_with(Instant::now);
+                }
+            } else if payload == b"D" || payload.starts_with(b"D;") {
+                self.command_started_at = None;
+                let exit_code = payload
+                    .strip_prefix(b"D;")
+                    .and_then(|code| std::str::from_utf8(code).ok())
+                    .and_then(|code| code.trim().parse().ok());
+                self.finish_watched_command(exit_code);
+            } else if payload == b"A" {
+                self.command_started_at = None;
+                self.finish_watched_command(None);
+            } else if let Some(cwd_bytes) = payload.strip_prefix(b"CWD=") {
+                // Sent by every prompt, so it also ends the running command.
+                self.command_started_at = None;
+                if !cwd_bytes.is_empty() {
+                    let dir = String::from_utf8_lossy(cwd_bytes).to_string();
+                    let changed = !self.current_dir_reported || dir != self.current_dir;
+                    self.current_dir = dir;
+                    self.current_dir_reported = true;
+                    if changed {
+                        if let Some(on_cwd_change) = self.on_cwd_change.as_mut() {
+                            on_cwd_change(&self.current_dir);
+                        }
+                    }
+                }
+                self.finish_watched_command(None);
+            } else if let Some(command_bytes) = payload.strip_prefix(b"E;") {
+                // The mark arrives before the prompt's CWD update, so `current_dir`
+                // is still the directory the command ran in.
+                let command = unescape_command_mark(command_bytes);
+                if self.current_dir_reported && !command.trim().is_empty() {
+                    self.executed_commands
+                        .push((self.current_dir.clone(), command.trim().to_string()));
+                }
             }
 
             cursor = end_idx + terminator_len;
         }
     }
+
+    fn update_modify_other_keys(&mut self, data: &[u8]) {
+        let joined;
+        let mut rest = if self.modify_other_keys_carry.is_empty() {
+            data
+        } else {
+            let carry = std::mem::take(&mut self.modify_other_keys_carry);
+            joined = [carry.as_slice(), data].concat();
+            joined.as_slice()
+        };
+        while let Some(idx) = find_subslice(rest, MODIFY_OTHER_KEYS_PREFIX) {
+            let params = &rest[idx + MODIFY_OTHER_KEYS_PREFIX.len()..];
+            let Some(len) = params.iter().position(|b| !matches!(b, b'0'..=b'9' | b';')) else {
+                // The sequence continues in the next chunk.
+                self.modify_other_keys_carry = rest[idx..].to_vec();
+                return;
+            };
+            if params[len] == b'm' {
+                match &params[..len] {
+                    b"" => self.modify_other_keys = 0,
+                    [b';', level @ ..] if !level.contains(&b';') => {
+                        self.modify_other_keys = std::str::from_utf8(level)
+                            .ok()
+                            .and_then(|l| l.parse().ok())
+                            .unwrap_or(0);
+                    }
+                    // Another resource (`>41`) or extra parameters.
+                    _ => {}
+                }
+            }
+            rest = &params[len..];
+        }
+        let keep = trailing_partial_marker_len(rest, MODIFY_OTHER_KEYS_PREFIX);
+        self.modify_other_keys_carry = rest[rest.len() - keep..].to_vec();
+    }
+
+    /// Record XTWINOPS resize requests (`CSI 8 ; rows ; cols t`), resolving a
+    /// 0 or missing dimension to the current one.
+    fn update_resize_request(&mut self, data: &[u8]) {
+        let joined;
+        let mut rest = if self.resize_request_carry.is_empty() {
+            data
+        } else {
+            let carry = std::mem::take(&mut self.resize_request_carry);
+            joined = [carry.as_slice(), data].concat();
+            joined.as_slice()
+        };
+        while let Some(idx) = find_subslice(rest, RESIZE_REQUEST_PREFIX) {
+            let params = &rest[idx + RESIZE_REQUEST_PREFIX.len()..];
+            let Some(len) = params.iter().position(|b| !matches!(b, b'0'..=b'9' | b';')) else {
+                // The sequence continues in the next chunk, unless it is already
+                // too long to be a size.
+                if params.len() <= RESIZE_REQUEST_MAX_PARAMS {
+                    self.resize_request_carry = rest[idx..].to_vec();
+                }
+                return;
+            };
+            if params[len] == b't' {
+                let mut dims = std::str::from_utf8(&params[..len]).unwrap_or("").split(';');
+                let mut dim =
+                    |current: usize| match dims.next().unwrap_or("").parse::<u16>().unwrap_or(0) {
+                        0 => current.min(u16::MAX as usize) as u16,
+                        n => n,
+                    };
+                let rows = dim(self.rows());
+                let cols = dim(self.cols());
+                if dims.next().is_none() {
+                    self.resize_request = Some((rows, cols));
+                }
+            }
+            rest = &params[len..];
+        }
+        let keep = trailing_partial_marker_len(rest, RESIZE_REQUEST_PREFIX);
+        self.resize_request_carry = rest[rest.len() - keep..].to_vec();
+    }
+
+    /// Whether `data` (after any carried partial sequence) contains
+    /// `ERASE_SCREEN` and `ERASE_SCROLLBACK` for the main screen. `alt_screen`
+    /// is the screen `data` starts on; a DECSET / DECRST listing
+    /// `ALT_SCREEN_MODE` switches it along the way.
+    fn scan_erase_display(&mut self, data: &[u8], mut alt_screen: bool) -> (bool, bool) {
+        let joined;
+        let data = if self.erase_display_carry.is_empty() {
+            data
+        } else {
+            let carry = std::mem::take(&mut self.erase_display_carry);
+            joined = [carry.as_slice(), data].concat();
+            joined.as_slice()
+        };
+        let keep = [ERASE_SCREEN, ERASE_SCROLLBACK]
+            .iter()
+            .map(|marker| trailing_partial_marker_len(data, marker))
+            .chain([trailing_partial_private_mode_len(data)])
+            .max()
+            .unwrap_or(0);
+        self.erase_display_carry = data[data.len() - keep..].to_vec();
+        let (mut screen, mut scrollback) = (false, false);
+        for (i, _) in data.iter().enumerate().filter(|(_, &b)| b == 0x1b) {
+            let rest = &data[i..];
+            if let Some(on) = alt_screen_switch(rest) {
+                alt_screen = on;
+            } else if !alt_screen {
+                screen |= rest.starts_with(ERASE_SCREEN);
+                scrollback |= rest.starts_with(ERASE_SCROLLBACK);
+            }
+        }
+        (screen, scrollback)
+    }
+}
+
+/// Decode a `633;E` command line: `\\` is a backslash and `\xNN` a raw byte.
+/// Decoding stops at the first unescaped `;` (an optional nonce follows).
+fn unescape_command_mark(bytes: &[u8]) -> String {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b';' => break,
+            b'\\' if bytes.get(i + 1) == Some(&b'\\') => {
+                out.push(b'\\');
+                i += 2;
+            }
+            b'\\' if bytes.get(i + 1) == Some(&b'x') => {
+                let hex = bytes
+                    .get(i + 2..i + 4)
+                    .and_then(|h| std::str::from_utf8(h).ok())
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 4;
+                    }
+                    None => {
+                        out.push(b'\\');
+                        i += 1;
+                    }
+                }
+            }
+            byte => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn normalize_paste(
+    text: &str,
+    line_ending: PasteLineEnding,
+    strip_trailing_newline: bool,
+) -> String {
+    let mut out = match line_ending {
+        PasteLineEnding::Keep => text.to_string(),
+        PasteLineEnding::Cr => text.replace("\r\n", "\r").replace('\n', "\r"),
+        PasteLineEnding::Lf => text.replace("\r\n", "\n").replace('\r', "\n"),
+    };
+    if strip_trailing_newline {
+        let trimmed = out.trim_end_matches(['\r', '\n']).len();
+        out.truncate(trimmed);
+    }
+    out
+}
+
+/// Split `data` after every OSC query (a `?` right before BEL or ST).
+fn split_after_osc_queries(data: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut rest = data;
+    std::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest
+            .iter()
+            .enumerate()
+            .filter(|(_, &b)| b == b'?')
+            .find_map(|(i, _)| {
+                let after = &rest[i + 1..];
+                if after.first() == Some(&OSC_BEL) {
+                    Some(i + 2)
+                } else {
+                    after.starts_with(OSC_ST).then_some(i + 1 + OSC_ST.len())
+                }
+            })
+            .unwrap_or(rest.len());
+        let (part, tail) = rest.split_at(end);
+        rest = tail;
+        Some(part)
+    })
 }
 
 fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
@@ -370,6 +1901,47 @@ fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
         .position(|window| window == needle)
 }
 
+/// What the DECSET / DECRST at the start of `seq` does to the alternate
+/// screen: `Some(true)` enters it, `Some(false)` leaves it.
+fn alt_screen_switch(seq: &[u8]) -> Option<bool> {
+    let params = seq.strip_prefix(PRIVATE_MODE_PREFIX)?;
+    let end = params
+        .iter()
+        .position(|&b| !(b.is_ascii_digit() || b == b';'))?;
+    let on = match params[end] {
+        b'h' => true,
+        b'l' => false,
+        _ => return None,
+    };
+    params[..end]
+        .split(|&b| b == b';')
+        .any(|param| {
+            std::str::from_utf8(param)
+                .ok()
+                .and_then(|param| param.parse::<u16>().ok())
+                == Some(ALT_SCREEN_MODE)
+        })
+        .then_some(on)
+}
+
+/// Length of a DECSET / DECRST cut off at the end of `data`, parameters
+/// included.
+fn trailing_partial_private_mode_len(data: &[u8]) -> usize {
+    let Some(start) = data.iter().rposition(|&b| b == 0x1b) else {
+        return 0;
+    };
+    let tail = &data[start..];
+    let partial = match tail.strip_prefix(PRIVATE_MODE_PREFIX) {
+        Some(params) => params.iter().all(|&b| b.is_ascii_digit() || b == b';'),
+        None => PRIVATE_MODE_PREFIX.starts_with(tail),
+    };
+    if partial && tail.len() <= PRIVATE_MODE_MAX_LEN {
+        tail.len()
+    } else {
+        0
+    }
+}
+
 fn trailing_partial_marker_len(data: &[u8], marker: &[u8]) -> usize {
     if data.is_empty() || marker.is_empty() {
         return 0;
@@ -387,15 +1959,43 @@ fn trailing_partial_marker_len(data: &[u8], marker: &[u8]) -> usize {
 // Terminal rendering (egui)
 // ---------------------------------------------------------------------------
 
-fn term_color_to_egui(color: &TermColor, is_fg: bool) -> egui::Color32 {
+/// Default foreground, background and cursor color of a terminal (OSC 10/11/12,
+/// else the profile's or built in), and alacritty's color table for palette
+/// entries changed with OSC 4.
+#[derive(Clone, Copy)]
+struct DefaultColors<'a> {
+    fg: egui::Color32,
+    bg: egui::Color32,
+    /// The default foreground unless OSC 12 set one.
+    cursor: egui::Color32,
+    colors: &'a Colors,
+}
+
+impl DefaultColors<'_> {
+    /// Palette entry `idx`, as set by OSC 4 or built in.
+    fn indexed(&self, idx: u8) -> egui::Color32 {
+        match self.colors[idx as usize] {
+            Some(rgb) => egui::Color32::from_rgb(rgb.r, rgb.g, rgb.b),
+            None => indexed_color_to_egui(idx, true),
+        }
+    }
+}
+
+fn term_color_to_egui(color: &TermColor, is_fg: bool, defaults: DefaultColors) -> egui::Color32 {
     match color {
-        TermColor::Named(named) => named_color_to_egui(named, is_fg),
+        TermColor::Named(named) => named_color_to_egui(named, is_fg, defaults),
         TermColor::Spec(rgb) => egui::Color32::from_rgb(rgb.r, rgb.g, rgb.b),
-        TermColor::Indexed(idx) => indexed_color_to_egui(*idx, is_fg),
+        TermColor::Indexed(idx) => defaults.indexed(*idx),
     }
 }
 
-fn named_color_to_egui(named: &NamedColor, is_fg: bool) -> egui::Color32 {
+fn named_color_to_egui(named: &NamedColor, is_fg: bool, defaults: DefaultColors) -> egui::Color32 {
+    // The 16 ANSI colors are palette entries 0-15, which OSC 4 may have changed.
+    if (*named as usize) < 16 {
+        if let Some(rgb) = defaults.colors[*named as usize] {
+            return egui::Color32::from_rgb(rgb.r, rgb.g, rgb.b);
+        }
+    }
     match named {
         NamedColor::Black => egui::Color32::from_rgb(0, 0, 0),
         NamedColor::Red => egui::Color32::from_rgb(204, 0, 0),
@@ -413,14 +2013,12 @@ fn named_color_to_egui(named: &NamedColor, is_fg: bool) -> egui::Color32 {
         NamedColor::BrightMagenta => egui::Color32::from_rgb(173, 127, 168),
         NamedColor::BrightCyan => egui::Color32::from_rgb(52, 226, 226),
         NamedColor::BrightWhite => egui::Color32::from_rgb(238, 238, 236),
-        NamedColor::Foreground | NamedColor::BrightForeground => {
-            egui::Color32::from_rgb(204, 204, 204)
-        }
-        NamedColor::Background => egui::Color32::from_rgb(18, 18, 18),
-        NamedColor::Cursor => egui::Color32::from_rgb(204, 204, 204),
+        NamedColor::Foreground | NamedColor::BrightForeground => defaults.fg,
+        NamedColor::Background => defaults.bg,
+        NamedColor::Cursor => defaults.cursor,
         _ => {
             if is_fg {
-                egui::Color32::from_rgb(204, 204, 204)
+                defaults.fg
             } else {
                 egui::Color32::TRANSPARENT
             }
@@ -428,6 +2026,15 @@ fn named_color_to_egui(named: &NamedColor, is_fg: bool) -> egui::Color32 {
     }
 }
 
+/// Background for a cell: transparent when the program left it at the default,
+/// so an explicit black (SGR 40 / 48;5;0) stays distinguishable from "unset".
+fn cell_background_to_egui(color: &TermColor, defaults: DefaultColors) -> egui::Color32 {
+    match color {
+        TermColor::Named(NamedColor::Background) => egui::Color32::TRANSPARENT,
+        _ => term_color_to_egui(color, false, defaults),
+    }
+}
+
 fn indexed_color_to_egui(idx: u8, _is_fg: bool) -> egui::Color32 {
     // Standard 16 colors
     static ANSI_COLORS: [[u8; 3]; 16] = [
@@ -473,6 +2080,37 @@ fn align_to_pixels(value: f32, pixels_per_point: f32) -> f32 {
     (value * pixels_per_point).round() / pixels_per_point
 }
 
+/// Where grid rows are painted. The first drawn row starts at a pixel-aligned
+/// origin, while the content origin itself is fractional at most scroll offsets
+/// (wheel deltas, animated scrolls); pointer mapping and the cursor rect must
+/// measure from the same origin, or they land a row off from what is drawn.
+#[derive(Clone, Copy)]
+struct RowLayout {
+    top: f32,
+    first_row: usize,
+    pitch: f32,
+}
+
+impl RowLayout {
+    fn new(content_top: f32, first_row: usize, pitch: f32, pixels_per_point: f32) -> Self {
+        Self {
+            top: align_to_pixels(content_top + first_row as f32 * pitch, pixels_per_point),
+            first_row,
+            pitch,
+        }
+    }
+
+    /// Top edge of `row`.
+    fn row_y(&self, row: usize) -> f32 {
+        self.top + (row as f32 - self.first_row as f32) * self.pitch
+    }
+
+    /// Row under the vertical position `y`.
+    fn row_at(&self, y: f32) -> usize {
+        (((y - self.top) / self.pitch).floor() + self.first_row as f32).max(0.0) as usize
+    }
+}
+
 fn align_to_pixels_ceil(value: f32, pixels_per_point: f32) -> f32 {
     if pixels_per_point <= 0.0 {
         return value;
@@ -494,6 +2132,50 @@ pub(crate) fn aligned_glyph_width(ui: &egui::Ui, font_id: &egui::FontId, ch: cha
     align_to_pixels(raw, ui.ctx().pixels_per_point())
 }
 
+/// Selection and cursor colors from the appearance settings. Text drawn on
+/// them is black or white, whichever contrasts with the chosen color.
+#[derive(Clone, Copy, Debug)]
+pub struct HighlightColors {
+    selection: egui::Color32,
+    selection_fg: egui::Color32,
+    cursor: egui::Color32,
+    cursor_fg: egui::Color32,
+}
+
+impl HighlightColors {
+    pub fn new(selection: [u8; 3], cursor: [u8; 3]) -> Self {
+        let selection = egui::Color32::from_rgb(selection[0], selection[1], selection[2]);
+        let cursor = egui::Color32::from_rgb(cursor[0], cursor[1], cursor[2]);
+        Self {
+            selection,
+            selection_fg: contrasting_fg(selection),
+            cursor,
+            cursor_fg: contrasting_fg(cursor),
+        }
+    }
+}
+
+/// Dark text on light backgrounds and vice versa (perceived brightness).
+pub fn contrasting_fg(bg: egui::Color32) -> egui::Color32 {
+    let brightness = 0.299 * bg.r() as f32 + 0.587 * bg.g() as f32 + 0.114 * bg.b() as f32;
+    if brightness > 128.0 {
+        egui::Color32::from_rgb(18, 18, 18)
+    } else {
+        egui::Color32::from_rgb(235, 235, 235)
+    }
+}
+
+/// Clamp a grid size to `MIN_GRID_ROWS`×`MIN_GRID_COLS` and to what ConPTY's
+/// `i16` coordinates can hold.
+pub fn clamp_grid_size(rows: u16, cols: u16) -> (u16, u16) {
+    let max = i16::MAX as u16;
+    (
+        rows.clamp(MIN_GRID_ROWS, max),
+        cols.clamp(MIN_GRID_COLS, max),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
 pub fn render_terminal(
     ui: &mut egui::Ui,
     terminal: Option<&TerminalInstance>,
@@ -501,6 +2183,14 @@ pub fn render_terminal(
     input_blocked: bool,
     scroll_request: Option<ScrollRequest>,
     scroll_id: u64,
+    ime_preedit: Option<&ImePreedit>,
+    render_cache: &mut TerminalRenderCache,
+    search: &mut SearchState,
+    highlight: HighlightColors,
+    animate_scroll: bool,
+    jump_request: &mut Option<ScrollRequest>,
+    cursor_locator: f32,
+    steady_cursor: bool,
 ) -> Option<egui::Rect> {
     let terminal = match terminal {
         Some(t) => t,
@@ -515,6 +2205,7 @@ pub fn render_terminal(
     };
 
     let term = terminal.term();
+    let defaults = terminal.default_colors();
     let grid = term.grid();
     let content = term.renderable_content();
     let cursor = content.cursor;
@@ -540,31 +2231,62 @@ pub fn render_terminal(
     } else {
         cursor.point.column.0.min(num_cols.saturating_sub(1))
     };
+    // After a character lands in the last column the cursor stays on it with
+    // a wrap pending (DECAWM): the next character starts the next line. Draw
+    // it as a half cell past the margin instead of over that character.
+    let pending_wrap = grid.cursor.input_needs_wrap && num_cols > 0;
     let selection_range = selection_state.normalized();
+    let copy_cursor = selection_state.copy_cursor;
     let mut ime_cursor_rect = None;
 
-    // Cursor blink: 500ms on / 500ms off
-    let cursor_visible = {
-        let ms = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_millis();
-        cursor.shape != ansi::CursorShape::Hidden && (ms / 500) % 2 == 0
-    };
+    // alacritty reports a DECTCEM-hidden cursor (`ESC[?25l`) as `Hidden`.
+    let cursor_hidden = cursor.shape == ansi::CursorShape::Hidden;
+    // Only a block recolors its cell; the other shapes are painted over the row.
+    let block_cursor = cursor.shape == ansi::CursorShape::Block;
+
+    // Cursor blink: 500ms on / 500ms off, unless the settings or the program
+    // (DECSCUSR, `ESC[?12l`) asked for a steady cursor, or motion is reduced.
+    let blinking = term.cursor_style().blinking && !steady_cursor;
+    let cursor_visible = !cursor_hidden
+        && (!blinking || {
+            let ms = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis();
+            // Repaint at the next blink phase change.
+            ui.ctx()
+                .request_repaint_after(std::time::Duration::from_millis((500 - ms % 500) as u64));
+            (ms / 500).is_multiple_of(2)
+        });
+
+    // A grid wider than the view (a size pinned over the control protocol, or a
+    // window narrower than MIN_GRID_COLS) scrolls sideways instead of silently
+    // clipping the right-hand columns.
+    let grid_width = char_width * num_cols as f32;
+    let wide = grid_width > ui.available_width() + 0.5;
+
+    style_terminal_scrollbar(ui, defaults.fg, render_cache);
 
     // Use scroll_id in the ScrollArea ID so Ctrl+L resets the scroll state
-    let mut scroll = egui::ScrollArea::vertical()
+    let mut scroll = egui::ScrollArea::new([wide, true])
         .id_source(("terminal_scroll", scroll_id))
         .auto_shrink([false, false])
-        .animated(true);
+        .animated(animate_scroll);
+
+    // The alternate screen has no scrollback; keep the view pinned to it.
+    if terminal.is_alt_screen() {
+        scroll = scroll
+            .enable_scrolling(false)
+            .scroll_bar_visibility(egui::scroll_area::ScrollBarVisibility::AlwaysHidden);
+    }
 
     if let Some(req) = scroll_request {
         let offset = match req {
             // Show the terminal "screen" (last `screen_lines` rows), not the absolute end of the
             // scrollback buffer (which can be blank below the cursor and confusing on startup).
             ScrollRequest::ScreenTop => Some(row_height * history_lines as f32),
-            // Scroll to absolute top (offset 0) - used for a clean slate
-            ScrollRequest::CursorTop => Some(0.0),
+            ScrollRequest::ScrollbackTop => Some(0.0),
+            ScrollRequest::Row(row) => Some(row_height * row as f32),
             // Cursor follow is handled with viewport-aware logic below.
             ScrollRequest::CursorLine => None,
         };
@@ -574,7 +2296,10 @@ pub fn render_terminal(
         }
     }
 
-    scroll.show_viewport(ui, |ui, viewport| {
+    // Whether the view shows the live screen / the top of scrollback; set
+    // during layout, used for the jump buttons afterwards.
+    let mut view_position = (true, true);
+    let scroll_output = scroll.show_viewport(ui, |ui, viewport| {
         // Compute content_height with viewport known so that scrolling to
         // ScreenTop (history_lines * row_height) fully hides scrollback.
         // Without this, the remainder (viewport_h - screen_lines * row_height)
@@ -583,23 +2308,77 @@ pub fn render_terminal(
             (row_height_with_spacing * total_lines as f32 - ui.spacing().item_spacing.y).max(0.0);
         let content_height = natural.max(row_height * history_lines as f32 + viewport.height());
         ui.set_height(content_height);
+        if wide {
+            ui.set_width(grid_width);
+        }
+        // Half a row of slack so pixel rounding doesn't flicker the jump buttons.
+        view_position = (
+            viewport.min.y + row_height * 0.5 >= row_height * history_lines as f32,
+            viewport.min.y <= row_height * 0.5,
+        );
 
         if matches!(scroll_request, Some(ScrollRequest::CursorLine)) {
             let cursor_top = cursor_row_idx as f32 * row_height_with_spacing;
             let cursor_bottom = cursor_top + row_height;
+            let cursor_left = cursor_col_idx as f32 * char_width;
             let cursor_above = cursor_top < viewport.min.y;
             let cursor_below = cursor_bottom > viewport.max.y;
+            let cursor_beside =
+                cursor_left < viewport.min.x || cursor_left + char_width > viewport.max.x;
+            // Keep the rest of the live screen below the cursor in view too:
+            // TUIs with a DECSTBM scroll region often pin a status line there,
+            // and aligning just the cursor row to the bottom would hide it.
+            // Only if it fits, so a pinned grid taller than the viewport still
+            // follows the cursor.
+            let screen_bottom = (total_lines as f32 * row_height_with_spacing
+                - ui.spacing().item_spacing.y)
+                .max(cursor_bottom);
+            let follow_bottom = if screen_bottom - cursor_top <= viewport.height() {
+                screen_bottom
+            } else {
+                cursor_bottom
+            };
+            let screen_cut_off = !cursor_above && follow_bottom > viewport.max.y;
 
-            // Only scroll when the cursor is outside the visible range.
-            if cursor_above || cursor_below {
-                let target_rect = egui::Rect::from_min_size(
-                    egui::pos2(ui.min_rect().left(), ui.min_rect().top() + cursor_top),
-                    egui::vec2(1.0, row_height),
+            // Only scroll when the cursor, or the screen below it, is out of view.
+            if cursor_above || cursor_below || screen_cut_off || cursor_beside {
+                let left = ui.min_rect().left() + cursor_left;
+                let target_rect = egui::Rect::from_min_max(
+                    egui::pos2(left, ui.min_rect().top() + cursor_top),
+                    egui::pos2(left + char_width, ui.min_rect().top() + follow_bottom),
                 );
                 ui.scroll_to_rect(target_rect, Some(egui::Align::BOTTOM));
             }
         }
 
+        if let Some((row, col)) = copy_cursor.filter(|_| selection_state.copy_scroll_pending) {
+            selection_state.copy_scroll_pending = false;
+            let top = row as f32 * row_height_with_spacing;
+            let left = col as f32 * char_width;
+            if top < viewport.min.y
+                || top + row_height > viewport.max.y
+                || left < viewport.min.x
+                || left + char_width > viewport.max.x
+            {
+                let target_rect = egui::Rect::from_min_size(
+                    egui::pos2(ui.min_rect().left() + left, ui.min_rect().top() + top),
+                    egui::vec2(char_width, row_height),
+                );
+                ui.scroll_to_rect(target_rect, None);
+            }
+        }
+
+        if let Some(((row, col), align)) = search.take_scroll_to() {
+            let target_rect = egui::Rect::from_min_size(
+                egui::pos2(
+                    ui.min_rect().left() + col as f32 * char_width,
+                    ui.min_rect().top() + row as f32 * row_height_with_spacing,
+                ),
+                egui::vec2(char_width, row_height),
+            );
+            ui.scroll_to_rect(target_rect, Some(align));
+        }
+
         let mut min_row = (viewport.min.y / row_height_with_spacing).floor().max(0.0) as usize;
         let mut max_row = (viewport.max.y / row_height_with_spacing).ceil().max(0.0) as usize + 1;
 
@@ -612,18 +2391,38 @@ pub fn render_terminal(
         if min_row > max_row {
             min_row = max_row;
         }
+        search.set_viewport_top_row(min_row);
+
+        let rows = RowLayout::new(
+            ui.max_rect().top(),
+            min_row,
+            row_height_with_spacing,
+            pixels_per_point,
+        );
 
         let viewport_rect = egui::Rect::from_min_max(
             egui::pos2(ui.max_rect().left(), ui.max_rect().top() + viewport.min.y),
             egui::pos2(ui.max_rect().right(), ui.max_rect().top() + viewport.max.y),
         );
         let text_grid_max_x = viewport_rect.left() + char_width * num_cols as f32;
-        if total_lines > 0 && num_cols > 0 && char_width > 0.0 && row_height > 0.0 {
-            let cursor_x = viewport_rect.left() + cursor_col_idx as f32 * char_width;
-            let cursor_y = ui.max_rect().top() + cursor_row_idx as f32 * row_height_with_spacing;
+        // No IME anchor for a hidden cursor: its position is often just where
+        // a full-screen app last drew.
+        if !cursor_hidden && total_lines > 0 && num_cols > 0 && char_width > 0.0 && row_height > 0.0
+        {
+            let (cursor_x, cursor_width) = if pending_wrap {
+                // Pulled back inside the panel when there's no room past the grid.
+                let half = (char_width * 0.5).max(1.0);
+                (text_grid_max_x.min(viewport_rect.right() - half), half)
+            } else {
+                (
+                    viewport_rect.left() + cursor_col_idx as f32 * char_width,
+                    char_width.max(1.0),
+                )
+            };
+            let cursor_y = rows.row_y(cursor_row_idx);
             ime_cursor_rect = Some(egui::Rect::from_min_size(
                 egui::pos2(cursor_x, cursor_y),
-                egui::vec2(char_width.max(1.0), row_height.max(1.0)),
+                egui::vec2(cursor_width, row_height.max(1.0)),
             ));
         }
         let to_cell = |pos: egui::Pos2| -> Option<(usize, usize)> {
@@ -638,8 +2437,7 @@ pub fn render_terminal(
                 return None;
             }
 
-            let y = (pos.y - ui.max_rect().top()).max(0.0);
-            let mut row = (y / row_height_with_spacing).floor() as usize;
+            let mut row = rows.row_at(pos.y);
             if row >= total_lines {
                 row = total_lines - 1;
             }
@@ -679,6 +2477,11 @@ pub fn render_terminal(
                         selection_state.clear();
                     }
                     selection_state.stop_dragging();
+                    if pointer.button_double_clicked(egui::PointerButton::Primary) {
+                        if let Some((row, col)) = pointer.interact_pos().and_then(to_cell) {
+                            selection_state.select_word(term, row, col);
+                        }
+                    }
                 }
             });
         } else if selection_state.dragging {
@@ -689,103 +2492,852 @@ pub fn render_terminal(
             egui::Layout::left_to_right(egui::Align::Min).with_cross_align(egui::Align::Min);
         let row_start = min_row;
 
-        let y_min = ui.max_rect().top() + min_row as f32 * row_height_with_spacing;
-        let y_max = ui.max_rect().top() + max_row as f32 * row_height_with_spacing;
+        let y_min = rows.row_y(min_row);
+        let y_max = rows.row_y(max_row);
         let rect = egui::Rect::from_x_y_ranges(ui.max_rect().x_range(), y_min..=y_max);
 
         ui.allocate_ui_at_rect(rect, |viewport_ui| {
             let row_width = viewport_ui.max_rect().width();
             let base_left = viewport_ui.min_rect().left();
             let base_top = align_to_pixels(viewport_ui.min_rect().top(), pixels_per_point);
+            let mut row_cells: Vec<RowCell> = Vec::with_capacity(num_cols);
+            // Non-straight underlines, painted over the row as (columns, style, color).
+            let mut decorations: Vec<(std::ops::Range<usize>, UnderlineStyle, egui::Color32)> =
+                Vec::new();
             for row_idx in min_row..max_row {
                 let line = Line(top_line + row_idx as i32);
                 let row = &grid[line];
-                let mut job = egui::text::LayoutJob::default();
+                row_cells.clear();
+                decorations.clear();
 
                 for col_idx in 0..num_cols {
                     let col = Column(col_idx);
                     let cell = &row[col];
                     let ch = cell.c;
-                    let display_char = if ch == '\0' || ch == ' ' { ' ' } else { ch };
+                    // alacritty leaves '\t' in the cell a tab started from; the
+                    // emulator already moved the cursor, so draw it as one blank
+                    // cell (egui would lay it out several spaces wide).
+                    let display_char = if ch == '\0' || ch == ' ' || ch == '\t' {
+                        ' '
+                    } else {
+                        ch
+                    };
 
-                    let show_cursor = cursor.point == Point::new(line, col) && cursor_visible;
+                    let show_cursor = block_cursor
+                        && cursor_visible
+                        && !pending_wrap
+                        && cursor.point == Point::new(line, col);
                     let is_wide_continuation = cell.flags.contains(CellFlags::WIDE_CHAR_SPACER);
                     if is_wide_continuation {
                         continue;
                     }
                     let is_selected = selection_range_contains(selection_range, row_idx, col_idx);
+                    let is_copy_cursor = copy_cursor == Some((row_idx, col_idx));
+                    let search_hit = search.match_at(row_idx, col_idx);
 
                     let is_ghost = cell.flags.intersects(CellFlags::DIM | CellFlags::ITALIC);
                     let is_inverse = cell.flags.contains(CellFlags::INVERSE);
 
-                    // Base colors (before selection/cursor override)
-                    let (mut base_fg, mut base_bg) = if is_ghost {
-                        (egui::Color32::from_gray(140), egui::Color32::TRANSPARENT)
+                    // Base colors (before selection/cursor override). Dim/italic
+                    // "ghost" text only overrides the fg; an explicit bg is kept.
+                    let mut base_fg = if is_ghost {
+                        egui::Color32::from_gray(140)
                     } else {
-                        let f = term_color_to_egui(&cell.fg, true);
-                        let b = term_color_to_egui(&cell.bg, false);
-                        (f, b)
+                        term_color_to_egui(&cell.fg, true, defaults)
                     };
+                    let mut base_bg = cell_background_to_egui(&cell.bg, defaults);
 
                     // Handle SGR 7 (reverse video): swap fg and bg
                     if is_inverse {
                         if base_bg == egui::Color32::TRANSPARENT {
-                            base_bg = egui::Color32::from_rgb(18, 18, 18);
+                            base_bg = defaults.bg;
                         }
                         std::mem::swap(&mut base_fg, &mut base_bg);
                     }
 
-                    let fg = if show_cursor {
-                        egui::Color32::from_rgb(18, 18, 18)
-                    } else if is_selected {
-                        egui::Color32::from_rgb(18, 18, 18)
-                    } else {
-                        base_fg
-                    };
-                    let bg = if is_selected {
-                        egui::Color32::from_rgb(180, 180, 180)
-                    } else if show_cursor {
-                        egui::Color32::from_rgb(204, 204, 204)
-                    } else {
-                        base_bg
-                    };
+                    let fg = if is_copy_cursor {
+                        egui::Color32::from_rgb(18, 18, 18)
+                    } else if is_selected {
+                        highlight.selection_fg
+                    } else if show_cursor {
+                        highlight.cursor_fg
+                    } else if search_hit == Some(true) {
+                        egui::Color32::from_rgb(18, 18, 18)
+                    } else {
+                        base_fg
+                    };
+                    let bg = if is_copy_cursor {
+                        egui::Color32::from_rgb(230, 180, 80)
+                    } else if is_selected {
+                        highlight.selection
+                    } else if show_cursor {
+                        highlight.cursor
+                    } else if search_hit == Some(true) {
+                        egui::Color32::from_rgb(230, 140, 40)
+                    } else if search_hit == Some(false) {
+                        egui::Color32::from_rgb(110, 90, 30)
+                    } else {
+                        base_bg
+                    };
+
+                    // SGR 58 underline color, falling back to the fg when unset
+                    // (SGR 59) or when cursor/selection recolors the cell.
+                    let underline = UnderlineStyle::from_flags(cell.flags).map(|style| {
+                        let color = match cell.underline_color() {
+                            Some(color)
+                                if !show_cursor
+                                    && !is_selected
+                                    && !is_copy_cursor
+                                    && search_hit != Some(true) =>
+                            {
+                                term_color_to_egui(&color, true, defaults)
+                            }
+                            _ => fg,
+                        };
+                        (style, color)
+                    });
+                    // Plain SGR 4 goes through the text layout; the other styles are
+                    // painted after the row.
+                    if let Some((style, color)) = underline {
+                        if style != UnderlineStyle::Straight {
+                            let width = if cell.flags.contains(CellFlags::WIDE_CHAR) {
+                                2
+                            } else {
+                                1
+                            };
+                            match decorations.last_mut() {
+                                Some((cols, last_style, last_color))
+                                    if cols.end == col_idx
+                                        && *last_style == style
+                                        && *last_color == color =>
+                                {
+                                    cols.end = col_idx + width;
+                                }
+                                _ => decorations.push((col_idx..col_idx + width, style, color)),
+                            }
+                        }
+                    }
+
+                    // Combining marks live on the base cell; keep them with the base
+                    // glyph so egui composes them.
+                    let zerowidth = cell.zerowidth().unwrap_or(&[]);
+                    row_cells.push(RowCell {
+                        ch: display_char,
+                        zerowidth,
+                        fg,
+                        bg,
+                        underline,
+                    });
+                }
+
+                let key = row_cache_key(&row_cells, pixels_per_point);
+                let galley = match render_cache.rows.get(&row_idx) {
+                    Some((cached_key, galley)) if *cached_key == key => galley.clone(),
+                    _ => {
+                        let mut job = egui::text::LayoutJob::default();
+                        job.text.reserve(row_cells.len());
+                        for cell in &row_cells {
+                            let text_format = egui::TextFormat {
+                                font_id: font_id.clone(),
+                                color: cell.fg,
+                                background: cell.bg,
+                                underline: match cell.underline {
+                                    Some((UnderlineStyle::Straight, color)) => {
+                                        egui::Stroke::new(1.0, color)
+                                    }
+                                    _ => egui::Stroke::NONE,
+                                },
+                                ..Default::default()
+                            };
+                            append_cell(&mut job, cell.ch, cell.zerowidth, text_format);
+                        }
+                        let galley = viewport_ui.fonts(|f| f.layout_job(job));
+                        render_cache.rows.insert(row_idx, (key, galley.clone()));
+                        galley
+                    }
+                };
+
+                let row_top = base_top + (row_idx - row_start) as f32 * row_height_with_spacing;
+                let rect = egui::Rect::from_min_size(
+                    egui::pos2(base_left, row_top),
+                    egui::vec2(row_width, row_height),
+                );
+
+                viewport_ui.allocate_ui_at_rect(rect, |row_ui| {
+                    row_ui.with_layout(row_layout, |row_ui| {
+                        let label = egui::Label::new(galley).wrap(false);
+                        row_ui.add(label);
+                    });
+                });
+
+                for (cols, style, color) in &decorations {
+                    let x_range = egui::Rangef::new(
+                        base_left + cols.start as f32 * char_width,
+                        base_left + cols.end as f32 * char_width,
+                    );
+                    paint_underline(
+                        viewport_ui.painter(),
+                        x_range,
+                        rect.bottom(),
+                        *style,
+                        *color,
+                    );
+                }
+            }
+        });
+        // Drop rows that scrolled out of view so the cache stays viewport-sized.
+        render_cache
+            .rows
+            .retain(|row_idx, _| (min_row..max_row).contains(row_idx));
+
+        if let (true, Some(cursor_rect)) = (cursor_visible, ime_cursor_rect) {
+            if !block_cursor {
+                paint_cursor_shape(ui.painter(), cursor_rect, cursor.shape, highlight.cursor);
+            } else if pending_wrap {
+                ui.painter().rect_filled(cursor_rect, 0.0, highlight.cursor);
+            }
+        }
+        let predicted = terminal.local_echo_preview();
+        if let (false, Some(cursor_rect)) = (predicted.is_empty(), ime_cursor_rect) {
+            paint_local_echo(
+                ui.painter(),
+                predicted,
+                cursor_rect,
+                &font_id,
+                defaults,
+                highlight.cursor,
+            );
+            // Come back to drop the prediction if the echo never arrives.
+            ui.ctx().request_repaint_after(LOCAL_ECHO_TIMEOUT);
+        }
+        if let (Some(preedit), Some(cursor_rect)) = (ime_preedit, ime_cursor_rect) {
+            paint_ime_preedit(ui.painter(), preedit, cursor_rect, &font_id, defaults);
+        }
+        if let (true, Some(cursor_rect)) = (cursor_locator > 0.0, ime_cursor_rect) {
+            paint_cursor_locator(
+                ui.painter(),
+                cursor_rect,
+                egui::Rangef::new(viewport_rect.left(), text_grid_max_x),
+                highlight.cursor,
+                cursor_locator,
+            );
+        }
+    });
+
+    if scroll_output.state.offset.y != render_cache.scroll_offset {
+        render_cache.scroll_offset = scroll_output.state.offset.y;
+        render_cache.scrolled_at = Some(Instant::now());
+    }
+
+    let (at_live_screen, at_scrollback_top) = view_position;
+    if !at_live_screen && !terminal.is_alt_screen() {
+        *jump_request = render_jump_buttons(ui.ctx(), scroll_output.inner_rect, at_scrollback_top);
+    }
+
+    ime_cursor_rect
+}
+
+/// Non-block cursors: an underline or bar inside `cell`, or a hollow box.
+fn paint_cursor_shape(
+    painter: &egui::Painter,
+    cell: egui::Rect,
+    shape: ansi::CursorShape,
+    color: egui::Color32,
+) {
+    let thickness = (cell.height() * 0.1).round().max(2.0);
+    match shape {
+        ansi::CursorShape::Underline => {
+            let rect = egui::Rect::from_min_max(
+                egui::pos2(cell.left(), cell.bottom() - thickness),
+                cell.max,
+            );
+            painter.rect_filled(rect, 0.0, color);
+        }
+        ansi::CursorShape::Beam => {
+            let rect = egui::Rect::from_min_max(
+                cell.min,
+                egui::pos2(cell.left() + thickness, cell.bottom()),
+            );
+            painter.rect_filled(rect, 0.0, color);
+        }
+        ansi::CursorShape::HollowBlock => {
+            painter.rect_stroke(cell.shrink(0.5), 0.0, egui::Stroke::new(1.0, color));
+        }
+        ansi::CursorShape::Block | ansi::CursorShape::Hidden => {}
+    }
+}
+
+/// Row and column guides through the cursor cell plus a ring around it, at
+/// `strength` (1 = fully shown, fading to 0). The column guide spans whatever
+/// the painter's clip rect shows.
+fn paint_cursor_locator(
+    painter: &egui::Painter,
+    cell: egui::Rect,
+    grid_x: egui::Rangef,
+    color: egui::Color32,
+    strength: f32,
+) {
+    let strength = strength.clamp(0.0, 1.0);
+    let guide = color.gamma_multiply(0.18 * strength);
+    let clip_y = painter.clip_rect().y_range();
+    painter.rect_filled(
+        egui::Rect::from_x_y_ranges(grid_x, cell.y_range()),
+        0.0,
+        guide,
+    );
+    painter.rect_filled(
+        egui::Rect::from_x_y_ranges(cell.x_range(), clip_y),
+        0.0,
+        guide,
+    );
+    painter.rect_stroke(
+        cell.expand(3.0),
+        3.0,
+        egui::Stroke::new(2.0, color.gamma_multiply(strength)),
+    );
+}
+
+/// A floating scrollbar in the terminal's foreground color with no track
+/// until hovered. It shows while the pointer is over the terminal, and for
+/// `SCROLLBAR_LINGER` after the view scrolls.
+fn style_terminal_scrollbar(
+    ui: &mut egui::Ui,
+    color: egui::Color32,
+    render_cache: &TerminalRenderCache,
+) {
+    let linger = render_cache
+        .scrolled_at
+        .map(|at| at.elapsed())
+        .filter(|elapsed| *elapsed < SCROLLBAR_LINGER);
+    if let Some(elapsed) = linger {
+        ui.ctx().request_repaint_after(SCROLLBAR_LINGER - elapsed);
+    }
+
+    let style = ui.style_mut();
+    style.spacing.scroll = egui::style::ScrollStyle {
+        bar_width: 8.0,
+        floating_width: 4.0,
+        foreground_color: false,
+        dormant_background_opacity: 0.0,
+        active_background_opacity: 0.0,
+        interact_background_opacity: 1.0,
+        dormant_handle_opacity: if linger.is_some() { 0.5 } else { 0.0 },
+        active_handle_opacity: 0.5,
+        interact_handle_opacity: 1.0,
+        ..egui::style::ScrollStyle::floating()
+    };
+    let visuals = &mut style.visuals;
+    visuals.extreme_bg_color = color.gamma_multiply(0.08);
+    for (widget, strength) in [
+        (&mut visuals.widgets.inactive, 0.6),
+        (&mut visuals.widgets.hovered, 0.8),
+        (&mut visuals.widgets.active, 1.0),
+    ] {
+        widget.bg_fill = color.gamma_multiply(strength);
+        widget.rounding = egui::Rounding::same(4.0);
+    }
+}
+
+/// Floating "Top" / "Bottom" buttons over the bottom-right of the terminal,
+/// shown while the view is scrolled away from the live screen.
+fn render_jump_buttons(
+    ctx: &egui::Context,
+    terminal_rect: egui::Rect,
+    at_scrollback_top: bool,
+) -> Option<ScrollRequest> {
+    let mut request = None;
+    egui::Area::new(egui::Id::new("terminal_jump_buttons"))
+        .order(egui::Order::Foreground)
+        .pivot(egui::Align2::RIGHT_BOTTOM)
+        .fixed_pos(terminal_rect.right_bottom() - egui::vec2(24.0, 12.0))
+        .show(ctx, |ui| {
+            egui::Frame::none()
+                .fill(egui::Color32::from_rgb(40, 40, 40))
+                .stroke(egui::Stroke::new(1.0, egui::Color32::from_gray(80)))
+                .rounding(egui::Rounding::same(12.0))
+                .inner_margin(egui::Margin::symmetric(6.0, 3.0))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let button = |text: &str| {
+                            egui::Button::new(
+                                egui::RichText::new(text)
+                                    .monospace()
+                                    .size(12.0)
+                                    .color(egui::Color32::from_gray(210)),
+                            )
+                            .frame(false)
+                        };
+                        if !at_scrollback_top
+                            && ui
+                                .add(button("▲ Top"))
+                                .on_hover_text("Oldest scrollback")
+                                .clicked()
+                        {
+                            request = Some(ScrollRequest::ScrollbackTop);
+                        }
+                        if ui
+                            .add(button("▼ Bottom"))
+                            .on_hover_text("Live screen")
+                            .clicked()
+                        {
+                            request = Some(ScrollRequest::ScreenTop);
+                        }
+                    });
+                });
+        });
+    request
+}
+
+/// Resolved look of one visible cell; also what the row cache key hashes.
+#[derive(Hash)]
+struct RowCell<'a> {
+    ch: char,
+    zerowidth: &'a [char],
+    fg: egui::Color32,
+    bg: egui::Color32,
+    underline: Option<(UnderlineStyle, egui::Color32)>,
+}
+
+/// Underline styles selected with `ESC[4:Nm`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum UnderlineStyle {
+    Straight,
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl UnderlineStyle {
+    fn from_flags(flags: CellFlags) -> Option<Self> {
+        if flags.contains(CellFlags::UNDERCURL) {
+            Some(UnderlineStyle::Curly)
+        } else if flags.contains(CellFlags::DOUBLE_UNDERLINE) {
+            Some(UnderlineStyle::Double)
+        } else if flags.contains(CellFlags::DOTTED_UNDERLINE) {
+            Some(UnderlineStyle::Dotted)
+        } else if flags.contains(CellFlags::DASHED_UNDERLINE) {
+            Some(UnderlineStyle::Dashed)
+        } else if flags.contains(CellFlags::UNDERLINE) {
+            Some(UnderlineStyle::Straight)
+        } else {
+            None
+        }
+    }
+}
+
+/// Paint a non-straight underline along the bottom of a row. Patterns are
+/// phased on absolute x so adjacent runs line up.
+fn paint_underline(
+    painter: &egui::Painter,
+    x_range: egui::Rangef,
+    row_bottom: f32,
+    style: UnderlineStyle,
+    color: egui::Color32,
+) {
+    let stroke = egui::Stroke::new(1.0, color);
+    let y = row_bottom - 1.5;
+    match style {
+        UnderlineStyle::Straight => {
+            painter.hline(x_range, y, stroke);
+        }
+        UnderlineStyle::Double => {
+            painter.hline(x_range, y, stroke);
+            painter.hline(x_range, y - 2.0, stroke);
+        }
+        UnderlineStyle::Curly => {
+            const PERIOD: f32 = 6.0;
+            const AMPLITUDE: f32 = 1.25;
+            let wave_y = y - AMPLITUDE;
+            let mut points = Vec::with_capacity(x_range.span().ceil() as usize + 2);
+            let mut x = x_range.min;
+            while x < x_range.max {
+                let phase = x / PERIOD * std::f32::consts::TAU;
+                points.push(egui::pos2(x, wave_y + AMPLITUDE * phase.sin()));
+                x += 1.0;
+            }
+            let phase = x_range.max / PERIOD * std::f32::consts::TAU;
+            points.push(egui::pos2(x_range.max, wave_y + AMPLITUDE * phase.sin()));
+            painter.add(egui::Shape::line(points, stroke));
+        }
+        UnderlineStyle::Dotted | UnderlineStyle::Dashed => {
+            let (on, period) = if style == UnderlineStyle::Dotted {
+                (1.0, 3.0)
+            } else {
+                (4.0, 7.0)
+            };
+            let mut x = (x_range.min / period).floor() * period;
+            while x < x_range.max {
+                let start = x.max(x_range.min);
+                let end = (x + on).min(x_range.max);
+                if end > start {
+                    painter.hline(start..=end, y, stroke);
+                }
+                x += period;
+            }
+        }
+    }
+}
+
+/// Append one cell's glyph (plus combining marks) straight into the job's
+/// text buffer instead of going through a temporary `String`. Consecutive
+/// cells with the same format share one section; cursor and selection cells
+/// differ in color, so they still get their own.
+fn append_cell(
+    job: &mut egui::text::LayoutJob,
+    ch: char,
+    zerowidth: &[char],
+    format: egui::TextFormat,
+) {
+    let start = job.text.len();
+    job.text.push(ch);
+    job.text.extend(zerowidth);
+    if let Some(last) = job.sections.last_mut() {
+        if last.byte_range.end == start && last.format == format {
+            last.byte_range.end = job.text.len();
+            return;
+        }
+    }
+    job.sections.push(egui::text::LayoutSection {
+        leading_space: 0.0,
+        byte_range: start..job.text.len(),
+        format,
+    });
+}
+
+fn row_cache_key(cells: &[RowCell], pixels_per_point: f32) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    TERM_FONT_SIZE.to_bits().hash(&mut hasher);
+    pixels_per_point.to_bits().hash(&mut hasher);
+    cells.hash(&mut hasher);
+    hasher.finish()
+}
 
-                    let text_format = egui::TextFormat {
-                        font_id: font_id.clone(),
-                        color: fg,
-                        background: bg,
-                        ..Default::default()
-                    };
-                    job.append(&display_char.to_string(), 0.0, text_format);
-                }
+/// Draw not-yet-echoed input dimmed from the cursor cell, with a thin bar
+/// where the next character will go.
+fn paint_local_echo(
+    painter: &egui::Painter,
+    text: &str,
+    cursor_rect: egui::Rect,
+    font_id: &egui::FontId,
+    defaults: DefaultColors,
+    cursor_color: egui::Color32,
+) {
+    let fg = defaults.fg.gamma_multiply(0.55);
+    let width = cursor_rect.width() * text.chars().count() as f32;
+    let text_rect =
+        egui::Rect::from_min_size(cursor_rect.min, egui::vec2(width, cursor_rect.height()));
+    painter.rect_filled(text_rect, 0.0, defaults.bg);
+    painter.text(
+        text_rect.min,
+        egui::Align2::LEFT_TOP,
+        text,
+        font_id.clone(),
+        fg,
+    );
+    painter.vline(
+        text_rect.right(),
+        text_rect.y_range(),
+        egui::Stroke::new(1.0, cursor_color),
+    );
+}
 
-                let row_top = base_top + (row_idx - row_start) as f32 * row_height_with_spacing;
-                let rect = egui::Rect::from_min_size(
-                    egui::pos2(base_left, row_top),
-                    egui::vec2(row_width, row_height),
-                );
+/// Draw the IME composition string over the cursor cell with an underline,
+/// emphasizing the segment (or caret) the IME reports as active.
+fn paint_ime_preedit(
+    painter: &egui::Painter,
+    preedit: &ImePreedit,
+    cursor_rect: egui::Rect,
+    font_id: &egui::FontId,
+    defaults: DefaultColors,
+) {
+    if preedit.text.is_empty() {
+        return;
+    }
 
-                viewport_ui.allocate_ui_at_rect(rect, |row_ui| {
-                    row_ui.with_layout(row_layout, |row_ui| {
-                        let label = egui::Label::new(job).wrap(false);
-                        row_ui.add(label);
-                    });
-                });
-            }
-        });
-    });
+    let fg = defaults.fg;
+    let galley = painter.layout_no_wrap(preedit.text.clone(), font_id.clone(), fg);
+    let text_rect = egui::Rect::from_min_size(
+        cursor_rect.min,
+        egui::vec2(
+            galley.size().x.max(cursor_rect.width()),
+            cursor_rect.height(),
+        ),
+    );
+    painter.rect_filled(text_rect, 0.0, defaults.bg);
+    painter.galley(text_rect.min, galley.clone(), fg);
 
-    ime_cursor_rect
+    let underline_y = text_rect.bottom() - 1.0;
+    painter.hline(text_rect.x_range(), underline_y, egui::Stroke::new(1.0, fg));
+
+    let Some((start, end)) = preedit.cursor else {
+        return;
+    };
+    let x_at = |byte: usize| -> Option<f32> {
+        let chars = preedit.text.get(..byte)?.chars().count();
+        let pos = galley.pos_from_ccursor(egui::text::CCursor::new(chars));
+        Some(text_rect.left() + pos.min.x)
+    };
+    let (Some(x0), Some(x1)) = (x_at(start), x_at(end)) else {
+        return;
+    };
+    if start == end {
+        painter.vline(x0, text_rect.y_range(), egui::Stroke::new(1.0, fg));
+    } else {
+        painter.hline(x0..=x1, underline_y, egui::Stroke::new(2.0, fg));
+    }
 }
 
+/// The selection as plain text, at most `max_bytes` (itself capped at
+/// `MAX_SELECTION_COPY_LIMIT`); the flag is set when it was cut short.
 pub fn selected_text_for_copy(
     terminal: &TerminalInstance,
     selection_state: &TerminalSelectionState,
-) -> Option<String> {
+    filter: CopyFilter,
+    filter_box_drawing: bool,
+    max_bytes: usize,
+) -> Option<(String, bool)> {
     if !selection_state.has_selection() {
         return None;
     }
-    selected_text(terminal.term(), selection_state)
+    let (text, truncated) = selected_text(
+        terminal.term(),
+        selection_state,
+        terminal.tab_width,
+        max_bytes,
+    )?;
+    if filter == CopyFilter::Off {
+        return Some((text, truncated));
+    }
+    let text = filter_copied_text(&text, filter, filter_box_drawing);
+    if text.is_empty() {
+        None
+    } else {
+        Some((text, truncated))
+    }
+}
+
+/// The selection as HTML for rich editors: a `<pre>` in the terminal's
+/// default colors, with a styled `<span>` for each run of cells that differ
+/// from them (colors, reverse video, bold, italic, underline, strikeout).
+/// Trailing blanks are trimmed per row as in the plain-text copy; the copy
+/// filter only applies to the plain text.
+pub fn selected_html_for_copy(
+    terminal: &TerminalInstance,
+    selection_state: &TerminalSelectionState,
+    max_bytes: usize,
+) -> Option<String> {
+    let ((start_row, start_col), (end_row, end_col)) = selection_state.normalized()?;
+    if start_row == end_row && start_col == end_col {
+        return None;
+    }
+    let term = terminal.term();
+    let defaults = terminal.default_colors();
+    let grid = term.grid();
+    let total_lines = grid.total_lines();
+    let num_cols = term.columns();
+    if total_lines == 0 || num_cols == 0 || start_row >= total_lines {
+        return None;
+    }
+    let top_line = -(grid.history_size() as i32);
+    let last_row = end_row.min(total_lines - 1);
+
+    let mut out = format!(
+        "<pre style=\"font-family:monospace;color:{};background-color:{}\">",
+        css_color(defaults.fg),
+        css_color(defaults.bg)
+    );
+    let tab_width = terminal.tab_width;
+    // Inline style of the cell at hand and of the open run; empty is default
+    // text. Both are reused, so unchanged runs cost no allocation.
+    let mut style = String::new();
+    let mut run_style = String::new();
+    for row_idx in start_row..=last_row {
+        // Markup roughly triples the text; stop at a comparable size.
+        if out.len() >= max_bytes.min(MAX_SELECTION_COPY_LIMIT) * 3 {
+            break;
+        }
+        let row = &grid[Line(top_line + row_idx as i32)];
+        let first = if row_idx == start_row { start_col } else { 0 };
+        let last = if row_idx == last_row {
+            end_col.min(num_cols - 1)
+        } else {
+            num_cols - 1
+        };
+        // Default-styled blanks are held back until text follows them, so
+        // trailing ones are trimmed.
+        let mut blanks = 0;
+        // As in the plain-text copy, a tab's padding is copied as the tab.
+        let mut tab_end = 0;
+        for col_idx in first..=last {
+            let cell = &row[Column(col_idx)];
+            if cell
+                .flags
+                .intersects(CellFlags::WIDE_CHAR_SPACER | CellFlags::LEADING_WIDE_CHAR_SPACER)
+            {
+                continue;
+            }
+            let ch = if cell.c == '\0' { ' ' } else { cell.c };
+            if ch == ' ' && col_idx < tab_end {
+                continue;
+            }
+            if ch == '\t' {
+                tab_end = (col_idx / tab_width + 1) * tab_width;
+            }
+            let mut fg = term_color_to_egui(&cell.fg, true, defaults);
+            let mut bg = cell_background_to_egui(&cell.bg, defaults);
+            if cell.flags.contains(CellFlags::INVERSE) {
+                if bg == egui::Color32::TRANSPARENT {
+                    bg = defaults.bg;
+                }
+                std::mem::swap(&mut fg, &mut bg);
+            }
+            style.clear();
+            if fg != defaults.fg {
+                let _ = write!(style, "color:{};", css_color(fg));
+            }
+            if bg != egui::Color32::TRANSPARENT && bg != defaults.bg {
+                let _ = write!(style, "background-color:{};", css_color(bg));
+            }
+            if cell.flags.contains(CellFlags::BOLD) {
+                style.push_str("font-weight:bold;");
+            }
+            if cell.flags.contains(CellFlags::ITALIC) {
+                style.push_str("font-style:italic;");
+            }
+            let underline = UnderlineStyle::from_flags(cell.flags).is_some();
+            let strikeout = cell.flags.contains(CellFlags::STRIKEOUT);
+            match (underline, strikeout) {
+                (true, true) => style.push_str("text-decoration:underline line-through;"),
+                (true, false) => style.push_str("text-decoration:underline;"),
+                (false, true) => style.push_str("text-decoration:line-through;"),
+                (false, false) => {}
+            }
+
+            if style != run_style {
+                if !run_style.is_empty() {
+                    out.push_str("</span>");
+                }
+                if !style.is_empty() {
+                    out.extend(std::iter::repeat_n(' ', blanks));
+                    blanks = 0;
+                    let _ = write!(out, "<span style=\"{}\">", style);
+                }
+                std::mem::swap(&mut style, &mut run_style);
+            }
+            let zerowidth = cell.zerowidth().unwrap_or(&[]);
+            if run_style.is_empty() && ch == ' ' && zerowidth.is_empty() {
+                blanks += 1;
+                continue;
+            }
+            out.extend(std::iter::repeat_n(' ', blanks));
+            blanks = 0;
+            push_html_escaped(&mut out, ch);
+            for &mark in zerowidth {
+                push_html_escaped(&mut out, mark);
+            }
+        }
+        if !run_style.is_empty() {
+            out.push_str("</span>");
+            run_style.clear();
+        }
+        if row_idx != last_row {
+            out.push('\n');
+        }
+    }
+    out.push_str("</pre>");
+    Some(out)
+}
+
+/// SGR parameters (after a reset) reproducing `cell`'s colors and attributes.
+fn cell_sgr(cell: &alacritty_terminal::term::cell::Cell, out: &mut String) {
+    out.clear();
+    out.push('0');
+    for (flag, code) in [
+        (CellFlags::BOLD, "1"),
+        (CellFlags::DIM, "2"),
+        (CellFlags::ITALIC, "3"),
+        (CellFlags::INVERSE, "7"),
+        (CellFlags::HIDDEN, "8"),
+        (CellFlags::STRIKEOUT, "9"),
+    ] {
+        if cell.flags.contains(flag) {
+            out.push(';');
+            out.push_str(code);
+        }
+    }
+    if UnderlineStyle::from_flags(cell.flags).is_some() {
+        out.push_str(";4");
+    }
+    for (color, base) in [(cell.fg, 30), (cell.bg, 40)] {
+        match color {
+            TermColor::Named(named) if (named as usize) < 8 => {
+                let _ = write!(out, ";{}", base + named as usize);
+            }
+            TermColor::Named(named) if (named as usize) < 16 => {
+                let _ = write!(out, ";{}", base + 60 + named as usize - 8);
+            }
+            // Default colors (and the dim variants alacritty resolves itself).
+            TermColor::Named(_) => {}
+            TermColor::Indexed(idx) => {
+                let _ = write!(out, ";{};5;{}", base + 8, idx);
+            }
+            TermColor::Spec(rgb) => {
+                let _ = write!(out, ";{};2;{};{};{}", base + 8, rgb.r, rgb.g, rgb.b);
+            }
+        }
+    }
+}
+
+/// `#rrggbb`, formatted in place rather than through a String.
+struct CssColor(egui::Color32);
+
+impl std::fmt::Display for CssColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let CssColor(color) = self;
+        write!(f, "#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+    }
+}
+
+fn css_color(color: egui::Color32) -> CssColor {
+    CssColor(color)
+}
+
+fn push_html_escaped(out: &mut String, c: char) {
+    match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        c => out.push(c),
+    }
+}
+
+/// Replace or drop control characters, U+FFFD and optionally box-drawing /
+/// block characters, then re-trim trailing blanks on each line.
+fn filter_copied_text(text: &str, filter: CopyFilter, box_drawing: bool) -> String {
+    let is_filtered = |c: char| {
+        (c.is_control() && c != '\n' && c != '\t')
+            || c == char::REPLACEMENT_CHARACTER
+            || (box_drawing && ('\u{2500}'..='\u{259F}').contains(&c))
+    };
+    let mut out = String::with_capacity(text.len());
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let line_start = out.len();
+        for c in line.chars() {
+            if !is_filtered(c) {
+                out.push(c);
+            } else if filter == CopyFilter::Replace {
+                out.push(' ');
+            }
+        }
+        let trimmed = out[line_start..].trim_end_matches(' ').len();
+        out.truncate(line_start + trimmed);
+    }
+    out
 }
 
 fn selection_range_contains(
@@ -812,7 +3364,14 @@ fn selection_range_contains(
     true
 }
 
-fn selected_text(term: &Term<VoidListener>, selection_state: &TerminalSelectionState) -> Option<String> {
+/// Selected cells as text, trailing blanks trimmed per row, and whether the
+/// text stopped at `max_bytes` before the end of the selection.
+fn selected_text(
+    term: &Term<TermEventQueue>,
+    selection_state: &TerminalSelectionState,
+    tab_width: usize,
+    max_bytes: usize,
+) -> Option<(String, bool)> {
     let ((start_row, start_col), (end_row, end_col)) = selection_state.normalized()?;
     if start_row == end_row && start_col == end_col {
         return None;
@@ -830,11 +3389,14 @@ fn selected_text(term: &Term<VoidListener>, selection_state: &TerminalSelectionS
     let last_row = end_row.min(total_lines - 1);
     let selected_rows = last_row.saturating_sub(start_row) + 1;
     let estimated = selected_rows.saturating_mul(num_cols.saturating_add(1));
-    let reserve = estimated.min(MAX_SELECTION_COPY_BYTES);
+    let max_bytes = max_bytes.min(MAX_SELECTION_COPY_LIMIT);
+    let reserve = estimated.min(max_bytes);
     let mut out = String::with_capacity(reserve);
+    let mut truncated = false;
 
     'rows: for row_idx in start_row..=last_row {
-        if out.len() >= MAX_SELECTION_COPY_BYTES {
+        if out.len() >= max_bytes {
+            truncated = true;
             break;
         }
         let line = Line(top_line + row_idx as i32);
@@ -852,26 +3414,48 @@ fn selected_text(term: &Term<VoidListener>, selection_state: &TerminalSelectionS
 
         let row_start_len = out.len();
         let mut row_non_space_len = 0usize;
+        // Blank cells between a '\t' and the next tab stop are the tab's
+        // expansion; copy the tab itself instead of the padding.
+        let mut tab_end = 0;
         for col_idx in line_start..=line_end {
             let cell = &row[Column(col_idx)];
-            if cell.flags.contains(CellFlags::WIDE_CHAR_SPACER) {
+            // Spacers are synthetic: the right half of a wide char, or the blank
+            // left at the end of a row when a wide char wrapped to the next one.
+            // They never count toward trailing-space trimming.
+            if cell
+                .flags
+                .intersects(CellFlags::WIDE_CHAR_SPACER | CellFlags::LEADING_WIDE_CHAR_SPACER)
+            {
                 continue;
             }
             let ch = if cell.c == '\0' { ' ' } else { cell.c };
-            let ch_len = ch.len_utf8();
-            if out.len().saturating_add(ch_len) > MAX_SELECTION_COPY_BYTES {
+            if ch == ' ' && col_idx < tab_end {
+                continue;
+            }
+            if ch == '\t' {
+                tab_end = (col_idx / tab_width + 1) * tab_width;
+            }
+            let zerowidth = cell.zerowidth().unwrap_or(&[]);
+            let blank = ch == ' ' && zerowidth.is_empty();
+            let ch_len = ch.len_utf8() + zerowidth.iter().map(|c| c.len_utf8()).sum::<usize>();
+            // Blanks only count once text follows them; trailing ones are
+            // trimmed and must not use up the limit.
+            if !blank && out.len().saturating_add(ch_len) > max_bytes {
                 out.truncate(row_start_len + row_non_space_len);
+                truncated = true;
                 break 'rows;
             }
             out.push(ch);
-            if ch != ' ' {
+            out.extend(zerowidth);
+            if !blank {
                 row_non_space_len = out.len() - row_start_len;
             }
         }
         out.truncate(row_start_len + row_non_space_len);
 
         if row_idx != last_row {
-            if out.len().saturating_add(1) > MAX_SELECTION_COPY_BYTES {
+            if out.len().saturating_add(1) > max_bytes {
+                truncated = true;
                 break;
             }
             out.push('\n');
@@ -881,7 +3465,7 @@ fn selected_text(term: &Term<VoidListener>, selection_state: &TerminalSelectionS
     if out.is_empty() {
         None
     } else {
-        Some(out)
+        Some((out, truncated))
     }
 }
 
@@ -950,24 +3534,56 @@ pub fn render_vt_log(ui: &mut egui::Ui, terminal: Option<&TerminalInstance>) {
 pub fn key_to_terminal_input(
     event: &winit::event::KeyEvent,
     modifiers: &winit::event::Modifiers,
+    mode: TermMode,
+    modify_other_keys: bool,
+    alt_sends_esc: bool,
 ) -> Option<Vec<u8>> {
     if !event.state.is_pressed() {
         return None;
     }
 
     let ctrl = modifiers.state().control_key();
+    let alt = modifiers.state().alt_key();
+    let shift = modifiers.state().shift_key();
 
-    // Ctrl + letter → control character (0x01..=0x1a)
+    // Ctrl + letter → control character (0x01..=0x1a). A control byte cannot
+    // carry Shift, so with modifyOtherKeys on, Ctrl+Shift+letter is sent as
+    // `CSI 27 ; m ; code ~` instead (code of the uppercase letter, like xterm).
+    // Ctrl+Shift shortcuts the UI handles itself never get here.
     if ctrl {
         if let Key::Character(text) = &event.logical_key {
             let ch = text.chars().next()?;
             if ch.is_ascii_alphabetic() {
+                if shift && modify_other_keys {
+                    let param = csi_modifier_param(modifiers);
+                    let code = ch.to_ascii_uppercase() as u32;
+                    return Some(format!("\x1b[27;{};{}~", param, code).into_bytes());
+                }
                 let ctrl_byte = (ch.to_ascii_lowercase() as u8) - b'a' + 1;
                 return Some(vec![ctrl_byte]);
             }
         }
     }
 
+    // Numeric keypad in application keypad mode (DECKPAM) → SS3 sequences
+    if mode.contains(TermMode::APP_KEYPAD) {
+        if let Some(bytes) = keypad_app_sequence(event) {
+            return Some(bytes.to_vec());
+        }
+    }
+
+    // Modified special keys → xterm-style `CSI 1;m X` / `CSI n;m ~`
+    if let Key::Named(named) = &event.logical_key {
+        let param = csi_modifier_param(modifiers);
+        if param > 1 {
+            if let Some(bytes) = modified_named_key_sequence(*named, param) {
+                return Some(bytes);
+            }
+        }
+    }
+
+    let app_cursor = mode.contains(TermMode::APP_CURSOR);
+
     // Handle named (special) keys
     match &event.logical_key {
         Key::Named(named) => {
@@ -977,6 +3593,12 @@ pub fn key_to_terminal_input(
                 NamedKey::Tab => b"\t",
                 NamedKey::Escape => b"\x1b",
                 NamedKey::Space => b" ",
+                NamedKey::ArrowUp if app_cursor => b"\x1bOA",
+                NamedKey::ArrowDown if app_cursor => b"\x1bOB",
+                NamedKey::ArrowRight if app_cursor => b"\x1bOC",
+                NamedKey::ArrowLeft if app_cursor => b"\x1bOD",
+                NamedKey::Home if app_cursor => b"\x1bOH",
+                NamedKey::End if app_cursor => b"\x1bOF",
                 NamedKey::ArrowUp => b"\x1b[A",
                 NamedKey::ArrowDown => b"\x1b[B",
                 NamedKey::ArrowRight => b"\x1b[C",
@@ -1004,12 +3626,606 @@ pub fn key_to_terminal_input(
             Some(bytes.to_vec())
         }
         Key::Character(text) => {
-            if let Some(ref text) = event.text {
-                Some(text.as_bytes().to_vec())
-            } else {
-                Some(text.as_bytes().to_vec())
+            let text = event.text.as_ref().unwrap_or(text);
+            let mut bytes = Vec::with_capacity(text.len() + 1);
+            // Alt as Meta: ESC prefix. Ctrl+Alt is left alone since it doubles
+            // as AltGr on Windows keyboard layouts.
+            if alt_sends_esc && alt && !ctrl {
+                bytes.push(0x1b);
             }
+            bytes.extend_from_slice(text.as_bytes());
+            Some(bytes)
         }
         _ => None,
     }
 }
+
+/// xterm modifier parameter: 1 + (Shift=1 | Alt=2 | Ctrl=4).
+fn csi_modifier_param(modifiers: &winit::event::Modifiers) -> u8 {
+    let state = modifiers.state();
+    let mut bits = 0u8;
+    if state.shift_key() {
+        bits |= 1;
+    }
+    if state.alt_key() {
+        bits |= 2;
+    }
+    if state.control_key() {
+        bits |= 4;
+    }
+    1 + bits
+}
+
+/// Encode a special key together with a modifier parameter (e.g. Ctrl+Right →
+/// `ESC[1;5C`). Returns `None` for keys that have no modified form.
+fn modified_named_key_sequence(named: NamedKey, param: u8) -> Option<Vec<u8>> {
+    let seq = match named {
+        NamedKey::ArrowUp => format!("\x1b[1;{}A", param),
+        NamedKey::ArrowDown => format!("\x1b[1;{}B", param),
+        NamedKey::ArrowRight => format!("\x1b[1;{}C", param),
+        NamedKey::ArrowLeft => format!("\x1b[1;{}D", param),
+        NamedKey::Home => format!("\x1b[1;{}H", param),
+        NamedKey::End => format!("\x1b[1;{}F", param),
+        NamedKey::Insert => format!("\x1b[2;{}~", param),
+        NamedKey::Delete => format!("\x1b[3;{}~", param),
+        NamedKey::PageUp => format!("\x1b[5;{}~", param),
+        NamedKey::PageDown => format!("\x1b[6;{}~", param),
+        NamedKey::F1 => format!("\x1b[1;{}P", param),
+        NamedKey::F2 => format!("\x1b[1;{}Q", param),
+        NamedKey::F3 => format!("\x1b[1;{}R", param),
+        NamedKey::F4 => format!("\x1b[1;{}S", param),
+        NamedKey::F5 => format!("\x1b[15;{}~", param),
+        NamedKey::F6 => format!("\x1b[17;{}~", param),
+        NamedKey::F7 => format!("\x1b[18;{}~", param),
+        NamedKey::F8 => format!("\x1b[19;{}~", param),
+        NamedKey::F9 => format!("\x1b[20;{}~", param),
+        NamedKey::F10 => format!("\x1b[21;{}~", param),
+        NamedKey::F11 => format!("\x1b[23;{}~", param),
+        NamedKey::F12 => format!("\x1b[24;{}~", param),
+        // Shift+Tab is sent as back-tab (CBT) rather than a parameterized form.
+        NamedKey::Tab if param == 2 => "\x1b[Z".to_string(),
+        _ => return None,
+    };
+    Some(seq.into_bytes())
+}
+
+/// Map a numeric keypad key to its application keypad (SS3) sequence.
+///
+/// Only keys that produce a character, and keypad Enter (told apart from the
+/// main Enter by its physical key), are remapped; with NumLock off the keypad
+/// reports navigation keys, which keep their cursor-key encoding.
+fn keypad_app_sequence(event: &winit::event::KeyEvent) -> Option<&'static [u8]> {
+    if event.location != KeyLocation::Numpad
+        || !matches!(
+            event.logical_key,
+            Key::Character(_) | Key::Named(NamedKey::Enter)
+        )
+    {
+        return None;
+    }
+    let PhysicalKey::Code(code) = event.physical_key else {
+        return None;
+    };
+    let bytes: &[u8] = match code {
+        KeyCode::Numpad0 => b"\x1bOp",
+        KeyCode::Numpad1 => b"\x1bOq",
+        KeyCode::Numpad2 => b"\x1bOr",
+        KeyCode::Numpad3 => b"\x1bOs",
+        KeyCode::Numpad4 => b"\x1bOt",
+        KeyCode::Numpad5 => b"\x1bOu",
+        KeyCode::Numpad6 => b"\x1bOv",
+        KeyCode::Numpad7 => b"\x1bOw",
+        KeyCode::Numpad8 => b"\x1bOx",
+        KeyCode::Numpad9 => b"\x1bOy",
+        KeyCode::NumpadMultiply => b"\x1bOj",
+        KeyCode::NumpadAdd => b"\x1bOk",
+        KeyCode::NumpadComma => b"\x1bOl",
+        KeyCode::NumpadSubtract => b"\x1bOm",
+        KeyCode::NumpadDecimal => b"\x1bOn",
+        KeyCode::NumpadDivide => b"\x1bOo",
+        KeyCode::NumpadEqual => b"\x1bOX",
+        KeyCode::NumpadEnter => b"\x1bOM",
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pty::MemoryPty;
+
+    /// PTY output the test hands over one chunk per read. It signals `idle`
+    /// before waiting for the next chunk, by which point the reader thread has
+    /// queued the previous one for `process_input`.
+    struct ScriptedOutput {
+        chunks: mpsc::Receiver<Vec<u8>>,
+        idle: mpsc::Sender<()>,
+        pending: Vec<u8>,
+    }
+
+    impl io::Read for ScriptedOutput {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            while self.pending.is_empty() {
+                let _ = self.idle.send(());
+                match self.chunks.recv() {
+                    Ok(chunk) => self.pending = chunk,
+                    Err(_) => return Ok(0),
+                }
+            }
+            let n = buf.len().min(self.pending.len());
+            buf[..n].copy_from_slice(&self.pending[..n]);
+            self.pending.drain(..n);
+            Ok(n)
+        }
+    }
+
+    /// A `TerminalInstance` on a `MemoryPty` whose output arrives in the
+    /// chunks the test picks.
+    struct Harness {
+        // Dropped first, so the reader thread sees the end of the output.
+        chunks: mpsc::Sender<Vec<u8>>,
+        idle: mpsc::Receiver<()>,
+        input: Arc<Mutex<Vec<u8>>>,
+        pty_size: Arc<Mutex<Option<PtySize>>>,
+        terminal: TerminalInstance,
+    }
+
+    impl Harness {
+        fn new(rows: u16, cols: u16) -> Self {
+            let (chunks, chunk_rx) = mpsc::channel();
+            let (idle_tx, idle) = mpsc::channel();
+            let pty = MemoryPty::new(ScriptedOutput {
+                chunks: chunk_rx,
+                idle: idle_tx,
+                pending: Vec::new(),
+            });
+            let input = pty.input();
+            let pty_size = pty.size();
+            let shell_config = ShellConfig {
+                queue_capacity: 64,
+                ..ShellConfig::default()
+            };
+            let terminal = TerminalInstance::with_backend(
+                rows,
+                cols,
+                PathBuf::new(),
+                pty,
+                &shell_config,
+                Arc::new(|| {}),
+            );
+            idle.recv().unwrap();
+            Self {
+                chunks,
+                idle,
+                input,
+                pty_size,
+                terminal,
+            }
+        }
+
+        /// Deliver `bytes` as one PTY read and parse it.
+        fn feed(&mut self, bytes: &[u8]) -> ProcessInputResult {
+            self.chunks.send(bytes.to_vec()).unwrap();
+            self.idle.recv().unwrap();
+            self.terminal.process_input()
+        }
+
+        /// Everything written to the PTY since the last call.
+        fn take_input(&self) -> Vec<u8> {
+            std::mem::take(&mut *self.input.lock().unwrap())
+        }
+
+        /// Screen row `line`, without trailing blanks.
+        fn row(&self, line: i32) -> String {
+            self.terminal.row_text(Line(line)).trim_end().to_string()
+        }
+    }
+
+    #[test]
+    fn memory_pty_output_reaches_the_grid_and_replies_are_recorded() {
+        let mut h = Harness::new(4, 20);
+        let result = h.feed(b"hello\r\nworld\x1b[6n");
+        assert!(result.had_input);
+        assert_eq!(h.row(0), "hello");
+        assert_eq!(h.row(1), "world");
+        assert_eq!(h.take_input(), b"\x1b[2;6R");
+    }
+
+    #[test]
+    fn sgr_58_sets_the_underline_color_and_59_clears_it() {
+        let mut h = Harness::new(2, 10);
+        h.feed(b"\x1b[4;58;2;10;20;30mA\x1b[58;5;196mB\x1b[59mC");
+        let defaults = h.terminal.default_colors();
+        let underline = |col: usize| {
+            let cell = &h.terminal.term().grid()[Line(0)][Column(col)];
+            assert!(cell.flags.contains(CellFlags::UNDERLINE));
+            cell.underline_color()
+                .map(|color| term_color_to_egui(&color, true, defaults))
+        };
+        assert_eq!(underline(0), Some(egui::Color32::from_rgb(10, 20, 30)));
+        assert_eq!(underline(1), Some(indexed_color_to_egui(196, true)));
+        assert_eq!(underline(2), None);
+    }
+
+    #[test]
+    fn copy_filter_replaces_or_drops_control_and_replacement_characters() {
+        let text = "a\u{1}b\u{FFFD}c\td\u{7f}\nx\u{1b}";
+        assert_eq!(
+            filter_copied_text(text, CopyFilter::Replace, false),
+            "a b c\td\nx"
+        );
+        assert_eq!(
+            filter_copied_text(text, CopyFilter::Drop, false),
+            "abc\td\nx"
+        );
+    }
+
+    #[test]
+    fn copy_filter_only_touches_box_drawing_when_asked() {
+        let text = "\u{250c}\u{2500}\u{2510} ok \u{2588}\n\u{2502}x\u{2502}";
+        assert_eq!(filter_copied_text(text, CopyFilter::Drop, false), text);
+        assert_eq!(
+            filter_copied_text(text, CopyFilter::Replace, true),
+            "    ok\n x"
+        );
+        assert_eq!(filter_copied_text(text, CopyFilter::Drop, true), " ok\nx");
+    }
+
+    /// Plain text of the selection from `start` to `end` (inclusive cells).
+    fn copy(h: &Harness, start: (usize, usize), end: (usize, usize), max_bytes: usize) -> String {
+        let mut selection = TerminalSelectionState::default();
+        selection.start(start.0, start.1);
+        selection.update(end.0, end.1);
+        selected_text_for_copy(&h.terminal, &selection, CopyFilter::Off, false, max_bytes)
+            .map(|(text, _)| text)
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn copy_keeps_spaces_past_the_tab_stop() {
+        let mut h = Harness::new(2, 20);
+        h.feed(b"a\t  b\r\n\tc");
+        assert_eq!(copy(&h, (0, 0), (1, 19), 1024), "a\t  b\n\tc");
+
+        h.terminal.set_tab_width(4);
+        h.feed(b"\r\nx\t y");
+        assert_eq!(copy(&h, (2, 0), (2, 19), 1024), "x\t y");
+    }
+
+    #[test]
+    fn wide_characters_copy_once() {
+        let mut h = Harness::new(3, 6);
+        h.feed("a中b\r\nabcde中".as_bytes());
+        // Ending the selection on the spacer half still takes the character.
+        assert_eq!(copy(&h, (0, 0), (0, 2), 1024), "a中");
+        assert_eq!(copy(&h, (0, 0), (0, 5), 1024), "a中b");
+        // At the row end a wide character wraps, leaving a spacer behind.
+        assert_eq!(copy(&h, (1, 0), (2, 5), 1024), "abcde\n中");
+    }
+
+    #[test]
+    fn osc_10_11_12_set_the_default_colors() {
+        let mut h = Harness::new(2, 10);
+        h.feed(b"\x1b]10;#102030;rgb:ff/80/00\x07\x1b]12;#aabbcc\x1b\\");
+        let defaults = h.terminal.default_colors();
+        assert_eq!(defaults.fg, egui::Color32::from_rgb(0x10, 0x20, 0x30));
+        assert_eq!(defaults.bg, egui::Color32::from_rgb(0xff, 0x80, 0x00));
+        assert_eq!(h.terminal.cursor_color(), Some([0xaa, 0xbb, 0xcc]));
+
+        h.take_input();
+        h.feed(b"\x1b]11;?\x07");
+        assert_eq!(h.take_input(), b"\x1b]11;rgb:ffff/8080/0000\x07");
+    }
+
+    #[test]
+    fn osc_110_111_112_reset_to_the_profile_colors() {
+        let mut h = Harness::new(2, 10);
+        h.terminal
+            .set_theme_colors(Some([1, 2, 3]), Some([4, 5, 6]));
+        h.feed(b"\x1b]10;#101010\x07\x1b]11;#202020\x07\x1b]12;#303030\x07");
+        h.feed(b"\x1b]110\x07\x1b]111\x07\x1b]112\x07");
+        let defaults = h.terminal.default_colors();
+        assert_eq!(defaults.fg, egui::Color32::from_rgb(1, 2, 3));
+        assert_eq!(defaults.bg, egui::Color32::from_rgb(4, 5, 6));
+        assert_eq!(h.terminal.cursor_color(), None);
+        // Without OSC 12 the cursor is the profile foreground, not the built-in one.
+        assert_eq!(
+            named_color_to_egui(&NamedColor::Cursor, true, defaults),
+            egui::Color32::from_rgb(1, 2, 3)
+        );
+        h.take_input();
+        h.feed(b"\x1b]12;?\x07");
+        assert_eq!(h.take_input(), b"\x1b]12;rgb:0101/0202/0303\x07");
+    }
+
+    #[test]
+    fn osc_4_and_104_change_and_restore_palette_entries() {
+        let mut h = Harness::new(2, 10);
+        h.feed(b"\x1b]4;1;#102030;200;rgb:40/50/60\x1b\\");
+        let defaults = h.terminal.default_colors();
+        assert_eq!(
+            named_color_to_egui(&NamedColor::Red, true, defaults),
+            egui::Color32::from_rgb(0x10, 0x20, 0x30)
+        );
+        assert_eq!(
+            defaults.indexed(200),
+            egui::Color32::from_rgb(0x40, 0x50, 0x60)
+        );
+
+        h.feed(b"\x1b]104;1\x07");
+        let defaults = h.terminal.default_colors();
+        assert_eq!(defaults.indexed(1), indexed_color_to_egui(1, true));
+        assert_eq!(
+            defaults.indexed(200),
+            egui::Color32::from_rgb(0x40, 0x50, 0x60)
+        );
+        h.feed(b"\x1b]104\x07");
+        let defaults = h.terminal.default_colors();
+        assert_eq!(defaults.indexed(200), indexed_color_to_egui(200, true));
+    }
+
+    #[test]
+    fn a_color_query_is_answered_before_a_later_set_in_the_same_chunk() {
+        let mut h = Harness::new(2, 10);
+        h.take_input();
+        h.feed(b"\x1b]4;1;?\x07\x1b]4;1;#102030\x07\x1b]4;1;?\x1b\\");
+        assert_eq!(
+            h.take_input(),
+            b"\x1b]4;1;rgb:cccc/0000/0000\x07\x1b]4;1;rgb:1010/2020/3030\x1b\\"
+        );
+    }
+
+    #[test]
+    fn output_splits_after_osc_queries_only() {
+        let parts: Vec<&[u8]> =
+            split_after_osc_queries(b"a?b\x1b]11;?\x07c\x1b]4;1;?\x1b\\").collect();
+        assert_eq!(parts, [&b"a?b\x1b]11;?\x07"[..], b"c\x1b]4;1;?\x1b\\"]);
+        assert_eq!(split_after_osc_queries(b"").count(), 0);
+    }
+
+    #[test]
+    fn narrowing_then_widening_restores_wrapped_lines() {
+        let mut h = Harness::new(4, 20);
+        h.feed(b"0123456789abcdef\r\nshort");
+        h.terminal.resize(4, 10);
+        let history = h.terminal.term().grid().history_size() as i32;
+        assert_eq!(h.row(-history), "0123456789");
+        assert_eq!(h.row(1 - history), "abcdef");
+        h.terminal.resize(4, 20);
+        let history = h.terminal.term().grid().history_size() as i32;
+        let rows: Vec<String> = (-history..2).map(|line| h.row(line)).collect();
+        assert_eq!(rows.join("\n").trim_start(), "0123456789abcdef\nshort");
+    }
+
+    #[test]
+    fn decscusr_sets_the_cursor_style_and_0_restores_the_default() {
+        let mut h = Harness::new(2, 10);
+        h.terminal
+            .set_default_cursor_style(CursorShape::Underline, true);
+        let style = |shape, blinking| ansi::CursorStyle { shape, blinking };
+        for (sequence, expected) in [
+            (&b"\x1b[1 q"[..], style(ansi::CursorShape::Block, true)),
+            (b"\x1b[2 q", style(ansi::CursorShape::Block, false)),
+            (b"\x1b[3 q", style(ansi::CursorShape::Underline, true)),
+            (b"\x1b[4 q", style(ansi::CursorShape::Underline, false)),
+            (b"\x1b[5 q", style(ansi::CursorShape::Beam, true)),
+            (b"\x1b[6 q", style(ansi::CursorShape::Beam, false)),
+            (b"\x1b[0 q", style(ansi::CursorShape::Underline, true)),
+        ] {
+            h.feed(sequence);
+            assert_eq!(h.terminal.term().cursor_style(), expected);
+        }
+    }
+
+    #[test]
+    fn clicks_map_to_the_row_drawn_under_them_at_fractional_scroll_offsets() {
+        for pixels_per_point in [1.0, 1.25] {
+            let pitch = align_to_pixels_ceil(17.3, pixels_per_point);
+            for step in 0..200 {
+                // Wheel deltas leave the content origin between pixels.
+                let scroll = step as f32 * 7.37;
+                let first_row = (scroll / pitch).floor() as usize;
+                let rows = RowLayout::new(100.0 - scroll, first_row, pitch, pixels_per_point);
+                for row in first_row..first_row + 30 {
+                    let (top, bottom) = (rows.row_y(row), rows.row_y(row + 1));
+                    assert_eq!(rows.row_at(top + 0.05), row);
+                    assert_eq!(rows.row_at(bottom - 0.05), row, "scroll {scroll}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn pastes_at_a_password_prompt_stay_out_of_the_input_log() {
+        let mut h = Harness::new(2, 30);
+        h.terminal.set_input_logging(InputLog::Raw, true);
+        let logged = |h: &Harness| {
+            h.terminal
+                .vt_lines
+                .iter()
+                .filter_map(|entry| match entry {
+                    VtLogEntry::Input(s) => Some(s.clone()),
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+        };
+        h.feed(b"$ ");
+        h.terminal
+            .paste_text("ls", PasteLineEnding::Cr, false, false, false);
+        assert_eq!(logged(&h), ["ls"]);
+
+        h.feed(b"\r\nPassword: ");
+        h.terminal
+            .paste_text("hunter2", PasteLineEnding::Cr, false, false, true);
+        assert_eq!(h.take_input(), b"lshunter2\r");
+        assert_eq!(logged(&h), ["ls"]);
+    }
+
+    #[test]
+    fn size_report_and_resize_requests() {
+        let mut h = Harness::new(5, 20);
+        h.feed(b"\x1b[18t");
+        assert_eq!(h.take_input(), b"\x1b[8;5;20t");
+
+        assert_eq!(h.feed(b"\x1b[8;30;100t").resize_request, Some((30, 100)));
+        // 0 or an empty dimension keeps the current one.
+        assert_eq!(h.feed(b"\x1b[8;0;100t").resize_request, Some((5, 100)));
+        assert_eq!(h.feed(b"\x1b[8;30;t").resize_request, Some((30, 20)));
+        assert_eq!(h.feed(b"\x1b[8;1;2;3t").resize_request, None);
+
+        // Split anywhere, including inside the prefix.
+        let request = b"\x1b[8;30;100t";
+        for split in 1..request.len() {
+            assert_eq!(h.feed(&request[..split]).resize_request, None);
+            assert_eq!(h.feed(&request[split..]).resize_request, Some((30, 100)));
+        }
+
+        // A run of parameters with no end is not carried forever.
+        h.feed(b"\x1b[8;");
+        for _ in 0..10 {
+            h.feed(b"1234567890;");
+        }
+        assert!(h.terminal.resize_request_carry.len() <= RESIZE_REQUEST_MAX_PARAMS + 4);
+        assert_eq!(h.feed(b"t").resize_request, None);
+    }
+
+    #[test]
+    fn copy_stops_at_the_byte_limit() {
+        let mut h = Harness::new(3, 10);
+        h.feed(b"abc def\r\ngh\xc3\xa9\r\nxyz");
+        let copy = |max_bytes| {
+            let mut selection = TerminalSelectionState::default();
+            selection.start(0, 0);
+            selection.update(2, 9);
+            selected_text_for_copy(&h.terminal, &selection, CopyFilter::Off, false, max_bytes)
+        };
+        // Trailing blanks are trimmed, so they do not count toward the limit.
+        let whole = "abc def\ngh\u{e9}\nxyz";
+        assert_eq!(copy(whole.len()), Some((whole.to_string(), false)));
+        assert_eq!(
+            copy(whole.len() - 1),
+            Some(("abc def\ngh\u{e9}\nxy".into(), true))
+        );
+        // A character that does not fit whole is left out.
+        assert_eq!(copy(11), Some(("abc def\ngh".into(), true)));
+        // Blanks before the cut are dropped with it.
+        assert_eq!(copy(5), Some(("abc d".into(), true)));
+        assert_eq!(copy(4), Some(("abc".into(), true)));
+    }
+
+    #[test]
+    fn resizes_interleaved_with_output_keep_the_grid_and_pty_in_step() {
+        let mut h = Harness::new(24, 80);
+        // Deterministic xorshift, so a failure reproduces.
+        let mut seed = 0x2545_f491_u32;
+        let mut next = |bound: u32| {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed % bound
+        };
+        let grid_size = |h: &Harness| (h.terminal.rows() as u16, h.terminal.cols() as u16);
+        let (mut applied, mut deferred) = (0, 0);
+        for _ in 0..200 {
+            // Output formatted for an 80-column screen, whatever the grid is now,
+            // plus wide text that wraps.
+            let mut chunk = Vec::new();
+            for _ in 0..20 {
+                let (row, col) = (next(24) + 1, next(80) + 1);
+                chunk.extend_from_slice(format!("\x1b[{row};{col}Hline {row}\x1b[K").as_bytes());
+            }
+            chunk.extend_from_slice("\r\n漢字かな".repeat(next(8) as usize).as_bytes());
+            h.feed(&chunk);
+
+            let (rows, cols) = (next(60) as u16 + 1, next(200) as u16 + 8);
+            let before = grid_size(&h);
+            if next(4) == 0 {
+                h.terminal.resize(rows, cols);
+                assert_eq!(grid_size(&h), clamp_grid_size(rows, cols));
+            } else if let Some(wait) = h.terminal.request_resize(rows, cols) {
+                assert!(wait <= RESIZE_INTERVAL);
+                assert_eq!(grid_size(&h), before);
+                deferred += 1;
+            } else {
+                assert_eq!(grid_size(&h), clamp_grid_size(rows, cols));
+                applied += 1;
+            }
+            if let Some(size) = *h.pty_size.lock().unwrap() {
+                assert_eq!((size.rows, size.cols), grid_size(&h));
+            }
+        }
+        assert!(
+            applied >= 1 && deferred >= 1,
+            "{applied} applied, {deferred} deferred"
+        );
+
+        // The last size of a drag lands once the interval is up.
+        std::thread::sleep(RESIZE_INTERVAL);
+        assert_eq!(h.terminal.request_resize(12, 40), None);
+        assert_eq!(grid_size(&h), (12, 40));
+
+        // ConPTY's repaint after the resize leaves exactly the repainted screen.
+        let mut repaint = b"\x1b[H\x1b[2J".to_vec();
+        for row in 1..=12 {
+            repaint.extend_from_slice(format!("\x1b[{row};1Hrow {row}\x1b[K").as_bytes());
+        }
+        h.feed(&repaint);
+        for row in 0..12 {
+            assert_eq!(h.row(row), format!("row {}", row + 1));
+        }
+    }
+
+    #[test]
+    fn erases_count_only_on_the_screen_they_hit() {
+        let mut h = Harness::new(4, 20);
+        let erased = |result: ProcessInputResult| (result.screen_erased, result.scrollback_erased);
+        // Erased on the alternate screen, back on the main one by the chunk's end.
+        assert_eq!(
+            erased(h.feed(b"\x1b[?1049h\x1b[2J\x1b[3Jvim\x1b[?1049l")),
+            (false, false)
+        );
+        // Erased on the main screen before switching away.
+        assert_eq!(erased(h.feed(b"\x1b[2J\x1b[3J\x1b[?1049h")), (true, true));
+        assert_eq!(erased(h.feed(b"\x1b[2J\x1b[?1049l\x1b[3J")), (false, true));
+        // The switch and the erase split across chunks.
+        assert_eq!(erased(h.feed(b"\x1b[?10")), (false, false));
+        assert_eq!(erased(h.feed(b"49h\x1b[2")), (false, false));
+        assert_eq!(erased(h.feed(b"J\x1b[?1049l\x1b[")), (false, false));
+        assert_eq!(erased(h.feed(b"2J")), (true, false));
+        // 1049 combined with other private modes, also split across chunks.
+        assert_eq!(erased(h.feed(b"\x1b[?1049;25h\x1b[2J")), (false, false));
+        assert!(h.terminal.is_alt_screen());
+        assert_eq!(erased(h.feed(b"\x1b[?25;10")), (false, false));
+        assert_eq!(erased(h.feed(b"49l\x1b[3J")), (false, true));
+        assert!(!h.terminal.is_alt_screen());
+    }
+
+    #[test]
+    fn cwd_changes_reach_the_callback_once_each() {
+        let mut h = Harness::new(2, 20);
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        h.terminal
+            .set_on_cwd_change(move |dir| sink.lock().unwrap().push(dir.to_string()));
+        h.feed(b"\x1b]633;CWD=/home\x07$ \x1b]633;CWD=/home\x07");
+        h.feed(b"\x1b]633;CWD=/t");
+        h.feed(b"mp\x1b\\$ ");
+        assert_eq!(*seen.lock().unwrap(), ["/home", "/tmp"]);
+    }
+
+    #[test]
+    fn html_copy_keeps_tabs_and_trims_only_default_blanks() {
+        let mut h = Harness::new(2, 20);
+        h.feed(b"a\tb \x1b[4mx \x1b[m  \r\n&<");
+        let mut selection = TerminalSelectionState::default();
+        selection.start(0, 0);
+        selection.update(1, 19);
+        let html = selected_html_for_copy(&h.terminal, &selection, 1024).unwrap();
+        let body = &html[html.find('>').unwrap() + 1..];
+        assert_eq!(
+            body,
+            "a\tb <span style=\"text-decoration:underline;\">x </span>\n&amp;&lt;</pre>"
+        );
+    }
+}
@@ -1,9 +1,23 @@
 use egui::{Align, Color32, FontId, Layout, RichText, Sense, Stroke};
 
+use crate::appconfig::Profile;
+
 pub struct TopBarInput<'a> {
     pub terminal_exited: bool,
     pub terminal_connecting: bool,
+    pub terminal_read_only: bool,
+    pub copy_mode: bool,
+    /// Shell working directory and whether the shell reported it (OSC 633).
+    pub current_dir: Option<(&'a str, bool)>,
     pub reconnect_requested: &'a mut bool,
+    /// Saved profiles offered in the switcher menu (hidden when empty), and
+    /// the one the current session runs.
+    pub profiles: &'a [Profile],
+    pub active_profile: Option<&'a str>,
+    /// Offer a button that brings the hidden left panel back.
+    pub left_panel_hidden: bool,
+    /// Seconds until the window closes itself after the shell exited.
+    pub close_countdown_secs: Option<u64>,
 }
 
 #[derive(Default, Clone, Copy)]
@@ -12,6 +26,13 @@ pub struct TopBarAction {
     pub request_toggle_maximize: bool,
     pub request_close: bool,
     pub request_drag_window: bool,
+    pub request_toggle_read_only: bool,
+    pub request_exit_copy_mode: bool,
+    pub request_open_current_dir: bool,
+    pub request_new_window: bool,
+    /// Index into `profiles` to respawn the shell with.
+    pub request_switch_profile: Option<usize>,
+    pub request_show_left_panel: bool,
 }
 
 pub fn render(ui: &mut egui::Ui, input: TopBarInput<'_>, bar_color: Color32) -> TopBarAction {
@@ -45,6 +66,126 @@ pub fn render(ui: &mut egui::Ui, input: TopBarInput<'_>, bar_color: Color32) ->
 
     ui.allocate_ui_at_rect(left_rect, |ui| {
         ui.with_layout(Layout::left_to_right(Align::Center), |ui| {
+            if input.left_panel_hidden {
+                ui.add_space(8.0);
+                let show_panel = ui.add(
+                    egui::Button::new(
+                        RichText::new("☰")
+                            .monospace()
+                            .color(Color32::from_gray(190))
+                            .size(12.0),
+                    )
+                    .frame(false),
+                );
+                if show_panel
+                    .on_hover_text("Show the side panel (Ctrl+Shift+B)")
+                    .clicked()
+                {
+                    action.request_show_left_panel = true;
+                }
+            }
+            if !input.profiles.is_empty() {
+                ui.add_space(8.0);
+                let active = input.active_profile.unwrap_or("Profile");
+                let label = RichText::new(format!("▾ {}", active))
+                    .monospace()
+                    .color(Color32::from_gray(190))
+                    .size(12.0);
+                ui.menu_button(label, |ui| {
+                    for (idx, profile) in input.profiles.iter().enumerate() {
+                        let active = input.active_profile == Some(profile.name.as_str());
+                        if ui.radio(active, &profile.name).clicked() {
+                            action.request_switch_profile = Some(idx);
+                            ui.close_menu();
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Restart the shell with another profile");
+            }
+            if let Some((dir, reported)) = input.current_dir {
+                ui.add_space(8.0);
+                let exists = std::path::Path::new(dir).is_dir();
+                let folder = ui.add_enabled(
+                    exists,
+                    egui::Button::new(
+                        RichText::new("📂")
+                            .monospace()
+                            .color(Color32::from_gray(190))
+                            .size(12.0),
+                    )
+                    .frame(false),
+                );
+                let mut hover = if exists {
+                    format!("Open {} in the file manager", dir)
+                } else {
+                    format!("{} no longer exists", dir)
+                };
+                if !reported {
+                    hover.push_str("\n(startup directory; the shell has not reported its CWD)");
+                }
+                if folder
+                    .on_hover_text(&hover)
+                    .on_disabled_hover_text(&hover)
+                    .clicked()
+                {
+                    action.request_open_current_dir = true;
+                }
+                let new_window = ui.add(
+                    egui::Button::new(
+                        RichText::new("+")
+                            .monospace()
+                            .color(Color32::from_gray(190))
+                            .size(12.0),
+                    )
+                    .frame(false),
+                );
+                if new_window
+                    .on_hover_text("New window in this directory (Ctrl+Shift+N)")
+                    .clicked()
+                {
+                    action.request_new_window = true;
+                }
+            }
+            if input.terminal_read_only {
+                ui.add_space(8.0);
+                let lock = ui.add(
+                    egui::Button::new(
+                        RichText::new("🔒 Read-only")
+                            .monospace()
+                            .color(Color32::from_rgb(230, 180, 80))
+                            .size(12.0),
+                    )
+                    .frame(false),
+                );
+                if lock
+                    .on_hover_text("Click or Ctrl+Shift+R to unlock")
+                    .clicked()
+                {
+                    action.request_toggle_read_only = true;
+                }
+            }
+            if input.copy_mode {
+                ui.add_space(8.0);
+                let copy = ui.add(
+                    egui::Button::new(
+                        RichText::new("⌨ Copy mode")
+                            .monospace()
+                            .color(Color32::from_rgb(230, 180, 80))
+                            .size(12.0),
+                    )
+                    .frame(false),
+                );
+                if copy
+                    .on_hover_text(
+                        "Arrows/hjkl move, Shift+arrows or v select, Enter/y copy.\n\
+                         Click, Esc or Ctrl+Shift+M to leave",
+                    )
+                    .clicked()
+                {
+                    action.request_exit_copy_mode = true;
+                }
+            }
             if input.terminal_exited {
                 ui.add_space(8.0);
                 ui.label(
@@ -62,6 +203,15 @@ pub fn render(ui: &mut egui::Ui, input: TopBarInput<'_>, bar_color: Color32) ->
                 if reconnect.clicked() {
                     *input.reconnect_requested = true;
                 }
+                if let Some(secs) = input.close_countdown_secs {
+                    ui.add_space(8.0);
+                    ui.label(
+                        RichText::new(format!("Closing in {}s (any key keeps it open)", secs))
+                            .monospace()
+                            .color(Color32::from_rgb(230, 180, 80))
+                            .size(12.0),
+                    );
+                }
                 if input.terminal_connecting {
                     ui.add_space(8.0);
                     ui.label(